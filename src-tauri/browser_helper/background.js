@@ -0,0 +1,9 @@
+const HOST = "__HOST__";
+const api = globalThis.browser ?? chrome;
+api.runtime.onMessage.addListener((msg, _sender, sendResponse) => {
+  if (!msg || msg.type !== "family" || !Array.isArray(msg.members)) return false;
+  Promise.resolve(api.runtime.sendNativeMessage(HOST, { type: "family", members: msg.members }))
+    .then((res) => sendResponse(res))
+    .catch((e) => sendResponse({ ok: false, error: String(e) }));
+  return true;
+});
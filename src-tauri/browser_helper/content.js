@@ -13,6 +13,52 @@
     });
     return Array.from(ids);
   }
+  // учасники з ніком і роллю — для передачі в застосунок через native messaging
+  function collectMembers() {
+    const out = new Map();
+    document.querySelectorAll("[data-miniprofile]").forEach((el) => {
+      const n = Number(el.getAttribute("data-miniprofile"));
+      if (!Number.isFinite(n) || n <= 0) return;
+      const id = (OFFSET + BigInt(n)).toString();
+      if (out.has(id)) return;
+      const card = el.closest("li, tr, [class*='member'], [class*='Member']") || el.parentElement;
+      const text = ((card && card.textContent) || "").toLowerCase();
+      const role = /organi[sz]er/.test(text) ? "organizer"
+        : /\bchild\b/.test(text) ? "child"
+        : /\badult\b/.test(text) ? "adult"
+        : "unknown";
+      const persona = (el.getAttribute("title") || el.textContent || "").trim();
+      out.set(id, { steamid64: id, persona, role });
+    });
+    return Array.from(out.values());
+  }
+  function ensureSendButton() {
+    let btn = document.getElementById("__sghelper_send_btn");
+    if (btn) return btn;
+    btn = document.createElement("button");
+    btn.id = "__sghelper_send_btn";
+    btn.style.cssText = [
+      "position:fixed","right:18px","bottom:72px","z-index:99999",
+      "border:none","border-radius:14px","padding:12px 16px","font-size:14px",
+      "cursor:pointer","box-shadow:0 6px 18px rgba(0,0,0,.25)",
+      "background:#1a9fff","color:#fff","font-weight:600"
+    ].join(";");
+    document.body.appendChild(btn);
+    return btn;
+  }
+  function renderSend() {
+    const members = collectMembers();
+    const btn = ensureSendButton();
+    btn.style.display = members.length ? "" : "none";
+    btn.textContent = `Send ${members.length} to SteamGameHelper`;
+    btn.onclick = async () => {
+      try {
+        const res = await (globalThis.browser ?? chrome).runtime.sendMessage({ type: "family", members });
+        btn.textContent = res && res.ok ? "Sent!" : `Error: ${(res && res.error) || "no host"}`;
+      } catch (e) { btn.textContent = "Error: app host not installed"; }
+      setTimeout(renderSend, 2000);
+    };
+  }
   function ensureButton() {
     let btn = document.getElementById("__sghelper_copy_ids_btn");
     if (btn) return btn;
@@ -48,4 +94,5 @@
   } else {
     render(); setTimeout(render, 1200);
   }
-})();
\ No newline at end of file
+  setTimeout(renderSend, 1200);
+})();
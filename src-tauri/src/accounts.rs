@@ -0,0 +1,261 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::{self, AccountHint};
+use crate::steamid;
+use crate::vdf::{self, Vdf};
+
+// ====================== Локальні акаунти Steam ======================
+//
+// config/loginusers.vdf — хто логінився на цьому ПК (ключі — SteamID64),
+// userdata/<accountid>/config/localconfig.vdf — локальна статистика акаунта
+// (теки названі 32-бітним account id). Один корінь може прийти двічі через
+// симлінк ~/.steam/steam, тому все зводимо за id.
+
+#[derive(Clone, Debug, Default)]
+pub struct LocalAppStat {
+  pub playtime_minutes: Option<u32>,
+  pub last_played_unix: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LocalConfig {
+  /// friends/PersonaName — нік на момент останнього входу.
+  pub persona: Option<String>,
+  pub apps: HashMap<i64, LocalAppStat>,
+}
+
+fn app_stat(node: &Vdf) -> LocalAppStat {
+  let playtime = node.u64("Playtime")
+    .or_else(|| node.u64("playtime_forever"))
+    .or_else(|| {
+      node.entries().iter()
+        .filter(|(k, _)| k.starts_with("MinutesPlayed"))
+        .find_map(|(_, v)| match v { Vdf::Str(s) => s.trim().parse().ok(), _ => None })
+    });
+  LocalAppStat {
+    playtime_minutes: playtime.map(|m| m.min(u32::MAX as u64) as u32),
+    last_played_unix: node.u64("LastPlayed").filter(|&t| t > 0),
+  }
+}
+
+pub fn parse_localconfig(txt: &str) -> LocalConfig {
+  let root = match vdf::parse(txt) {
+    Ok(v) => v,
+    Err(e) => {
+      log::warn!("localconfig.vdf: {e}");
+      return LocalConfig::default();
+    }
+  };
+  let Some(store) = root.get("UserLocalConfigStore") else { return LocalConfig::default(); };
+
+  let mut apps = HashMap::new();
+  if let Some(list) = store.path(&["Software", "Valve", "Steam", "apps"]) {
+    for (k, v) in list.entries() {
+      let Ok(appid) = k.parse::<i64>() else { continue; };
+      if appid == 0 || !matches!(v, Vdf::Obj(_)) { continue; }
+      apps.insert(appid, app_stat(v));
+    }
+  }
+  let persona = store.path(&["friends"])
+    .and_then(|f| f.str("PersonaName"))
+    .map(|s| s.to_string())
+    .filter(|s| !s.is_empty());
+  LocalConfig { persona, apps }
+}
+
+pub fn read_localconfig(dir: &Path) -> Option<LocalConfig> {
+  let txt = fs::read_to_string(dir.join("config").join("localconfig.vdf")).ok()?;
+  Some(parse_localconfig(&txt))
+}
+
+/// Теки userdata/<accountid> з усіх коренів; за однаковим id перемагає перша.
+pub fn userdata_dirs(roots: &[PathBuf]) -> Vec<(u32, PathBuf)> {
+  let mut seen = HashSet::<u32>::new();
+  let mut out = vec![];
+  for root in roots {
+    let Ok(entries) = fs::read_dir(root.join("userdata")) else { continue; };
+    for e in entries.flatten() {
+      let Some(id) = e.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else { continue; };
+      // userdata/0 — анонімний/офлайн-кеш, не акаунт
+      if id == 0 || !e.path().is_dir() { continue; }
+      if seen.insert(id) {
+        out.push((id, e.path()));
+      }
+    }
+  }
+  out
+}
+
+fn login_users(roots: &[PathBuf]) -> Vec<AccountHint> {
+  let mut out: Vec<AccountHint> = vec![];
+  for root in roots {
+    let Ok(txt) = fs::read_to_string(root.join("config").join("loginusers.vdf")) else { continue; };
+    let parsed = match vdf::parse(&txt) {
+      Ok(v) => v,
+      Err(e) => {
+        log::warn!("loginusers.vdf: {e}");
+        continue;
+      }
+    };
+    let Some(users) = parsed.get("users") else { continue; };
+    for (id, u) in users.entries() {
+      if steamid::parse_steamid64(id).is_err() { continue; }
+      let hint = AccountHint {
+        steamid64: id.clone(),
+        persona: u.str("PersonaName").unwrap_or_default().to_string(),
+        account_name: u.str("AccountName").unwrap_or_default().to_string(),
+        most_recent: u.flag("MostRecent"),
+        timestamp: u.u64("Timestamp").unwrap_or(0),
+        remember_password: u.flag("RememberPassword"),
+        wants_offline_mode: u.flag("WantsOfflineMode"),
+        allow_auto_login: u.flag("AllowAutoLogin"),
+        ..Default::default()
+      };
+      match out.iter_mut().find(|h| h.steamid64 == hint.steamid64) {
+        Some(h) if hint.timestamp > h.timestamp => *h = hint,
+        Some(_) => {}
+        None => out.push(hint),
+      }
+    }
+  }
+  out
+}
+
+/// Акаунти з loginusers.vdf плюс теки userdata без запису там; найсвіжіший — першим.
+pub fn detect(roots: &[PathBuf]) -> Vec<AccountHint> {
+  let mut out = login_users(roots);
+
+  for (acc, dir) in userdata_dirs(roots) {
+    let sid = steamid::from_account_id(acc).to_string();
+    let cfg = read_localconfig(&dir);
+    let idx = match out.iter().position(|h| h.steamid64 == sid) {
+      Some(i) => i,
+      None => {
+        out.push(AccountHint { steamid64: sid, ..Default::default() });
+        out.len() - 1
+      }
+    };
+    let h = &mut out[idx];
+    h.has_userdata = true;
+    if let Some(cfg) = cfg {
+      h.local_apps = cfg.apps.len();
+      if h.persona.is_empty() {
+        h.persona = cfg.persona.unwrap_or_default();
+      }
+    }
+  }
+
+  out.sort_by(|a, b| {
+    b.most_recent.cmp(&a.most_recent)
+      .then(b.timestamp.cmp(&a.timestamp))
+      .then(b.has_userdata.cmp(&a.has_userdata))
+      .then(b.local_apps.cmp(&a.local_apps))
+  });
+  out
+}
+
+// ====================== Розбивка часу по акаунтах ======================
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AppPlaytime {
+  pub appid: i64,
+  pub playtime_minutes: Option<u32>,
+  pub last_played_unix: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AccountPlaytime {
+  pub steamid64: String,
+  pub account_id: u32,
+  pub persona: String,
+  pub total_minutes: u64,
+  pub apps: Vec<AppPlaytime>,
+}
+
+fn playtime_breakdown(roots: &[PathBuf], appids: Option<&HashSet<i64>>) -> Vec<AccountPlaytime> {
+  let hints = detect(roots);
+  let mut out = vec![];
+  for (acc, dir) in userdata_dirs(roots) {
+    let Some(cfg) = read_localconfig(&dir) else { continue; };
+    let steamid64 = steamid::from_account_id(acc).to_string();
+    let persona = hints.iter()
+      .find(|h| h.steamid64 == steamid64)
+      .map(|h| h.persona.clone())
+      .or(cfg.persona)
+      .unwrap_or_default();
+
+    let mut apps: Vec<AppPlaytime> = cfg.apps.into_iter()
+      .filter(|(id, st)| appids.map_or(true, |f| f.contains(id)) && (st.playtime_minutes.is_some() || st.last_played_unix.is_some()))
+      .map(|(appid, st)| AppPlaytime { appid, playtime_minutes: st.playtime_minutes, last_played_unix: st.last_played_unix })
+      .collect();
+    apps.sort_by(|a, b| b.playtime_minutes.cmp(&a.playtime_minutes).then(a.appid.cmp(&b.appid)));
+    let total_minutes = apps.iter().filter_map(|a| a.playtime_minutes).map(u64::from).sum();
+    out.push(AccountPlaytime { steamid64, account_id: acc, persona, total_minutes, apps });
+  }
+  out.sort_by_key(|a| std::cmp::Reverse(a.total_minutes));
+  out
+}
+
+/// Локальний час у кожного акаунта цього ПК окремо; `appids` — лише ці ігри.
+#[tauri::command]
+pub async fn local_playtime_breakdown(appids: Option<Vec<i64>>) -> Result<Vec<AccountPlaytime>, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let filter = appids.map(|v| v.into_iter().collect::<HashSet<i64>>());
+    playtime_breakdown(&commands::roots_from_detect_roots(), filter.as_ref())
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write(path: PathBuf, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+  }
+
+  fn localconfig(persona: &str, apps: &[u32]) -> String {
+    let apps: String = apps.iter().map(|a| format!("\"{a}\" {{ \"Playtime\" \"5\" }}\n")).collect();
+    format!(
+      "\"UserLocalConfigStore\" {{ \"friends\" {{ \"PersonaName\" \"{persona}\" }} \
+       \"Software\" {{ \"Valve\" {{ \"Steam\" {{ \"apps\" {{ {apps} }} }} }} }} }}"
+    )
+  }
+
+  #[test]
+  fn detect_merges_roots_and_ranks_most_recent_first() {
+    let base = std::env::temp_dir().join(format!("sgh_accounts_{}", std::process::id()));
+    let (root, other) = (base.join("steam"), base.join("steam2"));
+    write(root.join("config/loginusers.vdf"), r#""users"
+{
+  "76561197960287930" { "AccountName" "alice" "PersonaName" "Alice" "MostRecent" "0" "Timestamp" "1700000000" }
+  "76561198000000000" { "AccountName" "bob" "PersonaName" "Bob" "MostRecent" "1" "Timestamp" "1600000000" "WantsOfflineMode" "1" }
+  "12345" { "PersonaName" "not a steamid" }
+}"#);
+    // той самий акаунт у другій інсталяції, свіжіший запис перемагає
+    write(other.join("config/loginusers.vdf"), r#""users" { "76561197960287930" { "PersonaName" "Alice2" "Timestamp" "1800000000" } }"#);
+    write(root.join("userdata/22202/config/localconfig.vdf"), &localconfig("ignored", &[10]));
+    write(root.join("userdata/5/config/localconfig.vdf"), &localconfig("Ghost", &[10, 20]));
+    write(root.join("userdata/0/config/localconfig.vdf"), &localconfig("anon", &[10]));
+
+    // корінь двічі — як через симлінк ~/.steam/steam
+    let hints = detect(&[root.clone(), root.clone(), other]);
+    let ids: Vec<&str> = hints.iter().map(|h| h.steamid64.as_str()).collect();
+    assert_eq!(ids, ["76561198000000000", "76561197960287930", "76561197960265733"]);
+
+    let bob = &hints[0];
+    assert!(bob.most_recent && bob.wants_offline_mode && !bob.has_userdata);
+    let alice = &hints[1];
+    assert_eq!((alice.persona.as_str(), alice.timestamp), ("Alice2", 1_800_000_000));
+    assert!(alice.has_userdata);
+    assert_eq!(alice.local_apps, 1);
+    let ghost = &hints[2];
+    assert_eq!((ghost.persona.as_str(), ghost.local_apps, ghost.timestamp), ("Ghost", 2, 0));
+    let _ = fs::remove_dir_all(&base);
+  }
+}
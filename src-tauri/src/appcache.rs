@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// ====================== Бінарний кеш Steam (appcache/) ======================
+//
+// appinfo.vdf і packageinfo.vdf — послідовність записів із бінарним KeyValues.
+// Формат недокументований, тож читаємо обережно: будь-яка невідповідність
+// обриває розбір, і повертаємо те, що встигли прочитати.
+//
+// appinfo.vdf: magic, universe, [v29+: i64 зсув таблиці рядків], далі записи
+//   appid u32 (0 = кінець), size u32, решта заголовка + KV у межах size.
+// packageinfo.vdf: magic, universe, далі записи
+//   packageid u32 (0xFFFFFFFF = кінець), sha1[20], change u32, [v28: token u64], KV.
+
+const APPINFO_V27: u32 = 0x0756_4427;
+const APPINFO_V28: u32 = 0x0756_4428;
+const APPINFO_V29: u32 = 0x0756_4429;
+const PACKAGEINFO_V27: u32 = 0x0656_5527;
+const PACKAGEINFO_V28: u32 = 0x0656_5528;
+
+// типи вузлів бінарного KV
+const KV_OBJ: u8 = 0x00;
+const KV_STR: u8 = 0x01;
+const KV_INT32: u8 = 0x02;
+const KV_FLOAT32: u8 = 0x03;
+const KV_PTR: u8 = 0x04;
+const KV_WSTR: u8 = 0x05;
+const KV_COLOR: u8 = 0x06;
+const KV_UINT64: u8 = 0x07;
+const KV_END: u8 = 0x08;
+const KV_INT64: u8 = 0x0A;
+const KV_END_ALT: u8 = 0x0B;
+
+#[derive(Clone, Debug)]
+pub enum Bkv {
+  Str(String),
+  Int(i64),
+  Obj(Vec<(String, Bkv)>),
+}
+
+impl Bkv {
+  pub fn get(&self, key: &str) -> Option<&Bkv> {
+    match self {
+      Bkv::Obj(items) => items.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v),
+      _ => None,
+    }
+  }
+
+  pub fn path(&self, keys: &[&str]) -> Option<&Bkv> {
+    keys.iter().try_fold(self, |node, k| node.get(k))
+  }
+
+  pub fn str(&self, key: &str) -> Option<&str> {
+    match self.get(key)? {
+      Bkv::Str(s) => Some(s.as_str()),
+      _ => None,
+    }
+  }
+
+  pub fn entries(&self) -> &[(String, Bkv)] {
+    match self {
+      Bkv::Obj(items) => items,
+      _ => &[],
+    }
+  }
+}
+
+struct Reader<'a> {
+  b: &'a [u8],
+  i: usize,
+  // v29: імена ключів — індекси в таблицю рядків
+  strings: Option<&'a [String]>,
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+    let end = self.i.checked_add(n)?;
+    let s = self.b.get(self.i..end)?;
+    self.i = end;
+    Some(s)
+  }
+
+  fn u8(&mut self) -> Option<u8> {
+    self.take(1).map(|s| s[0])
+  }
+
+  fn u32(&mut self) -> Option<u32> {
+    self.take(4).map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+  }
+
+  fn u64(&mut self) -> Option<u64> {
+    let s = self.take(8)?;
+    let mut a = [0u8; 8];
+    a.copy_from_slice(s);
+    Some(u64::from_le_bytes(a))
+  }
+
+  fn cstr(&mut self) -> Option<String> {
+    let rest = self.b.get(self.i..)?;
+    let end = rest.iter().position(|&c| c == 0)?;
+    let s = String::from_utf8_lossy(&rest[..end]).into_owned();
+    self.i += end + 1;
+    Some(s)
+  }
+
+  fn wstr(&mut self) -> Option<String> {
+    let mut units = vec![];
+    loop {
+      let s = self.take(2)?;
+      let u = u16::from_le_bytes([s[0], s[1]]);
+      if u == 0 { break; }
+      units.push(u);
+    }
+    Some(String::from_utf16_lossy(&units))
+  }
+
+  fn key(&mut self) -> Option<String> {
+    match self.strings {
+      Some(table) => table.get(self.u32()? as usize).cloned(),
+      None => self.cstr(),
+    }
+  }
+
+  fn obj(&mut self, depth: usize) -> Option<Vec<(String, Bkv)>> {
+    // захист від зациклення на пошкодженому файлі
+    if depth > 64 { return None; }
+    let mut items = vec![];
+    loop {
+      let t = self.u8()?;
+      if t == KV_END || t == KV_END_ALT { return Some(items); }
+      let key = self.key()?;
+      let val = match t {
+        KV_OBJ => Bkv::Obj(self.obj(depth + 1)?),
+        KV_STR => Bkv::Str(self.cstr()?),
+        KV_WSTR => Bkv::Str(self.wstr()?),
+        KV_INT32 => Bkv::Int(self.u32()? as i32 as i64),
+        KV_FLOAT32 | KV_PTR | KV_COLOR => Bkv::Int(self.u32()? as i64),
+        KV_UINT64 | KV_INT64 => Bkv::Int(self.u64()? as i64),
+        _ => return None,
+      };
+      items.push((key, val));
+    }
+  }
+}
+
+fn string_table(b: &[u8], offset: usize) -> Option<Vec<String>> {
+  let mut r = Reader { b, i: offset, strings: None };
+  let n = r.u32()? as usize;
+  let mut out = Vec::with_capacity(n.min(1 << 20));
+  for _ in 0..n {
+    out.push(r.cstr()?);
+  }
+  Some(out)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AppInfo {
+  pub name: String,
+  /// common/type у нижньому регістрі: game, dlc, tool, application, music…
+  pub kind: String,
+}
+
+/// Імена й типи з appcache/appinfo.vdf; `wanted` — лише ці appid (решта записів пропускається за size).
+pub fn read_appinfo(path: &Path, wanted: &HashSet<i64>) -> HashMap<i64, AppInfo> {
+  let Ok(b) = fs::read(path) else { return HashMap::new(); };
+  parse_appinfo(&b, wanted)
+}
+
+pub fn parse_appinfo(b: &[u8], wanted: &HashSet<i64>) -> HashMap<i64, AppInfo> {
+  let mut out = HashMap::new();
+  let mut r = Reader { b, i: 0, strings: None };
+  let (Some(magic), Some(_universe)) = (r.u32(), r.u32()) else { return out; };
+
+  let table;
+  // заголовок запису після size: state, last_updated, token, sha1, change, [sha1 бінарного KV]
+  let header_len = match magic {
+    APPINFO_V27 => 4 + 4 + 8 + 20 + 4,
+    APPINFO_V28 => 4 + 4 + 8 + 20 + 4 + 20,
+    APPINFO_V29 => {
+      let Some(off) = r.u64() else { return out; };
+      let Some(t) = string_table(b, off as usize) else { return out; };
+      table = t;
+      r.strings = Some(&table);
+      4 + 4 + 8 + 20 + 4 + 20
+    }
+    _ => {
+      log::warn!("appinfo.vdf: unknown format {magic:#x}");
+      return out;
+    }
+  };
+
+  while out.len() < wanted.len() {
+    let Some(appid) = r.u32() else { break; };
+    if appid == 0 { break; }
+    let Some(size) = r.u32() else { break; };
+    let next = r.i + size as usize;
+    if next > b.len() { break; }
+
+    if wanted.contains(&(appid as i64)) {
+      r.i += header_len;
+      if let Some(root) = r.obj(0).map(Bkv::Obj) {
+        let common = root.path(&["appinfo", "common"]);
+        let name = common.and_then(|c| c.str("name")).unwrap_or_default().to_string();
+        let kind = common.and_then(|c| c.str("type")).unwrap_or_default().to_ascii_lowercase();
+        if !name.is_empty() {
+          out.insert(appid as i64, AppInfo { name, kind });
+        }
+      }
+    }
+    r.i = next;
+  }
+  out
+}
+
+/// appid усіх пакетів `packages` з appcache/packageinfo.vdf.
+pub fn read_package_apps(path: &Path, packages: &HashSet<u32>) -> HashSet<i64> {
+  let Ok(b) = fs::read(path) else { return HashSet::new(); };
+  parse_package_apps(&b, packages)
+}
+
+pub fn parse_package_apps(b: &[u8], packages: &HashSet<u32>) -> HashSet<i64> {
+  let mut out = HashSet::new();
+  let mut r = Reader { b, i: 0, strings: None };
+  let (Some(magic), Some(_universe)) = (r.u32(), r.u32()) else { return out; };
+  let has_token = match magic {
+    PACKAGEINFO_V27 => false,
+    PACKAGEINFO_V28 => true,
+    _ => {
+      log::warn!("packageinfo.vdf: unknown format {magic:#x}");
+      return out;
+    }
+  };
+
+  while let Some(pkg) = r.u32() {
+    if pkg == u32::MAX { break; }
+    if r.take(20 + 4).is_none() { break; }
+    if has_token && r.u64().is_none() { break; }
+    // розміру запису немає — KV треба прочитати повністю навіть для чужих пакетів
+    let Some(root) = r.obj(0).map(Bkv::Obj) else { break; };
+    if !packages.contains(&pkg) { continue; }
+    let Some((_, body)) = root.entries().first() else { continue; };
+    for (_, v) in body.get("appids").map(|a| a.entries()).unwrap_or_default() {
+      if let Bkv::Int(id) = v {
+        if *id > 0 { out.insert(*id); }
+      }
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  /// Вузол бінарного KV для фікстур.
+  pub(crate) enum Node {
+    Obj(String, Vec<Node>),
+    Str(String, String),
+    Int(String, u32),
+  }
+
+  fn obj(k: impl ToString, children: Vec<Node>) -> Node {
+    Node::Obj(k.to_string(), children)
+  }
+
+  fn int(k: impl ToString, v: u32) -> Node {
+    Node::Int(k.to_string(), v)
+  }
+
+  /// v29 (`table: Some`) пише замість імені ключа його індекс у таблиці рядків.
+  fn key(k: &str, table: &mut Option<Vec<String>>, out: &mut Vec<u8>) {
+    match table {
+      Some(t) => {
+        let i = t.iter().position(|s| s == k).unwrap_or_else(|| {
+          t.push(k.to_string());
+          t.len() - 1
+        });
+        out.extend((i as u32).to_le_bytes());
+      }
+      None => {
+        out.extend(k.as_bytes());
+        out.push(0);
+      }
+    }
+  }
+
+  fn enc(nodes: &[Node], table: &mut Option<Vec<String>>, out: &mut Vec<u8>) {
+    for n in nodes {
+      match n {
+        Node::Obj(k, children) => {
+          out.push(KV_OBJ);
+          key(k, table, out);
+          enc(children, table, out);
+        }
+        Node::Str(k, v) => {
+          out.push(KV_STR);
+          key(k, table, out);
+          out.extend(v.as_bytes());
+          out.push(0);
+        }
+        Node::Int(k, v) => {
+          out.push(KV_INT32);
+          key(k, table, out);
+          out.extend(v.to_le_bytes());
+        }
+      }
+    }
+    out.push(KV_END);
+  }
+
+  fn app_kv(name: &str, kind: &str) -> Vec<Node> {
+    vec![obj("appinfo", vec![
+      int("appid", 0),
+      obj("common", vec![Node::Str("name".into(), name.into()), Node::Str("type".into(), kind.into())]),
+    ])]
+  }
+
+  /// appinfo.vdf заданої версії з записами (appid, name, type).
+  pub(crate) fn appinfo(magic: u32, apps: &[(u32, &str, &str)]) -> Vec<u8> {
+    let mut table = (magic == APPINFO_V29).then(Vec::new);
+    let mut b = vec![];
+    b.extend(magic.to_le_bytes());
+    b.extend(1u32.to_le_bytes());
+    let table_at = b.len();
+    if table.is_some() {
+      b.extend(0u64.to_le_bytes());
+    }
+    let header = if magic == APPINFO_V27 { 4 + 4 + 8 + 20 + 4 } else { 4 + 4 + 8 + 20 + 4 + 20 };
+    for &(appid, name, kind) in apps {
+      let mut rec = vec![0xAB; header];
+      enc(&app_kv(name, kind), &mut table, &mut rec);
+      b.extend(appid.to_le_bytes());
+      b.extend((rec.len() as u32).to_le_bytes());
+      b.extend(rec);
+    }
+    b.extend(0u32.to_le_bytes());
+    if let Some(t) = table {
+      let off = b.len() as u64;
+      b[table_at..table_at + 8].copy_from_slice(&off.to_le_bytes());
+      b.extend((t.len() as u32).to_le_bytes());
+      for s in t {
+        b.extend(s.as_bytes());
+        b.push(0);
+      }
+    }
+    b
+  }
+
+  /// packageinfo.vdf з пакетами (packageid, appids).
+  pub(crate) fn packageinfo(magic: u32, packages: &[(u32, &[u32])]) -> Vec<u8> {
+    let mut b = vec![];
+    b.extend(magic.to_le_bytes());
+    b.extend(1u32.to_le_bytes());
+    for &(pkg, apps) in packages {
+      b.extend(pkg.to_le_bytes());
+      b.extend([0xCD; 20 + 4]);
+      if magic == PACKAGEINFO_V28 {
+        b.extend(7u64.to_le_bytes());
+      }
+      // ключі appids — "0", "1", …
+      let body = vec![
+        int("packageid", pkg),
+        obj("appids", apps.iter().enumerate().map(|(i, &a)| int(i, a)).collect()),
+      ];
+      enc(&[obj(pkg, body)], &mut None, &mut b);
+    }
+    b.extend(u32::MAX.to_le_bytes());
+    b
+  }
+
+  fn ids(v: &[i64]) -> HashSet<i64> {
+    v.iter().copied().collect()
+  }
+
+  #[test]
+  fn appinfo_versions() {
+    let apps = [(10, "Skipped", "Game"), (70, "Half-Life", "Game"), (80, "Soundtrack", "Music")];
+    for magic in [APPINFO_V27, APPINFO_V28, APPINFO_V29] {
+      let m = parse_appinfo(&appinfo(magic, &apps), &ids(&[70, 80, 999]));
+      assert_eq!(m.len(), 2, "{magic:#x}");
+      assert_eq!((m[&70].name.as_str(), m[&70].kind.as_str()), ("Half-Life", "game"), "{magic:#x}");
+      assert_eq!(m[&80].kind, "music", "{magic:#x}");
+    }
+  }
+
+  #[test]
+  fn appinfo_v29_keys_come_from_the_string_table() {
+    let b = appinfo(APPINFO_V29, &[(70, "Half-Life", "game")]);
+    // у записах немає самих імен ключів — лише індекси в таблицю після них
+    let table_at = u64::from_le_bytes(b[8..16].try_into().unwrap()) as usize;
+    let body = &b[16..table_at];
+    assert!(!body.windows(6).any(|w| w == b"common"));
+    assert_eq!(parse_appinfo(&b, &ids(&[70]))[&70].name, "Half-Life");
+
+    // зсув таблиці за межі файлу — нічого не читаємо
+    let mut bad = b.clone();
+    bad[8..16].copy_from_slice(&(b.len() as u64 + 100).to_le_bytes());
+    assert!(parse_appinfo(&bad, &ids(&[70])).is_empty());
+  }
+
+  #[test]
+  fn appinfo_truncated_and_bad_magic() {
+    let b = appinfo(APPINFO_V28, &[(10, "First", "game"), (20, "Second", "game")]);
+    // обрізано посеред другого запису: перший лишається
+    let cut = &b[..b.len() - 30];
+    let m = parse_appinfo(cut, &ids(&[10, 20]));
+    assert_eq!(m.len(), 1);
+    assert_eq!(m[&10].name, "First");
+
+    let mut bad = b.clone();
+    bad[0] ^= 0xFF;
+    assert!(parse_appinfo(&bad, &ids(&[10])).is_empty());
+    assert!(parse_appinfo(&[], &ids(&[10])).is_empty());
+    assert!(parse_appinfo(&b[..6], &ids(&[10])).is_empty());
+  }
+
+  #[test]
+  fn packageinfo_versions_and_errors() {
+    let packages: [(u32, &[u32]); 3] = [(5, &[100, 101]), (6, &[200]), (7, &[0, 300])];
+    for magic in [PACKAGEINFO_V27, PACKAGEINFO_V28] {
+      let b = packageinfo(magic, &packages);
+      assert_eq!(parse_package_apps(&b, &[6, 7].into()), ids(&[200, 300]), "{magic:#x}");
+      assert!(parse_package_apps(&b, &[42].into()).is_empty());
+
+      // обрізаний третій пакет: перші два вже прочитані
+      let cut = &b[..b.len() - 12];
+      assert_eq!(parse_package_apps(cut, &[5, 6, 7].into()), ids(&[100, 101, 200]), "{magic:#x}");
+    }
+    let mut bad = packageinfo(PACKAGEINFO_V28, &packages);
+    bad[3] = 0;
+    assert!(parse_package_apps(&bad, &[5].into()).is_empty());
+  }
+
+  #[test]
+  fn kv_nesting_is_capped() {
+    let mut b = vec![];
+    b.extend(PACKAGEINFO_V27.to_le_bytes());
+    b.extend(1u32.to_le_bytes());
+    b.extend(5u32.to_le_bytes());
+    b.extend([0; 24]);
+    for _ in 0..10_000 {
+      b.extend([KV_OBJ, b'k', 0]);
+    }
+    assert!(parse_package_apps(&b, &[5].into()).is_empty());
+  }
+}
@@ -0,0 +1,150 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Runtime;
+
+use crate::commands;
+use crate::nativehost;
+
+// ====================== Розширення-помічник для браузера ======================
+//
+// Генеруємо розпаковане MV3-розширення в app data dir (тека встановленої
+// програми read-only). Маніфест будуємо як JSON-значення, а не рядком — так
+// він завжди валідний. Скрипти лежать у src-tauri/browser_helper/.
+
+const CONTENT_JS: &str = include_str!("../browser_helper/content.js");
+const BACKGROUND_JS: &str = include_str!("../browser_helper/background.js");
+
+pub const FIREFOX_EXTENSION_ID: &str = "family-helper@steamgamehelper";
+// MV3 у Firefox — з версії 109
+const FIREFOX_MIN_VERSION: &str = "109.0";
+const FAMILY_PAGE: &str = "https://store.steampowered.com/account/familymanagement*";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+  Chromium,
+  Firefox,
+}
+
+impl Variant {
+  fn dir_name(self) -> &'static str {
+    match self {
+      Variant::Chromium => "chromium",
+      Variant::Firefox => "firefox",
+    }
+  }
+}
+
+/// Chrome приймає лише 1–4 числа через крапку: `0.2.0-beta.1` → `0.2.0`.
+fn manifest_version_of(app_version: &str) -> String {
+  let core = app_version.split(['-', '+']).next().unwrap_or_default();
+  let parts: Vec<u32> = core.split('.').map_while(|p| p.parse().ok()).take(4).collect();
+  if parts.is_empty() {
+    return "0.0.0".into();
+  }
+  parts.iter().map(u32::to_string).collect::<Vec<_>>().join(".")
+}
+
+pub fn manifest(variant: Variant, app_version: &str) -> Value {
+  let mut m = json!({
+    "manifest_version": 3,
+    "name": "SteamGameHelper Family Copier",
+    "version": manifest_version_of(app_version),
+    "version_name": app_version,
+    "description": "Копіює SteamID зі сторінки керування сім'єю і передає їх у SteamGameHelper.",
+    "permissions": ["clipboardWrite", "nativeMessaging"],
+    "host_permissions": ["https://store.steampowered.com/*"],
+    "content_scripts": [{
+      "matches": [FAMILY_PAGE],
+      "js": ["content.js"],
+      "run_at": "document_end"
+    }]
+  });
+  match variant {
+    Variant::Chromium => {
+      m["background"] = json!({ "service_worker": "background.js" });
+    }
+    Variant::Firefox => {
+      // Firefox не знає version_name і не підтримує service_worker у MV3
+      if let Some(o) = m.as_object_mut() {
+        o.remove("version_name");
+      }
+      m["background"] = json!({ "scripts": ["background.js"] });
+      m["browser_specific_settings"] = json!({
+        "gecko": { "id": FIREFOX_EXTENSION_ID, "strict_min_version": FIREFOX_MIN_VERSION }
+      });
+    }
+  }
+  m
+}
+
+/// Записує manifest.json, content.js і background.js у `dir`.
+pub fn write_extension(dir: &Path, variant: Variant, app_version: &str) -> Result<(), String> {
+  fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  let manifest = serde_json::to_vec_pretty(&manifest(variant, app_version)).map_err(|e| e.to_string())?;
+  fs::write(dir.join("manifest.json"), manifest).map_err(|e| e.to_string())?;
+  fs::write(dir.join("content.js"), CONTENT_JS).map_err(|e| e.to_string())?;
+  fs::write(dir.join("background.js"), BACKGROUND_JS.replace("__HOST__", nativehost::HOST_NAME))
+    .map_err(|e| e.to_string())
+}
+
+pub fn extension_dir<R: Runtime>(app: &tauri::AppHandle<R>, variant: Variant) -> Result<PathBuf, String> {
+  Ok(commands::data_dir(app)?.join("browser_helper").join(variant.dir_name()))
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BrowserHelper {
+  pub version: String,
+  pub chromium_dir: String,
+  pub firefox_dir: String,
+}
+
+/// Перезаписує розширення при кожному виклику — версія слідує за версією застосунку.
+#[tauri::command]
+pub async fn ensure_browser_helper<R: Runtime>(app: tauri::AppHandle<R>) -> Result<BrowserHelper, String> {
+  let version = app.package_info().version.to_string();
+  let chromium = extension_dir(&app, Variant::Chromium)?;
+  let firefox = extension_dir(&app, Variant::Firefox)?;
+  write_extension(&chromium, Variant::Chromium, &version)?;
+  write_extension(&firefox, Variant::Firefox, &version)?;
+  Ok(BrowserHelper {
+    version,
+    chromium_dir: chromium.to_string_lossy().to_string(),
+    firefox_dir: firefox.to_string_lossy().to_string(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn generated(variant: Variant, version: &str) -> Value {
+    let dir = std::env::temp_dir().join(format!("sgh_ext_{}_{}", variant.dir_name(), std::process::id()));
+    write_extension(&dir, variant, version).unwrap();
+    let raw = fs::read_to_string(dir.join("manifest.json")).unwrap();
+    assert!(dir.join("content.js").is_file() && dir.join("background.js").is_file());
+    let _ = fs::remove_dir_all(&dir);
+    serde_json::from_str(&raw).expect("manifest.json must be valid JSON")
+  }
+
+  #[test]
+  fn chromium_manifest_is_valid_mv3() {
+    let m = generated(Variant::Chromium, "0.3.1-beta.2");
+    assert_eq!(m["manifest_version"], 3);
+    assert_eq!(m["version"], "0.3.1");
+    assert_eq!(m["version_name"], "0.3.1-beta.2");
+    assert_eq!(m["content_scripts"][0]["js"][0], "content.js");
+    assert_eq!(m["background"]["service_worker"], "background.js");
+    assert!(m.get("browser_specific_settings").is_none());
+  }
+
+  #[test]
+  fn firefox_manifest_has_gecko_id() {
+    let m = generated(Variant::Firefox, env!("CARGO_PKG_VERSION"));
+    assert_eq!(m["manifest_version"], 3);
+    assert_eq!(m["browser_specific_settings"]["gecko"]["id"], FIREFOX_EXTENSION_ID);
+    assert_eq!(m["background"]["scripts"][0], "background.js");
+    assert!(m.get("version_name").is_none());
+  }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gguf::GgufMeta;
+
+// ====================== Шаблони чату ======================
+//
+// Інструкційні моделі навчені на власній розмітці реплік; з чужою (скажімо,
+// Phi-3 для Llama 3) вони гірше слухаються system prompt і не закінчують
+// відповідь своїм стоп-токеном. Формат беремо з tokenizer.chat_template у GGUF,
+// без нього — з general.architecture; у налаштуваннях можна задати вручну.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+  User,
+  Assistant,
+}
+
+impl Role {
+  fn as_str(self) -> &'static str {
+    match self {
+      Role::User => "user",
+      Role::Assistant => "assistant",
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+  pub role: Role,
+  pub text: String,
+}
+
+impl ChatMessage {
+  pub fn user(text: impl Into<String>) -> Self {
+    Self { role: Role::User, text: text.into() }
+  }
+
+  pub fn assistant(text: impl Into<String>) -> Self {
+    Self { role: Role::Assistant, text: text.into() }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatFormat {
+  Llama3,
+  /// ChatML: Qwen, Yi, більшість файнтюнів
+  Chatml,
+  Phi3,
+  Gemma,
+  Mistral,
+}
+
+impl ChatFormat {
+  /// Jinja-шаблон не виконуємо — лише впізнаємо за характерними токенами.
+  fn from_template(t: &str) -> Option<Self> {
+    if t.contains("<|start_header_id|>") {
+      Some(Self::Llama3)
+    } else if t.contains("<|im_start|>") {
+      Some(Self::Chatml)
+    } else if t.contains("<start_of_turn>") {
+      Some(Self::Gemma)
+    } else if t.contains("<|assistant|>") && t.contains("<|end|>") {
+      Some(Self::Phi3)
+    } else if t.contains("[INST]") {
+      Some(Self::Mistral)
+    } else {
+      None
+    }
+  }
+
+  fn from_architecture(arch: &str) -> Option<Self> {
+    match arch {
+      "llama" => Some(Self::Llama3),
+      "qwen" | "qwen2" | "qwen2moe" | "qwen3" => Some(Self::Chatml),
+      "phi3" => Some(Self::Phi3),
+      "gemma" | "gemma2" | "gemma3" => Some(Self::Gemma),
+      "mistral" => Some(Self::Mistral),
+      _ => None,
+    }
+  }
+
+  /// Невідома модель — ChatML: його розуміє найбільше файнтюнів.
+  pub fn detect(meta: &GgufMeta) -> Self {
+    meta
+      .chat_template
+      .as_deref()
+      .and_then(Self::from_template)
+      .or_else(|| Self::from_architecture(&meta.architecture))
+      .unwrap_or(Self::Chatml)
+  }
+
+  /// Промпт до початку відповіді асистента включно; `messages` закінчуються реплікою користувача.
+  /// BOS пишемо тут: llama_cpp токенізує контекст без нього.
+  pub fn render(self, system: &str, messages: &[ChatMessage]) -> String {
+    let system = system.trim();
+    // для форматів, де system — звичайна репліка
+    let turns: Vec<(&str, &str)> = std::iter::once(("system", system))
+      .chain(messages.iter().map(|m| (m.role.as_str(), m.text.trim())))
+      .collect();
+    let mut out = String::new();
+    match self {
+      Self::Llama3 => {
+        out.push_str("<|begin_of_text|>");
+        for &(role, text) in &turns {
+          out.push_str(&format!("<|start_header_id|>{role}<|end_header_id|>\n\n{text}<|eot_id|>"));
+        }
+        out.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+      }
+      Self::Chatml => {
+        for &(role, text) in &turns {
+          out.push_str(&format!("<|im_start|>{role}\n{text}<|im_end|>\n"));
+        }
+        out.push_str("<|im_start|>assistant\n");
+      }
+      Self::Phi3 => {
+        for &(role, text) in &turns {
+          out.push_str(&format!("<|{role}|>\n{text}<|end|>\n"));
+        }
+        out.push_str("<|assistant|>\n");
+      }
+      // ролі system у Gemma немає — інструкції йдуть на початку першої репліки користувача
+      Self::Gemma => {
+        out.push_str("<bos>");
+        for (i, m) in messages.iter().enumerate() {
+          let (role, text) = match m.role {
+            Role::User if i == 0 => ("user", format!("{system}\n\n{}", m.text.trim())),
+            Role::User => ("user", m.text.trim().to_string()),
+            Role::Assistant => ("model", m.text.trim().to_string()),
+          };
+          out.push_str(&format!("<start_of_turn>{role}\n{text}<end_of_turn>\n"));
+        }
+        out.push_str("<start_of_turn>model\n");
+      }
+      Self::Mistral => {
+        out.push_str("<s>");
+        for (i, m) in messages.iter().enumerate() {
+          match m.role {
+            Role::User if i == 0 => out.push_str(&format!("[INST] {system}\n\n{} [/INST]", m.text.trim())),
+            Role::User => out.push_str(&format!("[INST] {} [/INST]", m.text.trim())),
+            Role::Assistant => out.push_str(&format!(" {}</s>", m.text.trim())),
+          }
+        }
+      }
+    }
+    out
+  }
+
+  /// Текстові маркери кінця репліки — на випадок, якщо модель не видала EOS-токен.
+  pub fn stops(self) -> &'static [&'static str] {
+    match self {
+      Self::Llama3 => &["<|eot_id|>", "<|end_of_text|>", "<|start_header_id|>"],
+      Self::Chatml => &["<|im_end|>", "<|endoftext|>", "<|im_start|>"],
+      Self::Phi3 => &["<|end|>", "<|endoftext|>", "<|user|>"],
+      Self::Gemma => &["<end_of_turn>", "<eos>", "<start_of_turn>"],
+      Self::Mistral => &["</s>", "[INST]"],
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn meta(arch: &str, template: Option<&str>) -> GgufMeta {
+    GgufMeta { architecture: arch.into(), chat_template: template.map(String::from), ..Default::default() }
+  }
+
+  #[test]
+  fn template_wins_over_architecture() {
+    // Mistral-файнтюни з ChatML часто мають architecture = llama
+    let chatml = "{% for m in messages %}{{'<|im_start|>' + m['role'] + '\n' + m['content'] + '<|im_end|>'}}{% endfor %}";
+    assert_eq!(ChatFormat::detect(&meta("llama", Some(chatml))), ChatFormat::Chatml);
+    assert_eq!(ChatFormat::detect(&meta("llama", Some("{{ '<|start_header_id|>' + role }}"))), ChatFormat::Llama3);
+    assert_eq!(ChatFormat::detect(&meta("gemma2", None)), ChatFormat::Gemma);
+    assert_eq!(ChatFormat::detect(&meta("phi3", Some("{{ bos_token }}"))), ChatFormat::Phi3);
+    assert_eq!(ChatFormat::detect(&meta("rwkv6", None)), ChatFormat::Chatml);
+  }
+
+  #[test]
+  fn llama3_prompt_ends_at_assistant_header() {
+    let p = ChatFormat::Llama3.render(" sys ", &[ChatMessage::user("hi")]);
+    assert!(p.starts_with("<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nsys<|eot_id|>"));
+    assert!(p.ends_with("<|start_header_id|>user<|end_header_id|>\n\nhi<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n"));
+    assert!(ChatFormat::Llama3.stops().contains(&"<|eot_id|>"));
+  }
+
+  #[test]
+  fn earlier_turns_keep_their_roles() {
+    let turns = [ChatMessage::user("щось коротке"), ChatMessage::assistant("Hades"), ChatMessage::user("не це")];
+    assert_eq!(
+      ChatFormat::Chatml.render("sys", &turns),
+      "<|im_start|>system\nsys<|im_end|>\n<|im_start|>user\nщось коротке<|im_end|>\n\
+       <|im_start|>assistant\nHades<|im_end|>\n<|im_start|>user\nне це<|im_end|>\n<|im_start|>assistant\n"
+    );
+    assert_eq!(
+      ChatFormat::Mistral.render("sys", &turns),
+      "<s>[INST] sys\n\nщось коротке [/INST] Hades</s>[INST] не це [/INST]"
+    );
+    let gemma = ChatFormat::Gemma.render("sys", &turns);
+    assert!(gemma.starts_with("<bos><start_of_turn>user\nsys\n\nщось коротке<end_of_turn>\n<start_of_turn>model\nHades<end_of_turn>\n"));
+  }
+}
@@ -2,10 +2,22 @@ use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::path::PathBuf;
 use regex::Regex;
 use tauri::{Manager, Runtime}; // Manager для app.path() / opener(), Runtime для generic AppHandle
+use tauri::ipc::Channel;
+
+use crate::scan_progress::{self, CancelToken, ScanProgress, ScanReporter, SCAN_CANCELLED};
+use crate::accounts::{self, LocalAppStat};
+use crate::chat_template::ChatFormat;
+use crate::offline::{self, Confidence};
+use crate::picks::{self, Candidate, Picks};
+use crate::profiles::{self, Profile};
+use crate::redact;
+use crate::scanner;
+use crate::secrets::{self, Secret};
+use crate::steamid;
+use crate::url_policy;
 
 // ====================== Моделі даних (бібліотека/налаштування) ======================
 
@@ -25,22 +37,54 @@ pub struct LibraryGame {
   pub installed: bool,
   pub shared_from: Option<String>,
   pub playtime_minutes: Option<u32>,
+  // старі кеші без поля — це результати Steam API
+  #[serde(default)]
+  pub confidence: Confidence,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
 pub struct AccountHint {
   pub steamid64: String,
   pub persona: String,
+  // поля з loginusers.vdf
+  pub account_name: String,
+  pub most_recent: bool,
+  pub timestamp: u64,
+  pub remember_password: bool,
+  pub wants_offline_mode: bool,
+  pub allow_auto_login: bool,
+  // є userdata/<accountid> і скільки ігор у його localconfig.vdf
+  pub has_userdata: bool,
+  pub local_apps: usize,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
 pub struct UserPrefs {
+  // ключі живуть у secrets.vault.json; тут лишаються тільки для міграції старих prefs.json
+  #[serde(skip_serializing_if = "String::is_empty")]
   pub api_key: String,
   pub main_steam_id64: String,
   pub family_ids: Vec<String>,
-  // поля для сумісності (не використовуються для LLM)
+  #[serde(skip_serializing_if = "String::is_empty")]
   pub ai_api_key: String,
+  // OpenAI-сумісний сервер для llm_backend = openai_compat; ключ — у сховищі
   pub ai_base_url: String,
+  // порожня — модель, завантажена на сервері
+  pub ai_model: String,
+  // контекст моделі на сервері: None → openai_compat::DEFAULT_CONTEXT_TOKENS
+  pub ai_context_tokens: Option<u32>,
+  pub llm_backend: BackendKind,
+  // фонове оновлення: None → refresh::DEFAULT_INTERVAL_HOURS, 0 → вимкнено
+  pub refresh_interval_hours: Option<u32>,
+  // шаблон чату локальної моделі: None → за метаданими GGUF
+  pub llm_template: Option<ChatFormat>,
+  // вибрана .gguf (models::select_model): None → перша знайдена в теках моделей
+  pub llm_model: Option<String>,
+  // main_steam_id64/family_ids вище — дзеркало активного профілю
+  pub profiles: Vec<Profile>,
+  pub active_profile: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -48,69 +92,187 @@ pub struct PartialSettings {
   pub api_key: Option<String>,
   pub main_steam_id64: Option<String>,
   pub family_ids: Option<Vec<String>>,
-  // для сумісності зі старим UI
   pub ai_api_key: Option<String>,
   pub ai_base_url: Option<String>,
+  pub ai_model: Option<String>,
+  // 0 — повернути типовий
+  pub ai_context_tokens: Option<u32>,
+  pub llm_backend: Option<BackendKind>,
+  pub refresh_interval_hours: Option<u32>,
+  // порожній api_key означає «не змінювати»; стерти ключ — лише явно
+  pub clear_api_key: Option<bool>,
+  pub clear_ai_api_key: Option<bool>,
+  pub llm_template: Option<ChatFormat>,
+  // повернути автовизначення шаблону
+  pub clear_llm_template: Option<bool>,
+}
+
+/// Те, що бачить UI: замість ключів — прапорець і маскований прев'ю.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SettingsView {
+  pub api_key_set: bool,
+  pub api_key_preview: Option<String>,
+  pub main_steam_id64: Option<String>,
+  pub family_ids: Option<Vec<String>>,
+  pub ai_api_key_set: bool,
+  pub ai_api_key_preview: Option<String>,
+  pub ai_base_url: Option<String>,
+  pub ai_model: Option<String>,
+  pub ai_context_tokens: Option<u32>,
+  pub llm_backend: BackendKind,
+  pub refresh_interval_hours: Option<u32>,
+  pub llm_template: Option<ChatFormat>,
 }
 
 // ====================== FS-шляхи/збереження ======================
 
-fn data_dir<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+pub(crate) fn data_dir<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
   let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("steamgamehelper");
   fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
   Ok(dir)
 }
 
+// identifier зі стартового шаблону Tauri; під ним лежать дані ранніх версій
+const LEGACY_IDENTIFIER: &str = "com.tauri.dev";
+
+/// Одноразово перенести дані з app_data_dir під старим identifier. Викликати з
+/// `setup` першим. Те, що вже є в новій теці (скажімо, inbox від native host),
+/// не перезаписуємо.
+pub(crate) fn migrate_legacy_data_dir<R: Runtime>(app: &tauri::AppHandle<R>) {
+  let Ok(base) = app.path().app_data_dir() else { return; };
+  let Some(old) = base.parent().map(|p| p.join(LEGACY_IDENTIFIER).join("steamgamehelper")) else { return; };
+  let Ok(entries) = fs::read_dir(&old) else { return; };
+  let Ok(new) = data_dir(app) else { return; };
+  for e in entries.flatten() {
+    let dst = new.join(e.file_name());
+    if dst.exists() { continue; }
+    if let Err(err) = fs::rename(e.path(), &dst) {
+      log::warn!("data dir migration: {}: {err}", e.file_name().to_string_lossy());
+    }
+  }
+  // порожня тека — усе перенесено; інакше лишаємо як є
+  let _ = fs::remove_dir(&old);
+}
+
 fn prefs_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
   Ok(data_dir(app)?.join("prefs.json"))
 }
 
-fn cache_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
-  Ok(data_dir(app)?.join("library_cache.json"))
+pub(crate) fn cache_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(profiles::active_dir(app)?.join("library_cache.json"))
 }
 
 fn meta_cache_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
-  Ok(data_dir(app)?.join("app_meta.json"))
+  Ok(profiles::active_dir(app)?.join("app_meta.json"))
 }
 
-fn save_prefs_internal<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &UserPrefs) -> Result<(), String> {
+pub(crate) fn save_prefs_internal<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &UserPrefs) -> Result<(), String> {
   let p = prefs_path(app)?;
-  let data = serde_json::to_vec_pretty(prefs).map_err(|e| e.to_string())?;
+  let mut prefs = prefs.clone();
+  profiles::sync_on_save(&mut prefs);
+  let data = serde_json::to_vec_pretty(&prefs).map_err(|e| e.to_string())?;
   fs::write(p, data).map_err(|e| e.to_string())
 }
 
-fn load_prefs_internal<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<UserPrefs, String> {
+pub(crate) fn load_prefs_internal<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<UserPrefs, String> {
   let p = prefs_path(app)?;
-  if !p.exists() {
-    return Ok(UserPrefs::default());
+  let mut prefs: UserPrefs = if p.exists() {
+    let bytes = fs::read(p).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).unwrap_or_default()
+  } else {
+    UserPrefs::default()
+  };
+  if profiles::sync_on_load(app, &mut prefs) {
+    let _ = save_prefs_internal(app, &prefs);
   }
-  let bytes = fs::read(p).map_err(|e| e.to_string())?;
-  let prefs: UserPrefs = serde_json::from_slice(&bytes).unwrap_or_default();
+  migrate_plaintext_keys(app, &mut prefs);
   Ok(prefs)
 }
 
-// ====================== Налаштування ======================
+/// Старі prefs.json тримали ключі відкритим текстом: переносимо у сховище і стираємо з файлу.
+/// Якщо сховище недоступне — лишаємо як є, щоб не втратити ключ.
+fn migrate_plaintext_keys<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &mut UserPrefs) {
+  if prefs.api_key.is_empty() && prefs.ai_api_key.is_empty() { return; }
+  let moved = [
+    (Secret::SteamApiKey, &mut prefs.api_key),
+    (Secret::AiApiKey, &mut prefs.ai_api_key),
+  ]
+  .into_iter()
+  .filter(|(_, v)| !v.is_empty())
+  .all(|(which, v)| match secrets::set(app, which, v) {
+    Ok(()) => { v.clear(); true }
+    Err(e) => { log::warn!("secret migration failed: {}", redact::scrub(&e)); false }
+  });
+  if moved {
+    let _ = save_prefs_internal(app, prefs);
+  }
+}
 
-#[tauri::command]
-pub async fn get_settings<R: Runtime>(app: tauri::AppHandle<R>) -> Result<PartialSettings, String> {
-  let p = load_prefs_internal(&app)?;
-  Ok(PartialSettings {
-    api_key: if p.api_key.is_empty() { None } else { Some(p.api_key) },
+pub(crate) fn steam_api_key<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<String, String> {
+  secrets::get(app, Secret::SteamApiKey)?
+    .filter(|k| !k.trim().is_empty())
+    .ok_or_else(|| "Steam API key is not set".to_string())
+}
+
+fn settings_view<R: Runtime>(app: &tauri::AppHandle<R>, p: UserPrefs) -> SettingsView {
+  let (api_key_set, api_key_preview) = secrets::preview(app, Secret::SteamApiKey);
+  let (ai_api_key_set, ai_api_key_preview) = secrets::preview(app, Secret::AiApiKey);
+  SettingsView {
+    api_key_set,
+    api_key_preview,
     main_steam_id64: if p.main_steam_id64.is_empty() { None } else { Some(p.main_steam_id64) },
     family_ids: if p.family_ids.is_empty() { None } else { Some(p.family_ids) },
-    ai_api_key: if p.ai_api_key.is_empty() { None } else { Some(p.ai_api_key) },
+    ai_api_key_set,
+    ai_api_key_preview,
     ai_base_url: if p.ai_base_url.is_empty() { None } else { Some(p.ai_base_url) },
-  })
+    ai_model: if p.ai_model.is_empty() { None } else { Some(p.ai_model) },
+    ai_context_tokens: p.ai_context_tokens,
+    llm_backend: p.llm_backend,
+    refresh_interval_hours: p.refresh_interval_hours,
+    llm_template: p.llm_template,
+  }
+}
+
+// ====================== Налаштування ======================
+
+// менше не вмістить system prompt і відповідь; більше не буває в жодної моделі
+const MIN_AI_CONTEXT_TOKENS: u32 = 1024;
+const MAX_AI_CONTEXT_TOKENS: u32 = 1 << 20;
+
+#[tauri::command]
+pub async fn get_settings<R: Runtime>(app: tauri::AppHandle<R>) -> Result<SettingsView, String> {
+  let p = load_prefs_internal(&app)?;
+  Ok(settings_view(&app, p))
 }
 
 #[tauri::command]
 pub async fn save_settings<R: Runtime>(app: tauri::AppHandle<R>, s: PartialSettings) -> Result<(), String> {
   let mut cur = load_prefs_internal(&app)?;
-  if let Some(v) = s.api_key { cur.api_key = v; }
+  if s.clear_api_key == Some(true) { secrets::set(&app, Secret::SteamApiKey, "")?; }
+  if let Some(v) = s.api_key.filter(|v| !v.trim().is_empty()) {
+    secrets::set(&app, Secret::SteamApiKey, &v)?;
+  }
   if let Some(v) = s.main_steam_id64 { cur.main_steam_id64 = v; }
   if let Some(v) = s.family_ids { cur.family_ids = dedup_ids(v); }
-  if let Some(v) = s.ai_api_key { cur.ai_api_key = v; }
-  if let Some(v) = s.ai_base_url { cur.ai_base_url = v; }
+  if s.clear_ai_api_key == Some(true) { secrets::set(&app, Secret::AiApiKey, "")?; }
+  if let Some(v) = s.ai_api_key.filter(|v| !v.trim().is_empty()) {
+    secrets::set(&app, Secret::AiApiKey, &v)?;
+  }
+  if let Some(v) = s.ai_base_url.map(|v| v.trim().to_string()) {
+    if !v.is_empty() { openai_compat::endpoint(&v)?; }
+    cur.ai_base_url = v;
+  }
+  if let Some(v) = s.ai_model { cur.ai_model = v.trim().to_string(); }
+  if let Some(v) = s.ai_context_tokens {
+    if v != 0 && !(MIN_AI_CONTEXT_TOKENS..=MAX_AI_CONTEXT_TOKENS).contains(&v) {
+      return Err(format!("AI context must be {MIN_AI_CONTEXT_TOKENS}..={MAX_AI_CONTEXT_TOKENS} tokens"));
+    }
+    cur.ai_context_tokens = if v == 0 { None } else { Some(v) };
+  }
+  if let Some(v) = s.llm_backend { cur.llm_backend = v; }
+  if let Some(v) = s.refresh_interval_hours { cur.refresh_interval_hours = Some(v); }
+  if s.clear_llm_template == Some(true) { cur.llm_template = None; }
+  if let Some(v) = s.llm_template { cur.llm_template = Some(v); }
   save_prefs_internal(&app, &cur)
 }
 
@@ -129,14 +291,19 @@ pub async fn load_library_cache<R: Runtime>(app: tauri::AppHandle<R>) -> Result<
 
 #[tauri::command]
 pub async fn save_library_cache<R: Runtime>(app: tauri::AppHandle<R>, games: Vec<LibraryGame>) -> Result<(), String> {
-  let p = cache_path(&app)?;
-  let data = serde_json::to_vec_pretty(&games).map_err(|e| e.to_string())?;
+  save_library_cache_internal(&app, &games)
+}
+
+pub(crate) fn save_library_cache_internal<R: Runtime>(app: &tauri::AppHandle<R>, games: &[LibraryGame]) -> Result<(), String> {
+  let p = cache_path(app)?;
+  let data = serde_json::to_vec_pretty(games).map_err(|e| e.to_string())?;
   fs::write(p, data).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn load_cached_inputs<R: Runtime>(app: tauri::AppHandle<R>) -> Result<UserPrefs, String> {
-  load_prefs_internal(&app)
+pub async fn load_cached_inputs<R: Runtime>(app: tauri::AppHandle<R>) -> Result<SettingsView, String> {
+  let p = load_prefs_internal(&app)?;
+  Ok(settings_view(&app, p))
 }
 
 // ====================== Відкриття посилань/хелпер ======================
@@ -157,88 +324,10 @@ pub async fn open_family_page<R: Runtime>(app: tauri::AppHandle<R>) -> Result<()
     .map_err(|e| e.to_string())
 }
 
+/// Лише через url_policy: рядок приходить із вебв'ю.
 #[tauri::command]
 pub async fn open_url_external<R: Runtime>(app: tauri::AppHandle<R>, url: String) -> Result<(), String> {
-  use tauri_plugin_opener::OpenerExt;
-  app.opener().open_url(&url, None::<String>).map_err(|e| e.to_string())
-}
-
-#[tauri::command]
-pub async fn ensure_browser_helper<R: Runtime>(_app: tauri::AppHandle<R>) -> Result<String, String> {
-  let base = std::env::current_dir().map_err(|e| e.to_string())?.join("browser_helper");
-  fs::create_dir_all(&base).map_err(|e| e.to_string())?;
-
-  let manifest = r#"
-{
-  "manifest_version": 3,
-  "name": "SteamGameHelper Family Copier",
-  "version": "1.0.0",
-  "description": "Копіює SteamID зі сторінки керування сім'єю.",
-  "permissions": ["clipboardWrite", "activeTab", "scripting"],
-  "host_permissions": ["https://store.steampowered.com/*"],
-  "content_scripts": [{
-    "matches": ["https://store.steampowered.com/account/familymanagement*"],
-    "js": ["content.js"],
-    "run_at": "document_end"
-  }]]
-}
-"#;
-
-  let content_js = r#"(function () {
-  const OFFSET = BigInt("76561197960265728");
-  function collectOnce() {
-    const ids = new Set();
-    document.querySelectorAll("[data-miniprofile]").forEach((el) => {
-      const acc = el.getAttribute("data-miniprofile");
-      const n = Number(acc);
-      if (Number.isFinite(n) && n > 0) ids.add((OFFSET + BigInt(n)).toString());
-    });
-    document.querySelectorAll('a[href*="steamcommunity.com/profiles/"]').forEach((a) => {
-      const m = a.href.match(/profiles\/(\d{17})/);
-      if (m) ids.add(m[1]);
-    });
-    return Array.from(ids);
-  }
-  function ensureButton() {
-    let btn = document.getElementById("__sghelper_copy_ids_btn");
-    if (btn) return btn;
-    btn = document.createElement("button");
-    btn.id = "__sghelper_copy_ids_btn";
-    btn.style.cssText = [
-      "position:fixed","right:18px","bottom:18px","z-index:99999",
-      "border:none","border-radius:14px","padding:12px 16px","font-size:14px",
-      "cursor:pointer","box-shadow:0 6px 18px rgba(0,0,0,.25)",
-      "background:#10b981","color:#fff","font-weight:600"
-    ].join(";");
-    document.body.appendChild(btn);
-    return btn;
-  }
-  function render() {
-    const ids = collectOnce();
-    const btn = ensureButton();
-    if (ids.length === 0) {
-      btn.textContent = "No SteamIDs found";
-      btn.disabled = true; btn.style.opacity = "0.75";
-      return;
-    }
-    btn.disabled = false; btn.style.opacity = "1";
-    btn.textContent = `Copy ${ids.length} SteamIDs`;
-    btn.onclick = async () => {
-      try { await navigator.clipboard.writeText(ids.join(", ")); btn.textContent = "Copied!"; }
-      catch { btn.textContent = "Clipboard error"; }
-      setTimeout(render, 1200);
-    };
-  }
-  if (document.readyState === "loading") {
-    document.addEventListener("DOMContentLoaded", () => { render(); setTimeout(render, 1200); });
-  } else {
-    render(); setTimeout(render, 1200);
-  }
-})();"#;
-
-  fs::write(base.join("manifest.json"), manifest).map_err(|e| e.to_string())?;
-  fs::write(base.join("content.js"), content_js).map_err(|e| e.to_string())?;
-  Ok(base.to_string_lossy().to_string())
+  url_policy::open(&app, &url).await
 }
 
 #[tauri::command]
@@ -258,22 +347,34 @@ pub async fn detect_accounts() -> Vec<AccountHint> {
     .unwrap_or_default()
 }
 
+/// `api_key` необов'язковий: UI більше не знає збереженого ключа, тож за замовчуванням беремо зі сховища.
 #[tauri::command]
 pub async fn scan_library_unified<R: Runtime>(
   app: tauri::AppHandle<R>,
-  api_key: String,
+  api_key: Option<String>,
   main_steam_id64: String,
   family_ids: Option<Vec<String>>,
+  offline: Option<bool>,
+  on_progress: Channel<ScanProgress>,
 ) -> Result<Vec<LibraryGame>, String> {
   let fam = family_ids.unwrap_or_default();
 
+  // без ключа — одразу офлайн-бібліотека з локальних файлів
+  let api_key = match api_key.filter(|k| !k.trim().is_empty()) {
+    Some(k) => {
+      secrets::set(&app, Secret::SteamApiKey, &k)?;
+      k.trim().to_string()
+    }
+    None => steam_api_key(&app).unwrap_or_default(),
+  };
+  let mode = if offline.unwrap_or(false) { ScanMode::Offline } else { ScanMode::Auto };
+
   let mut prefs = load_prefs_internal(&app).unwrap_or_default();
-  prefs.api_key = api_key.clone();
   prefs.main_steam_id64 = main_steam_id64.clone();
   prefs.family_ids = dedup_ids(fam.clone());
   let _ = save_prefs_internal(&app, &prefs);
 
-  fetch_full_library(api_key, main_steam_id64, Some(fam)).await
+  run_scan(api_key, main_steam_id64, fam, mode, ScanReporter::new(Some(on_progress)), scan_progress::begin_scan()).await
 }
 
 #[tauri::command]
@@ -282,17 +383,127 @@ pub async fn fetch_full_library(
   steam_id64: String,
   family_ids: Option<Vec<String>>,
 ) -> Result<Vec<LibraryGame>, String> {
-  let client = reqwest::Client::new();
+  redact::remember(&api_key);
+  run_scan(
+    api_key,
+    steam_id64,
+    family_ids.unwrap_or_default(),
+    ScanMode::Auto,
+    ScanReporter::default(),
+    scan_progress::begin_scan(),
+  )
+  .await
+}
+
+#[tauri::command]
+pub async fn cancel_scan() -> bool {
+  scan_progress::cancel_active()
+}
+
+/// Онлайн-скан з офлайн-запасом.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ScanMode {
+  /// лише Steam API (фонове оновлення не повинно перезаписати кеш гіршими даними)
+  Online,
+  /// Steam API, а якщо ключа немає чи API недосяжне — локальні файли
+  Auto,
+  Offline,
+}
+
+const API_UNREACHABLE: &str = "Steam API unreachable";
+
+fn is_unreachable(e: &str) -> bool {
+  e.starts_with(API_UNREACHABLE)
+}
+
+pub(crate) async fn run_scan(
+  api_key: String,
+  steam_id64: String,
+  family_ids: Vec<String>,
+  mode: ScanMode,
+  progress: ScanReporter,
+  // зареєстрований скан (begin_scan / try_begin_scan); знімаємо його тут
+  cancel: CancelToken,
+) -> Result<Vec<LibraryGame>, String> {
+  let res = match mode {
+    ScanMode::Offline => offline_library(&steam_id64, "offline mode".into(), &progress, &cancel).await,
+    ScanMode::Auto if api_key.trim().is_empty() => {
+      offline_library(&steam_id64, "Steam API key is not set".into(), &progress, &cancel).await
+    }
+    _ => match fetch_library_inner(&api_key, &steam_id64, family_ids, &progress, &cancel).await {
+      Err(e) if mode == ScanMode::Auto && is_unreachable(&e) && !cancel.is_cancelled() => {
+        log::warn!("{}; falling back to local files", redact::scrub(&e));
+        offline_library(&steam_id64, redact::scrub(&e), &progress, &cancel).await
+      }
+      r => r,
+    },
+  };
+  scan_progress::end_scan(&cancel);
+
+  match &res {
+    Ok(games) => progress.emit(ScanProgress::Finished { games: games.len() }),
+    Err(_) if cancel.is_cancelled() => progress.emit(ScanProgress::Cancelled),
+    Err(_) => {}
+  }
+  res.map_err(|e| redact::scrub(&e))
+}
+
+/// Акаунт — введений SteamID64, інакше останній, хто входив у Steam на цьому ПК.
+async fn offline_library(
+  steam_id64: &str,
+  reason: String,
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<Vec<LibraryGame>, String> {
+  progress.emit(ScanProgress::Offline { reason });
+  let sid = steam_id64.to_string();
+  let progress = progress.clone();
+  let cancel = cancel.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let roots = roots_from_detect_roots();
+    let account = steamid::parse_steamid64(&sid)
+      .ok()
+      .or_else(|| {
+        accounts::detect(&roots)
+          .first()
+          .and_then(|h| steamid::parse_steamid64(&h.steamid64).ok())
+      })
+      .map(steamid::account_id);
+    offline::build(&roots, account, &progress, &cancel)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+async fn fetch_library_inner(
+  api_key: &str,
+  steam_id64: &str,
+  family_ids: Vec<String>,
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<Vec<LibraryGame>, String> {
+  let client = reqwest::Client::builder()
+    .connect_timeout(std::time::Duration::from_secs(10))
+    .build()
+    .map_err(redact::http_err)?;
 
   async fn owned(
     client: &reqwest::Client,
     key: &str,
     sid: &str,
+    cancel: &CancelToken,
   ) -> Result<Vec<LibraryGame>, String> {
     let url = format!(
       "https://api.steampowered.com/IPlayerService/GetOwnedGames/v1/?key={key}&steamid={sid}&include_appinfo=1&include_played_free_games=1"
     );
-    let res = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    // дроп футури при скасуванні закриває з'єднання — запит не висить до кінця
+    let res = tokio::select! {
+      r = client.get(&url).send() => r.map_err(|e| format!("{API_UNREACHABLE}: {}", redact::http_err(e)))?,
+      _ = cancel.cancelled() => return Err(SCAN_CANCELLED.into()),
+    };
+    if res.status().is_server_error() {
+      return Err(format!("{API_UNREACHABLE}: HTTP {}", res.status()));
+    }
     if !res.status().is_success() {
       return Err(format!("Steam API HTTP {}", res.status()));
     }
@@ -304,7 +515,10 @@ pub async fn fetch_full_library(
     #[derive(Deserialize)]
     struct ResponseRoot { response: ResponseInner }
 
-    let root: ResponseRoot = res.json().await.map_err(|e| e.to_string())?;
+    let root: ResponseRoot = tokio::select! {
+      r = res.json() => r.map_err(redact::http_err)?,
+      _ = cancel.cancelled() => return Err(SCAN_CANCELLED.into()),
+    };
     let mut out = vec![];
     if let Some(list) = root.response.games {
       for g in list {
@@ -314,40 +528,101 @@ pub async fn fetch_full_library(
           installed: false,
           shared_from: None,
           playtime_minutes: g.playtime_forever,
+          confidence: Confidence::Verified,
         });
       }
     }
     Ok(out)
   }
 
+  let fams = dedup_ids(family_ids);
+  let total = 1 + fams.len();
+  progress.emit(ScanProgress::Started { accounts: total });
+
   let mut all: HashMap<i64, LibraryGame> = HashMap::new();
 
-  for g in owned(&client, &api_key, &steam_id64).await? {
+  let main = owned(&client, api_key, steam_id64, cancel).await?;
+  progress.emit(ScanProgress::ApiAccount {
+    steamid64: steam_id64.to_string(),
+    index: 0,
+    total,
+    games: main.len(),
+  });
+  for g in main {
     all.insert(g.appid, g);
   }
 
-  if let Some(fams) = family_ids {
-    for fid in dedup_ids(fams) {
-      let list = owned(&client, &api_key, &fid).await?;
-      for mut g in list {
-        g.shared_from = Some(fid.clone());
-        g.playtime_minutes = None;
-        all.entry(g.appid).or_insert(g);
-      }
+  for (i, fid) in fams.into_iter().enumerate() {
+    let list = owned(&client, api_key, &fid, cancel).await?;
+    progress.emit(ScanProgress::ApiAccount {
+      steamid64: fid.clone(),
+      index: i + 1,
+      total,
+      games: list.len(),
+    });
+    for mut g in list {
+      g.shared_from = Some(fid.clone());
+      g.playtime_minutes = None;
+      all.entry(g.appid).or_insert(g);
     }
   }
 
-  let installed = scan_roots(roots_from_detect_roots()).map_err(|e| e.to_string())?;
-  let installed_set: HashSet<i64> = installed.into_iter().map(|g| g.appid).collect();
+  let account = steamid::parse_steamid64(steam_id64).ok().map(steamid::account_id);
+  let installed = {
+    let progress = progress.clone();
+    let cancel = cancel.clone();
+    tauri::async_runtime::spawn_blocking(move || scan_roots(roots_from_detect_roots(), account, &progress, &cancel))
+      .await
+      .map_err(|e| e.to_string())??
+  };
+  let installed: HashMap<i64, Game> = installed.into_iter().map(|g| (g.appid, g)).collect();
   for g in all.values_mut() {
-    if installed_set.contains(&g.appid) {
+    if let Some(local) = installed.get(&g.appid) {
       g.installed = true;
+      // API не віддає час, якщо він прихований приватністю; локальний — лише свого акаунта
+      if g.shared_from.is_none() && g.playtime_minutes.is_none() {
+        g.playtime_minutes = local.playtime_minutes;
+      }
     }
   }
 
   Ok(all.into_values().collect())
 }
 
+#[derive(Serialize, Clone, Debug)]
+pub struct PlayerSummary {
+  pub persona: String,
+  pub avatar: Option<String>,
+}
+
+/// Аватар/нік для головного екрана. Раніше UI ходив у Steam API сам, тримаючи ключ у вебв'ю.
+#[tauri::command]
+pub async fn get_player_summary<R: Runtime>(app: tauri::AppHandle<R>) -> Result<Option<PlayerSummary>, String> {
+  let prefs = load_prefs_internal(&app)?;
+  let sid = prefs.main_steam_id64.trim().to_string();
+  let Ok(key) = steam_api_key(&app) else { return Ok(None); };
+  if sid.is_empty() { return Ok(None); }
+
+  let url = format!("https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v2/?key={key}&steamids={sid}");
+  let res = reqwest::Client::new().get(&url).send().await.map_err(redact::http_err)?;
+  if !res.status().is_success() {
+    return Err(format!("Steam API HTTP {}", res.status()));
+  }
+
+  #[derive(Deserialize)]
+  struct Player { personaname: Option<String>, avatarfull: Option<String> }
+  #[derive(Deserialize)]
+  struct Inner { players: Vec<Player> }
+  #[derive(Deserialize)]
+  struct Root { response: Inner }
+
+  let root: Root = res.json().await.map_err(redact::http_err)?;
+  Ok(root.response.players.into_iter().next().map(|p| PlayerSummary {
+    persona: p.personaname.unwrap_or_default(),
+    avatar: p.avatarfull,
+  }))
+}
+
 #[tauri::command]
 pub async fn auto_detect_steam_roots() -> Vec<String> {
   tauri::async_runtime::spawn_blocking(move || detect_roots())
@@ -357,7 +632,7 @@ pub async fn auto_detect_steam_roots() -> Vec<String> {
 
 // ====================== Утиліти ======================
 
-fn dedup_ids(v: Vec<String>) -> Vec<String> {
+pub(crate) fn dedup_ids(v: Vec<String>) -> Vec<String> {
   let mut set = HashSet::<String>::new();
   let mut out = Vec::new();
   for s in v {
@@ -368,82 +643,31 @@ fn dedup_ids(v: Vec<String>) -> Vec<String> {
   out
 }
 
-fn scan_roots(roots: Vec<PathBuf>) -> Result<Vec<Game>, String> {
-  let mut games: Vec<Game> = vec![];
-
-  for steam_root in roots {
-    let steamapps = steam_root.join("steamapps");
-    if !steamapps.exists() { continue; }
-
-    let mut to_scan = vec![steamapps.clone()];
-    if let Some(extra) = parse_libraryfolders(&steamapps.join("libraryfolders.vdf")) {
-      to_scan.extend(extra);
-    }
-
-    for dir in to_scan {
-      if !dir.exists() { continue; }
-      for entry in WalkDir::new(&dir).into_iter().filter_map(|e| e.ok()) {
-        let p = entry.path();
-        if is_manifest(p) {
-          if let Ok(txt) = std::fs::read_to_string(p) {
-            if let Some((appid, name)) = parse_manifest(&txt) {
-              games.push(Game {
-                appid,
-                name,
-                installed: true,
-                playtime_minutes: None,
-                last_played_unix: None,
-              });
-            }
-          }
-        }
-      }
-    }
-  }
-
-  let stats = collect_user_stats();
-  if !stats.is_empty() {
-    for g in &mut games {
-      if let Some(s) = stats.get(&g.appid) {
-        if s.playtime_minutes.is_some() { g.playtime_minutes = s.playtime_minutes; }
-        if s.last_played_unix.is_some()  { g.last_played_unix  = s.last_played_unix; }
+/// `account` — 32-бітний id акаунта, чию локальну статистику застосовуємо.
+fn scan_roots(
+  roots: Vec<PathBuf>,
+  account: Option<u32>,
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<Vec<Game>, String> {
+  let stats = collect_user_stats(&roots, account, progress, cancel)?;
+  let games: Vec<Game> = scanner::installed_manifests(&roots, progress, cancel)?
+    .into_iter()
+    .map(|m| {
+      let s = stats.get(&m.appid);
+      Game {
+        appid: m.appid,
+        name: m.name,
+        installed: true,
+        playtime_minutes: s.and_then(|s| s.playtime_minutes),
+        last_played_unix: s.and_then(|s| s.last_played_unix),
       }
-    }
-  }
+    })
+    .collect();
 
   Ok(games)
 }
 
-fn is_manifest(p: &Path) -> bool {
-  p.file_name()
-    .and_then(|s| s.to_str())
-    .map(|f| f.starts_with("appmanifest_") && f.ends_with(".acf"))
-    .unwrap_or(false)
-}
-
-fn parse_manifest(s: &str) -> Option<(i64, String)> {
-  let re_appid = Regex::new(r#""appid"\s*"(\d+)""#).ok()?;
-  let re_name  = Regex::new(r#""name"\s*"([^"]+)""#).ok()?;
-  let appid = re_appid.captures(s)
-    .and_then(|c| c.get(1))
-    .and_then(|m| m.as_str().parse::<i64>().ok())?;
-  let name = re_name.captures(s)
-    .and_then(|c| c.get(1))
-    .map(|m| m.as_str().to_string())?;
-  Some((appid, name))
-}
-
-fn parse_libraryfolders(path: &Path) -> Option<Vec<PathBuf>> {
-  let txt = std::fs::read_to_string(path).ok()?;
-  let re_path = Regex::new(r#""path"\s*"([^"]+)""#).ok()?;
-  let mut out = vec![];
-  for cap in re_path.captures_iter(&txt) {
-    let p = PathBuf::from(cap.get(1)?.as_str());
-    out.push(p.join("steamapps"));
-  }
-  if out.is_empty() { None } else { Some(out) }
-}
-
 fn detect_roots() -> Vec<String> {
   let mut roots = steam_paths();
 
@@ -462,7 +686,7 @@ fn detect_roots() -> Vec<String> {
   let mut extra = vec![];
   for r in &roots {
     let lf = r.join("steamapps").join("libraryfolders.vdf");
-    if let Some(more) = parse_libraryfolders(&lf) {
+    if let Some(more) = scanner::parse_libraryfolders(&lf) {
       for e in more {
         if let Some(parent) = e.parent().map(|p| p.to_path_buf()) {
           if !roots.contains(&parent) && !extra.contains(&parent) {
@@ -507,101 +731,38 @@ fn steam_paths() -> Vec<PathBuf> {
   out
 }
 
-fn roots_from_detect_roots() -> Vec<PathBuf> {
+pub(crate) fn roots_from_detect_roots() -> Vec<PathBuf> {
   detect_roots().into_iter().map(PathBuf::from).collect()
 }
 
-#[derive(Clone, Debug)]
-struct AppStat { playtime_minutes: Option<u32>, last_played_unix: Option<u64> }
-
-fn collect_user_stats() -> HashMap<i64, AppStat> {
-  let mut out: HashMap<i64, AppStat> = HashMap::new();
-
-  let mut files: Vec<PathBuf> = vec![];
-  for root in roots_from_detect_roots() {
-    let userdata = root.join("userdata");
-    if !userdata.exists() { continue; }
-    if let Ok(entries) = std::fs::read_dir(&userdata) {
-      for e in entries.flatten() {
-        let p = e.path().join("config").join("localconfig.vdf");
-        if p.exists() { files.push(p); }
-      }
-    }
-  }
-
-  let re_apps = Regex::new(r#"(?s)"apps"\s*\{(.*?)\}"#).ok();
-  let re_per  = Regex::new(r#"(?s)"(\d+)"\s*\{(.*?)\}"#).ok();
-  let re_last = Regex::new(r#""LastPlayed"\s*"(\d+)""#).ok();
-  let re_p1   = Regex::new(r#""playtime_forever"\s*"(\d+)""#).ok();
-  let re_p2   = Regex::new(r#""Playtime"\s*"(\d+)""#).ok();
-  let re_p3   = Regex::new(r#""MinutesPlayed\d*"\s*"(\d+)""#).ok();
-
-  for f in files {
-    if let Ok(txt) = std::fs::read_to_string(f) {
-      if let Some(cap) = re_apps.as_ref().and_then(|r| r.captures(&txt)) {
-        let body = cap.get(1).map(|m| m.as_str()).unwrap_or("");
-        if let Some(re) = &re_per {
-          for c in re.captures_iter(body) {
-            let appid: i64 = c.get(1).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
-            if appid == 0 { continue; }
-            let b = c.get(2).map(|m| m.as_str()).unwrap_or("");
-
-            let last  = re_last.as_ref()
-              .and_then(|r| r.captures(b))
-              .and_then(|c| c.get(1))
-              .and_then(|m| m.as_str().parse::<u64>().ok());
-
-            let playm = re_p1.as_ref()
-                .and_then(|r| r.captures(b)).and_then(|c| c.get(1))
-                .and_then(|m| m.as_str().parse::<u32>().ok())
-              .or_else(|| re_p2.as_ref()
-                .and_then(|r| r.captures(b)).and_then(|c| c.get(1))
-                .and_then(|m| m.as_str().parse::<u32>().ok()))
-              .or_else(|| re_p3.as_ref()
-                .and_then(|r| r.captures(b)).and_then(|c| c.get(1))
-                .and_then(|m| m.as_str().parse::<u32>().ok()));
-
-            let entry = out.entry(appid).or_insert(AppStat { playtime_minutes: None, last_played_unix: None });
-            if playm.is_some() { entry.playtime_minutes = playm; }
-            if last.is_some()  { entry.last_played_unix  = last;  }
-          }
-        }
-      }
-    }
-  }
-
-  out
+/// Лише userdata/<account>/: на спільному ПК інші акаунти мають власний час у тих самих іграх.
+fn collect_user_stats(
+  roots: &[PathBuf],
+  account: Option<u32>,
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<HashMap<i64, LocalAppStat>, String> {
+  cancel.check()?;
+  let Some(account) = account else {
+    progress.emit(ScanProgress::UserStats { files: 0, apps: 0 });
+    return Ok(HashMap::new());
+  };
+  let cfg = accounts::userdata_dirs(roots)
+    .into_iter()
+    .find(|(id, _)| *id == account)
+    .and_then(|(_, dir)| accounts::read_localconfig(&dir));
+
+  let files = usize::from(cfg.is_some());
+  let out = cfg.map(|c| c.apps).unwrap_or_default();
+  progress.emit(ScanProgress::UserStats { files, apps: out.len() });
+  Ok(out)
 }
 
-fn detect_accounts_sync() -> Vec<AccountHint> {
-  let mut out = vec![];
-  let re_user_block = Regex::new(r#"(?s)"(\d{17})"\s*\{(.*?)\}"#).ok();
-  let re_persona = Regex::new(r#""PersonaName"\s*"([^"]*)""#).ok();
-
-  for root in roots_from_detect_roots() {
-    let file = root.join("config").join("loginusers.vdf");
-    if !file.exists() { continue; }
-    if let Ok(txt) = std::fs::read_to_string(file) {
-      if let Some(re) = &re_user_block {
-        for cap in re.captures_iter(&txt) {
-          let id = cap.get(1).map(|m| m.as_str().to_string()).unwrap_or_default();
-          let body = cap.get(2).map(|m| m.as_str()).unwrap_or("");
-          let persona = re_persona.as_ref()
-            .and_then(|r| r.captures(body))
-            .and_then(|c| c.get(1))
-            .map(|m| m.as_str().to_string())
-            .unwrap_or_default();
-          if !id.is_empty() {
-            out.push(AccountHint { steamid64: id, persona });
-          }
-        }
-      }
-    }
-  }
-  out
+/// Найсвіжіший акаунт першим — онбординг підставляє `[0]`.
+pub(crate) fn detect_accounts_sync() -> Vec<AccountHint> {
+  accounts::detect(&roots_from_detect_roots())
 }
 
-
 #[tauri::command]
 pub async fn resolve_steamids_from_text(text: String) -> Vec<String> {
   let re = Regex::new(r"(76\d{15})").ok();
@@ -744,27 +905,19 @@ fn build_profile(lib: &Vec<LibraryGame>, meta: &HashMap<i64, AppMeta>) -> Player
   }
 }
 
-fn chrono_now_u64() -> u64 {
+pub(crate) fn chrono_now_u64() -> u64 {
   use std::time::{SystemTime, UNIX_EPOCH};
   SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
 }
 
 
-#[derive(Clone)]
-struct CandRow {
-  appid: i64,
-  name: String,
-  installed: bool,
-  playmin: u32,            
-  genres: Vec<String>,     
-  score: f32,              
-}
-
-fn prefilter_candidates(lib: &Vec<LibraryGame>, meta: &HashMap<i64, AppMeta>, profile: &PlayerProfile) -> Vec<CandRow> {
-  let mut rows: Vec<CandRow> = Vec::new();
+/// Усі кандидати від найкращого; скільки з них піде в промпт, вирішує prompt::assemble.
+/// `exclude` — appid, які вже пропонували або відхилили в цій розмові.
+fn prefilter_candidates(lib: &Vec<LibraryGame>, meta: &HashMap<i64, AppMeta>, profile: &PlayerProfile, exclude: &HashSet<i64>) -> Vec<Candidate> {
+  let mut rows: Vec<Candidate> = Vec::new();
 
   for g in lib {
-    if g.name.trim().is_empty() { continue; }
+    if g.name.trim().is_empty() || exclude.contains(&g.appid) { continue; }
     let genres = meta.get(&g.appid)
       .map(|m| m.genres.clone())
       .filter(|v| !v.is_empty())
@@ -794,7 +947,7 @@ fn prefilter_candidates(lib: &Vec<LibraryGame>, meta: &HashMap<i64, AppMeta>, pr
 
     let score = 0.8 * pref_sum + 0.4 * novelty + installed_bonus + never_bonus + barely_bonus;
 
-    rows.push(CandRow {
+    rows.push(Candidate {
       appid: g.appid,
       name: g.name.clone(),
       installed: g.installed,
@@ -806,17 +959,19 @@ fn prefilter_candidates(lib: &Vec<LibraryGame>, meta: &HashMap<i64, AppMeta>, pr
 
   rows.sort_by(|a,b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
 
-  let mut kept: Vec<CandRow> = Vec::new();
+  let mut kept: Vec<Candidate> = Vec::new();
+  let mut deferred: Vec<Candidate> = Vec::new();
   let mut per_gen: HashMap<String, u32> = HashMap::new();
 
+  // різноманіття: понад 6 ігор одного жанру — у кінець черги, а не геть
   for r in rows {
     let lead = r.genres.get(0).cloned().unwrap_or_else(|| "misc".into());
     let cnt = per_gen.entry(lead.clone()).or_insert(0);
-    if *cnt >= 6 { continue; }
+    if *cnt >= 6 { deferred.push(r); continue; }
     kept.push(r);
     *cnt += 1;
-    if kept.len() >= 20 { break; }
   }
+  kept.extend(deferred);
 
   kept
 }
@@ -847,66 +1002,225 @@ fn make_profile_json(profile: &PlayerProfile) -> String {
   serde_json::to_string(&p).unwrap_or("{}".into())
 }
 
-fn make_candidates_tsv(cands: &[CandRow]) -> String {
-  let mut s = String::from("appid\tname\tinstalled\n");
-  for r in cands {
-    let line = format!(
-      "{}\t{}\t{}\n",
-      r.appid,
-      r.name.replace('\t', " ").replace('\n', " "),
-      if r.installed { 1 } else { 0 },
-    );
-    s.push_str(&line);
-  }
-  s
-}
-
-fn build_llm_prompt(user_text: &str, profile_json: &str, candidates_tsv: &str) -> (String, String) {
-  let system = "Ти — локальний асистент SteamGameHelper. Відповідай мовою користувача, лаконічно. \
-  Із наданого профілю й кандидатів вибери 5–7 найкращих і поверни СТРОГО JSON: \
+// незмінний між запитами: тепла сесія тримає його в KV-кеші (llm_backend::warm_up)
+pub(crate) const LLM_SYSTEM_PROMPT: &str = "Ти — локальний асистент SteamGameHelper. Відповідай мовою користувача, лаконічно. \
+  Із наданого профілю й кандидатів вибери 3–7 найкращих і поверни СТРОГО JSON: \
   {\"picks\":[{\"appid\":<number>,\"reason\":\"<<=120 символів>\"}, ...]}. \
   Не вигадуй ігор поза переліком. Причини конкретні (кооп/коротка/жанр/встановлено), без повторів.";
 
-  let user = format!(
-    "{}\n\n[profile]\n{}\n\n[candidates_tsv]\n{}",
-    user_text.trim(),
-    profile_json.trim(),
-    candidates_tsv.trim()
-  );
+use crate::chat_template::ChatMessage;
+use crate::conversation::{self, Conversation};
+use crate::prompt::{self, Budget, PromptDebug, TokenCounter};
+use crate::llm_backend::{self, BackendKind, GenOptions, LlmBackend, LocalLlama, QueuePolicy, StopReason};
+use crate::openai_compat::{self, OpenAiCompat};
 
-  (system.to_string(), user)
+#[derive(Deserialize)]
+pub struct ChatUserMsg {
+  pub text: String,
+  /// без id — одноразове питання без історії
+  #[serde(default)]
+  pub chat_id: Option<String>,
+  /// appid, від яких користувач відмовився після попередньої відповіді
+  #[serde(default)]
+  pub reject: Vec<i64>,
 }
 
+/// Готовий запит до моделі і розмова, в яку потім допишемо відповідь.
+struct ChatTurn {
+  system: String,
+  messages: Vec<ChatMessage>,
+  cands: Vec<Candidate>,
+  chat_id: Option<String>,
+  debug: PromptDebug,
+}
 
-use crate::llm_backend; 
-
-#[derive(Deserialize)]
-pub struct ChatUserMsg { pub text: String }
-
+/// Токенізатор з'являється лише з моделлю в пам'яті: до першого запиту її ще
+/// немає, і промпт рахувався б оцінкою. Модель уже завантажена — не чекаємо.
+async fn local_token_counter() -> Option<crate::prompt::CountFn> {
+  if let Some(count) = llm_backend::token_counter() {
+    return Some(count);
+  }
+  match tauri::async_runtime::spawn_blocking(llm_backend::ensure_loaded).await {
+    Ok(Ok(())) => llm_backend::token_counter(),
+    Ok(Err(e)) => {
+      log::warn!("LLM model not loaded, approximate token counts: {e}");
+      None
+    }
+    Err(e) => {
+      log::warn!("LLM model load task failed: {e}");
+      None
+    }
+  }
+}
 
-#[tauri::command]
-pub async fn llm_chat<R: Runtime>(app: tauri::AppHandle<R>, user: ChatUserMsg) -> Result<String, String> {
+/// Спільна підготовка для llm_chat і llm_chat_stream: профіль гравця + кандидати + хвіст історії
+/// у межах контексту моделі.
+async fn llm_prompt_for<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &UserPrefs, msg: &ChatUserMsg) -> Result<ChatTurn, String> {
   let lib = load_library_cache(app.clone()).await.unwrap_or_default();
   if lib.is_empty() {
     return Err("Library is empty. Scan your library first.".into());
   }
 
-  let mut meta = load_meta_cache(&app).unwrap_or_default();
+  let mut meta = load_meta_cache(app).unwrap_or_default();
   ensure_basic_meta(&lib, &mut meta);
-  save_meta_cache(&app, &meta).ok();
+  save_meta_cache(app, &meta).ok();
   let profile = build_profile(&lib, &meta);
 
-  let cands = prefilter_candidates(&lib, &meta, &profile);
+  // відмови зберігаємо одразу: наступний запит до розмови їх уже бачить
+  let conversation = match msg.chat_id.as_deref() {
+    Some(id) if !msg.reject.is_empty() => Some(conversation::update(app, id, |c| c.reject(&msg.reject))?),
+    Some(id) => Some(conversation::load(app, id)?),
+    None => None,
+  };
+  let excluded = conversation.as_ref().map(Conversation::excluded).unwrap_or_default();
+  let mut cands = prefilter_candidates(&lib, &meta, &profile, &excluded);
+  if cands.is_empty() && !excluded.is_empty() {
+    // усе вже пропонували — по другому колу, але без відхилених
+    let rejected = conversation.as_ref().map(|c| c.rejected.iter().copied().collect()).unwrap_or_default();
+    cands = prefilter_candidates(&lib, &meta, &profile, &rejected);
+  }
   if cands.is_empty() {
     return Err("No candidates to recommend. Try scanning again.".into());
   }
   let profile_json = make_profile_json(&profile);
-  let cands_tsv = make_candidates_tsv(&cands);
 
-  let (system, user_prompt) = build_llm_prompt(&user.text, &profile_json, &cands_tsv);
+  // токенізатор локальної моделі серверу не підходить — там лише оцінка
+  let (counter, n_ctx) = match prefs.llm_backend {
+    BackendKind::Local => (TokenCounter::new(local_token_counter().await), llm_backend::context_tokens()),
+    BackendKind::OpenaiCompat => (
+      TokenCounter::approx(),
+      prefs.ai_context_tokens.map_or(openai_compat::DEFAULT_CONTEXT_TOKENS, |n| n as usize),
+    ),
+  };
+  let budget = Budget { n_ctx, max_tokens: picks::PICKS_MAX_TOKENS };
+  // користувач міг вставити ключ у питання — у модель він не потрапляє
+  let question = redact::scrub(&msg.text);
+  let a = prompt::assemble(LLM_SYSTEM_PROMPT, &question, &profile_json, &cands, conversation.as_ref(), budget, &counter)?;
+  for d in &a.debug.dropped {
+    log::debug!("prompt: {d}");
+  }
+  Ok(ChatTurn { system: LLM_SYSTEM_PROMPT.to_string(), messages: a.messages, cands: a.cands, chat_id: msg.chat_id.clone(), debug: a.debug })
+}
+
+/// Дописати питання й показані рекомендації в розмову, якщо вона є.
+fn record_turn<R: Runtime>(app: &tauri::AppHandle<R>, chat_id: Option<&str>, text: &str, picks: &Picks) {
+  let Some(id) = chat_id else { return; };
+  let text = redact::scrub(text);
+  if let Err(e) = conversation::update(app, id, |c| c.record(&text, picks, chrono_now_u64())) {
+    log::warn!("conversation save failed: {e}");
+  }
+}
 
-  match llm_backend::answer(&system, &user_prompt) {
-    Ok(text) => Ok(text),
-    Err(e) => Err(format!("LLM error: {e}")),
+/// Вивід обмежений граматикою picks з appid лише серед `cands` (сервер — лише JSON mode).
+fn picks_options(prefs: &UserPrefs, cands: &[Candidate]) -> GenOptions {
+  GenOptions {
+    template: prefs.llm_template,
+    grammar: Some(picks::grammar(cands)),
+    max_tokens: Some(picks::PICKS_MAX_TOKENS),
+    json: true,
   }
 }
+
+/// Бекенд із налаштувань. Помилку конфігурації віддаємо як помилку генерації — буде евристика.
+fn llm_backend_for<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &UserPrefs) -> Result<Box<dyn LlmBackend>, String> {
+  match prefs.llm_backend {
+    BackendKind::Local => Ok(Box::new(LocalLlama)),
+    BackendKind::OpenaiCompat => {
+      let key = secrets::get(app, Secret::AiApiKey)?.filter(|k| !k.trim().is_empty());
+      Ok(Box::new(OpenAiCompat::new(&prefs.ai_base_url, key, &prefs.ai_model)?))
+    }
+  }
+}
+
+/// Рекомендації з бібліотеки; якщо модель недоступна або відповіла не за схемою — евристичні.
+#[tauri::command]
+pub async fn llm_chat<R: Runtime>(app: tauri::AppHandle<R>, user: ChatUserMsg) -> Result<Picks, String> {
+  let prefs = load_prefs_internal(&app)?;
+  let turn = llm_prompt_for(&app, &prefs, &user).await?;
+  let opts = picks_options(&prefs, &turn.cands);
+  let backend = llm_backend_for(&app, &prefs);
+
+  let ticket = llm_backend::begin(QueuePolicy::Queue)?;
+  let (system, messages) = (turn.system, turn.messages);
+  let res = tauri::async_runtime::spawn_blocking(move || {
+    backend?.generate(&system, &messages, &opts, &ticket, &mut |_| true)
+  })
+  .await
+  .map_err(|e| e.to_string())?;
+  let picks = match res {
+    Ok(g) => picks::resolve(Some(&g.text), &turn.cands),
+    Err(e) => {
+      log::warn!("LLM unavailable, heuristic picks: {}", redact::scrub(&e));
+      picks::resolve(None, &turn.cands)
+    }
+  };
+  record_turn(&app, turn.chat_id.as_deref(), &user.text, &picks);
+  Ok(picks)
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LlmStreamEvent {
+  /// першим: id для cancel_llm (запит може ще чекати в черзі)
+  Started { id: u64 },
+  /// як зібрано промпт: скільки кандидатів і реплік історії влізло в контекст
+  Prompt { debug: PromptDebug },
+  Chunk { text: String },
+  /// `text` — уся відповідь без стоп-послідовностей і пробілів по краях, `picks` — перевірені рекомендації з неї
+  Done { text: String, stop: StopReason, picks: Picks },
+  /// модель не відпрацювала: евристичні рекомендації замість відповіді
+  Fallback { error: String, picks: Picks },
+}
+
+/// Те саме, що llm_chat, але шматки відповіді йдуть у `on_event` у міру генерації.
+/// `policy` — що робити, якщо модель зайнята іншим запитом (за замовчуванням чекати).
+#[tauri::command]
+pub async fn llm_chat_stream<R: Runtime>(
+  app: tauri::AppHandle<R>,
+  user: ChatUserMsg,
+  policy: Option<QueuePolicy>,
+  on_event: Channel<LlmStreamEvent>,
+) -> Result<(), String> {
+  let ticket = llm_backend::begin(policy.unwrap_or_default())?;
+  let _ = on_event.send(LlmStreamEvent::Started { id: ticket.id });
+  let prefs = load_prefs_internal(&app)?;
+  let turn = llm_prompt_for(&app, &prefs, &user).await?;
+  let _ = on_event.send(LlmStreamEvent::Prompt { debug: turn.debug.clone() });
+  let opts = picks_options(&prefs, &turn.cands);
+  let backend = llm_backend_for(&app, &prefs);
+
+  let chan = on_event.clone();
+  let (system, messages) = (turn.system, turn.messages);
+  let res = tauri::async_runtime::spawn_blocking(move || {
+    // закрите вікно — send падає, і генерація зупиняється як Cancelled
+    backend?.generate(&system, &messages, &opts, &ticket, &mut |text| {
+      chan.send(LlmStreamEvent::Chunk { text: text.to_string() }).is_ok()
+    })
+  })
+  .await
+  .map_err(|e| e.to_string())?;
+
+  match res {
+    Ok(g) => {
+      let picks = picks::resolve(Some(&g.text), &turn.cands);
+      // скасовану відповідь користувач не бачив — в історію її не пишемо
+      if g.stop != StopReason::Cancelled {
+        record_turn(&app, turn.chat_id.as_deref(), &user.text, &picks);
+      }
+      let _ = on_event.send(LlmStreamEvent::Done { text: g.text, stop: g.stop, picks });
+    }
+    Err(e) => {
+      let error = redact::scrub(&format!("LLM error: {e}"));
+      let picks = picks::resolve(None, &turn.cands);
+      record_turn(&app, turn.chat_id.as_deref(), &user.text, &picks);
+      let _ = on_event.send(LlmStreamEvent::Fallback { error, picks });
+    }
+  }
+  Ok(())
+}
+
+/// Зупинити генерацію `id` або, без id, усі запити до LLM (і ті, що в черзі).
+#[tauri::command]
+pub fn cancel_llm(id: Option<u64>) -> bool {
+  llm_backend::cancel(id)
+}
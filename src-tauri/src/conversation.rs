@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Runtime;
+
+use crate::chat_template::{ChatMessage, Role};
+use crate::picks::Picks;
+use crate::profiles;
+
+// ====================== Розмови з асистентом ======================
+//
+// UI передає chat_id разом із питанням; історія, запропоновані та відхилені
+// appid лежать у conversations.json активного профілю. Запропоновані й
+// відхилені ігри не потрапляють у наступний prefilter_candidates, тож
+// «щось інше» справді дає інше. У промпт іде лише хвіст історії, що
+// вміщується у свою частку контексту (prompt::assemble).
+
+const FILE: &str = "conversations.json";
+const MAX_CONVERSATIONS: usize = 20;
+// зберігаємо більше, ніж влазить у контекст: решту відріже window()
+const MAX_MESSAGES: usize = 40;
+
+// читання-зміна-запис файлу з усіма розмовами
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Conversation {
+  pub id: String,
+  pub messages: Vec<ChatMessage>,
+  pub suggested: Vec<i64>,
+  pub rejected: Vec<i64>,
+  pub updated_at: u64,
+}
+
+fn push_unique(list: &mut Vec<i64>, appids: impl IntoIterator<Item = i64>) {
+  for id in appids {
+    if !list.contains(&id) {
+      list.push(id);
+    }
+  }
+}
+
+impl Conversation {
+  /// Що не пропонувати знову.
+  pub fn excluded(&self) -> HashSet<i64> {
+    self.suggested.iter().chain(&self.rejected).copied().collect()
+  }
+
+  pub fn reject(&mut self, appids: &[i64]) {
+    push_unique(&mut self.rejected, appids.iter().copied());
+  }
+
+  /// Дописати репліку користувача (без профілю й кандидатів) і рекомендації у відповідь.
+  pub fn record(&mut self, user_text: &str, picks: &Picks, now: u64) {
+    self.messages.push(ChatMessage::user(user_text.trim()));
+    self.messages.push(ChatMessage::assistant(picks.as_reply()));
+    if self.messages.len() > MAX_MESSAGES {
+      self.messages.drain(..self.messages.len() - MAX_MESSAGES);
+    }
+    push_unique(&mut self.suggested, picks.picks.iter().map(|p| p.appid));
+    self.updated_at = now;
+  }
+
+  /// Найновіші репліки в межах `budget_tokens`, де `cost` — ціна репліки в токенах;
+  /// вікно завжди починається з репліки користувача.
+  pub fn window(&self, budget_tokens: usize, cost: impl Fn(&str) -> usize) -> &[ChatMessage] {
+    let mut used = 0;
+    let mut start = self.messages.len();
+    for (i, m) in self.messages.iter().enumerate().rev() {
+      used += cost(&m.text);
+      if used > budget_tokens {
+        break;
+      }
+      start = i;
+    }
+    while self.messages.get(start).is_some_and(|m| m.role != Role::User) {
+      start += 1;
+    }
+    &self.messages[start..]
+  }
+}
+
+fn is_valid_id(id: &str) -> bool {
+  !id.is_empty() && id.len() <= 64 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(profiles::active_dir(app)?.join(FILE))
+}
+
+/// Відсутній файл — порожній список. Зіпсований — помилка: інакше наступний
+/// save перезаписав би всі розмови однією.
+fn read_all(path: &Path) -> Result<Vec<Conversation>, String> {
+  let bytes = match fs::read(path) {
+    Ok(b) => b,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(e) => return Err(e.to_string()),
+  };
+  serde_json::from_slice(&bytes).map_err(|e| format!("{FILE} is corrupted: {e}"))
+}
+
+/// Через тимчасовий файл: обірваний запис не залишить напівфайлу.
+fn write_all(path: &Path, all: &[Conversation]) -> Result<(), String> {
+  let data = serde_json::to_vec_pretty(all).map_err(|e| e.to_string())?;
+  let tmp = path.with_extension("json.tmp");
+  fs::write(&tmp, data).map_err(|e| e.to_string())?;
+  fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, ()>, String> {
+  FILE_LOCK.lock().map_err(|_| "conversation lock poisoned".to_string())
+}
+
+/// Збережена розмова або нова порожня з цим id.
+pub fn load<R: Runtime>(app: &tauri::AppHandle<R>, id: &str) -> Result<Conversation, String> {
+  if !is_valid_id(id) {
+    return Err(format!("Invalid chat id: {id}"));
+  }
+  let path = path(app)?;
+  let _guard = lock()?;
+  let found = read_all(&path)?.into_iter().find(|c| c.id == id);
+  Ok(found.unwrap_or_else(|| Conversation { id: id.into(), ..Default::default() }))
+}
+
+/// Змінити розмову `id` (або нову порожню) під FILE_LOCK: читаємо свіжу з файлу,
+/// а не знімок із початку запиту, тож паралельні запити до тієї ж розмови не
+/// затирають історію, suggested і rejected одне одного. Найстаріші понад
+/// MAX_CONVERSATIONS видаляються.
+pub fn update<R: Runtime>(
+  app: &tauri::AppHandle<R>,
+  id: &str,
+  f: impl FnOnce(&mut Conversation),
+) -> Result<Conversation, String> {
+  if !is_valid_id(id) {
+    return Err(format!("Invalid chat id: {id}"));
+  }
+  update_at(&path(app)?, id, f)
+}
+
+fn update_at(path: &Path, id: &str, f: impl FnOnce(&mut Conversation)) -> Result<Conversation, String> {
+  let _guard = lock()?;
+  let mut all = read_all(path)?;
+  let mut conv = match all.iter().position(|c| c.id == id) {
+    Some(i) => all.remove(i),
+    None => Conversation { id: id.into(), ..Default::default() },
+  };
+  f(&mut conv);
+  all.push(conv.clone());
+  all.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+  all.truncate(MAX_CONVERSATIONS);
+  write_all(path, &all)?;
+  Ok(conv)
+}
+
+#[tauri::command]
+pub async fn get_conversation<R: Runtime>(app: tauri::AppHandle<R>, chat_id: String) -> Result<Conversation, String> {
+  load(&app, &chat_id)
+}
+
+/// Забути історію та відхилені ігри; повертає, чи було що видаляти.
+#[tauri::command]
+pub async fn delete_conversation<R: Runtime>(app: tauri::AppHandle<R>, chat_id: String) -> Result<bool, String> {
+  let path = path(&app)?;
+  let _guard = lock()?;
+  let mut all = read_all(&path)?;
+  let before = all.len();
+  all.retain(|c| c.id != chat_id);
+  if all.len() == before {
+    return Ok(false);
+  }
+  write_all(&path, &all)?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::picks::{PickSource, Recommendation};
+
+  fn picks(appids: &[i64]) -> Picks {
+    let picks = appids
+      .iter()
+      .map(|&appid| Recommendation { appid, name: format!("app {appid}"), reason: "коротка".into(), installed: true })
+      .collect();
+    Picks { source: PickSource::Llm, picks }
+  }
+
+  #[test]
+  fn suggested_and_rejected_are_excluded() {
+    let mut c = Conversation::default();
+    c.record("щось на вечір", &picks(&[620, 400]), 1);
+    c.reject(&[1145360, 620]);
+    c.record("щось коротше", &picks(&[400, 70]), 2);
+    assert_eq!(c.suggested, vec![620, 400, 70]);
+    assert_eq!(c.rejected, vec![1145360, 620]);
+    assert_eq!(c.excluded(), HashSet::from([620, 400, 70, 1145360]));
+    assert_eq!(c.messages.len(), 4);
+    assert!(c.messages[1].text.contains("\"appid\":620"));
+  }
+
+  #[test]
+  fn corrupted_file_is_not_overwritten() {
+    let dir = std::env::temp_dir().join(format!("sgh_conv_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(FILE);
+    let _ = fs::remove_file(&path);
+    assert!(read_all(&path).unwrap().is_empty());
+
+    let c = Conversation { id: "a".into(), updated_at: 1, ..Default::default() };
+    write_all(&path, &[c]).unwrap();
+    assert_eq!(read_all(&path).unwrap()[0].id, "a");
+    assert!(!path.with_extension("json.tmp").exists());
+
+    fs::write(&path, b"[{\"id\": \"a\",").unwrap();
+    assert!(read_all(&path).is_err());
+    let _ = fs::remove_dir_all(dir);
+  }
+
+  #[test]
+  fn overlapping_updates_keep_each_other() {
+    let dir = std::env::temp_dir().join(format!("sgh_conv_upd_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(FILE);
+    let _ = fs::remove_file(&path);
+
+    // два запити до тієї ж розмови: відмова з одного, відповідь з іншого
+    std::thread::scope(|s| {
+      s.spawn(|| update_at(&path, "c", |c| c.reject(&[620])).unwrap());
+      s.spawn(|| update_at(&path, "c", |c| c.record("щось", &picks(&[400]), 5)).unwrap());
+    });
+    update_at(&path, "other", |c| c.updated_at = 9).unwrap();
+
+    let all = read_all(&path).unwrap();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].id, "other");
+    let c = all.iter().find(|c| c.id == "c").unwrap();
+    assert_eq!((c.rejected.as_slice(), c.suggested.as_slice(), c.messages.len()), (&[620][..], &[400][..], 2));
+    let _ = fs::remove_dir_all(dir);
+  }
+
+  #[test]
+  fn window_keeps_newest_turns_starting_with_user() {
+    let mut c = Conversation::default();
+    for i in 0..30 {
+      c.record(&format!("питання {i} {}", "x".repeat(60)), &picks(&[i]), i as u64);
+    }
+    assert_eq!(c.messages.len(), MAX_MESSAGES);
+    let cost = |t: &str| t.chars().count() / 3 + 4;
+    assert!(c.window(0, cost).is_empty());
+
+    let w = c.window(200, cost);
+    assert!(!w.is_empty() && w.len() < c.messages.len());
+    assert_eq!(w[0].role, Role::User);
+    assert_eq!(w.last(), c.messages.last());
+    assert!(w.iter().map(|m| cost(&m.text)).sum::<usize>() <= 200);
+    assert_eq!(c.window(usize::MAX, cost).len(), MAX_MESSAGES);
+  }
+}
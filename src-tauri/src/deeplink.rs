@@ -0,0 +1,382 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager, Runtime, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::commands;
+use crate::steamid;
+
+// ====================== Deep links: steamgamehelper:// ======================
+//
+//   steamgamehelper://import-family?ids=7656119...,7656119...
+//   steamgamehelper://recommend?mood=...
+//   steamgamehelper://game/<appid>
+//
+// Посилання може прийти з будь-якої сторінки в браузері, тому все, що не
+// збігається з цими формами до символу, відкидаємо й лише логуємо.
+//
+// Windows/Linux запускають для посилання новий процес. Плагін single-instance
+// передає його argv уже запущеному екземпляру й завершує новий, тож посилання
+// обробляє один процес з одним фоновим оновленням і одним inbox-watcher.
+
+pub const SCHEME: &str = "steamgamehelper";
+pub const EVENT_DEEP_LINK: &str = "deep-link-action";
+const EVENT_FAMILY_INGESTED: &str = "family_ids_ingested";
+
+const MAX_FAMILY_IDS: usize = 32;
+const MAX_MOOD_CHARS: usize = 200;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+  ImportFamily { ids: Vec<String> },
+  Recommend { mood: String },
+  Game { appid: u32 },
+}
+
+impl DeepLinkAction {
+  fn kind(&self) -> &'static str {
+    match self {
+      DeepLinkAction::ImportFamily { .. } => "import_family",
+      DeepLinkAction::Recommend { .. } => "recommend",
+      DeepLinkAction::Game { .. } => "game",
+    }
+  }
+}
+
+/// Єдиний параметр запиту `name`; дублікати й сторонні параметри — помилка.
+fn single_param(url: &Url, name: &str) -> Result<String, String> {
+  let mut found = None;
+  for (k, v) in url.query_pairs() {
+    if k != name || found.is_some() {
+      return Err(format!("unexpected query parameter: {k}"));
+    }
+    found = Some(v.into_owned());
+  }
+  found.ok_or_else(|| format!("missing query parameter: {name}"))
+}
+
+fn parse_family_ids(raw: &str) -> Result<Vec<String>, String> {
+  let ids: Vec<String> = raw.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+  if ids.is_empty() || ids.len() > MAX_FAMILY_IDS {
+    return Err(format!("expected 1..={MAX_FAMILY_IDS} SteamID64 values"));
+  }
+  for id in &ids {
+    steamid::parse_steamid64(id).map_err(|e| format!("{id}: {}", e.message()))?;
+  }
+  Ok(commands::dedup_ids(ids))
+}
+
+fn parse_mood(raw: &str) -> Result<String, String> {
+  let mood = raw.trim();
+  if mood.is_empty() {
+    return Err("mood is empty".into());
+  }
+  if mood.chars().count() > MAX_MOOD_CHARS {
+    return Err(format!("mood is longer than {MAX_MOOD_CHARS} characters"));
+  }
+  if mood.chars().any(char::is_control) {
+    return Err("mood contains control characters".into());
+  }
+  Ok(mood.to_string())
+}
+
+fn parse_appid(raw: &str) -> Result<u32, String> {
+  if raw.is_empty() || raw.len() > 10 || !raw.bytes().all(|b| b.is_ascii_digit()) {
+    return Err("appid must be a positive integer".into());
+  }
+  match raw.parse::<u32>() {
+    Ok(id) if id > 0 => Ok(id),
+    _ => Err("appid must be a positive integer".into()),
+  }
+}
+
+pub fn parse(url: &Url) -> Result<DeepLinkAction, String> {
+  if url.scheme() != SCHEME {
+    return Err(format!("unsupported scheme: {}", url.scheme()));
+  }
+  if !url.username().is_empty() || url.password().is_some() || url.port().is_some() || url.fragment().is_some() {
+    return Err("unexpected URL components".into());
+  }
+  let host = url.host_str().unwrap_or_default();
+  let path = url.path();
+
+  match host {
+    "import-family" if path.is_empty() || path == "/" => {
+      Ok(DeepLinkAction::ImportFamily { ids: parse_family_ids(&single_param(url, "ids")?)? })
+    }
+    "recommend" if path.is_empty() || path == "/" => {
+      Ok(DeepLinkAction::Recommend { mood: parse_mood(&single_param(url, "mood")?)? })
+    }
+    "game" => {
+      if url.query().is_some() {
+        return Err("game link takes no query".into());
+      }
+      let raw = path.strip_prefix('/').unwrap_or_default();
+      Ok(DeepLinkAction::Game { appid: parse_appid(raw.strip_suffix('/').unwrap_or(raw))? })
+    }
+    _ => Err(format!("unsupported deep link: {host}{path}")),
+  }
+}
+
+pub(crate) fn focus_main<R: Runtime>(app: &tauri::AppHandle<R>) {
+  if let Some(w) = app.get_webview_window("main") {
+    let _ = w.unminimize();
+    let _ = w.set_focus();
+  }
+}
+
+// ---------------------- Підтвердження ----------------------
+//
+// import-family змінює збережені налаштування, recommend іде в LLM — обидва
+// виконуємо лише після «OK» у діалозі. Одночасно відкрите лише одне вікно:
+// сторінка, що шле посилання в циклі, не засипле екран діалогами.
+
+static CONFIRMING: AtomicBool = AtomicBool::new(false);
+
+fn confirm_text(action: &DeepLinkAction) -> Option<String> {
+  match action {
+    DeepLinkAction::ImportFamily { ids } => Some(format!(
+      "Сторінка в браузері пропонує додати до family-списку {} SteamID:\n\n{}\n\nДодати?",
+      ids.len(),
+      ids.join("\n")
+    )),
+    DeepLinkAction::Recommend { mood } => Some(format!(
+      "Сторінка в браузері просить асистента порадити гру:\n\n«{mood}»\n\nНадіслати запит?"
+    )),
+    DeepLinkAction::Game { .. } => None,
+  }
+}
+
+/// Блокує потік до відповіді — викликати лише поза потоком подій.
+/// `false`, якщо користувач відмовився або вже відкрито інше підтвердження.
+pub(crate) fn confirm_blocking<R: Runtime>(app: &tauri::AppHandle<R>, text: String) -> bool {
+  use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+  if CONFIRMING.swap(true, Ordering::SeqCst) {
+    log::warn!("deep link dropped: another confirmation is open");
+    return false;
+  }
+  focus_main(app);
+  let ok = app
+    .dialog()
+    .message(text)
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancel)
+    .blocking_show();
+  CONFIRMING.store(false, Ordering::SeqCst);
+  ok
+}
+
+/// Як і ручне введення: у family-список активного профілю, без дублікатів і без власного акаунта.
+pub(crate) fn import_family<R: Runtime>(app: &tauri::AppHandle<R>, ids: &[String]) -> Result<(), String> {
+  let mut prefs = commands::load_prefs_internal(app)?;
+  let mut merged = prefs.family_ids.clone();
+  merged.extend(ids.iter().filter(|id| **id != prefs.main_steam_id64).cloned());
+  prefs.family_ids = commands::dedup_ids(merged);
+  commands::save_prefs_internal(app, &prefs)?;
+  let _ = app.emit(EVENT_FAMILY_INGESTED, prefs.family_ids.join(", "));
+  Ok(())
+}
+
+// ---------------------- Доставка у вебв'ю ----------------------
+//
+// Поки вебв'ю не запитало дії якогось виду (take_pending_deep_links), подія
+// загубилася б — тримаємо такі дії в черзі. Після першого запиту шлемо подією.
+// Рішення приймаємо під тим самим замком, що й take, тож дія не губиться й не
+// дублюється, якщо UI підписується на подію ДО виклику take.
+
+const MAX_QUEUED: usize = 8;
+
+struct Delivery {
+  queued: Vec<DeepLinkAction>,
+  ready: Vec<&'static str>,
+}
+
+static DELIVERY: Mutex<Delivery> = Mutex::new(Delivery { queued: Vec::new(), ready: Vec::new() });
+
+fn deliver<R: Runtime>(app: &tauri::AppHandle<R>, action: DeepLinkAction) -> Result<(), String> {
+  let mut g = DELIVERY.lock().map_err(|e| e.to_string())?;
+  if g.ready.contains(&action.kind()) {
+    drop(g);
+    focus_main(app);
+    return app.emit(EVENT_DEEP_LINK, &action).map_err(|e| e.to_string());
+  }
+  if g.queued.len() >= MAX_QUEUED {
+    return Err("too many queued deep links".into());
+  }
+  if !g.queued.contains(&action) {
+    g.queued.push(action);
+  }
+  Ok(())
+}
+
+fn dispatch<R: Runtime>(app: &tauri::AppHandle<R>, action: DeepLinkAction) -> Result<(), String> {
+  if let Some(text) = confirm_text(&action) {
+    if !confirm_blocking(app, text) {
+      log::info!("deep link {} declined", action.kind());
+      return Ok(());
+    }
+  }
+  if let DeepLinkAction::ImportFamily { ids } = &action {
+    import_family(app, ids)?;
+  }
+  deliver(app, action)
+}
+
+fn handle<R: Runtime>(app: &tauri::AppHandle<R>, urls: Vec<Url>) {
+  for url in urls {
+    match parse(&url) {
+      // діалог підтвердження блокує — не в потоці подій
+      Ok(action) => {
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+          if let Err(e) = dispatch(&app, action) {
+            log::warn!("deep link failed: {e}");
+          }
+        });
+      }
+      // сам URL не логуємо: у ньому можуть бути довільні дані зі сторінки
+      Err(e) => log::warn!("deep link rejected: {e}"),
+    }
+  }
+}
+
+/// Викликати з `setup`: посилання, з яким запустили застосунок, плюс усі наступні.
+/// Посилання, відкриті при вже запущеному застосунку, плагін single-instance
+/// пересилає сюди ж через on_open_url.
+pub fn init<R: Runtime>(app: &tauri::AppHandle<R>) {
+  #[cfg(any(windows, target_os = "linux"))]
+  if cfg!(debug_assertions) {
+    // інсталятор реєструє схему сам; у dev-збірках — вручну
+    if let Err(e) = app.deep_link().register_all() {
+      log::warn!("deep link registration failed: {e}");
+    }
+  }
+
+  if let Ok(Some(urls)) = app.deep_link().get_current() {
+    handle(app, urls);
+  }
+  let h = app.clone();
+  app.deep_link().on_open_url(move |ev| handle(&h, ev.urls()));
+}
+
+/// Для UI після підписки на `deep-link-action`: підтверджені дії заданого виду
+/// (`game`, `recommend`, ...), що прийшли раніше. Далі цей вид іде подією.
+#[tauri::command]
+pub fn take_pending_deep_links(action: String) -> Vec<DeepLinkAction> {
+  let Ok(mut g) = DELIVERY.lock() else { return vec![]; };
+  let (take, keep): (Vec<_>, Vec<_>) = g.queued.drain(..).partition(|a| a.kind() == action);
+  g.queued = keep;
+  if let Some(kind) = ["import_family", "recommend", "game"].into_iter().find(|k| *k == action) {
+    if !g.ready.contains(&kind) {
+      g.ready.push(kind);
+    }
+  }
+  take
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn link(raw: &str) -> Result<DeepLinkAction, String> {
+    parse(&Url::parse(raw).unwrap())
+  }
+
+  const A: &str = "76561197960287930";
+  const B: &str = "76561198000000000";
+
+  #[test]
+  fn valid_actions() {
+    assert_eq!(
+      link(&format!("steamgamehelper://import-family?ids={A},%20{B},{A}")),
+      Ok(DeepLinkAction::ImportFamily { ids: vec![A.into(), B.into()] })
+    );
+    assert_eq!(
+      link(&format!("steamgamehelper://import-family/?ids={A}")),
+      Ok(DeepLinkAction::ImportFamily { ids: vec![A.into()] })
+    );
+    assert_eq!(
+      link("steamgamehelper://recommend?mood=%D1%89%D0%BE%D1%81%D1%8C+%D0%BA%D0%BE%D1%80%D0%BE%D1%82%D0%BA%D0%B5"),
+      Ok(DeepLinkAction::Recommend { mood: "щось коротке".into() })
+    );
+    assert_eq!(link("steamgamehelper://game/620"), Ok(DeepLinkAction::Game { appid: 620 }));
+    assert_eq!(link("steamgamehelper://game/620/"), Ok(DeepLinkAction::Game { appid: 620 }));
+  }
+
+  #[test]
+  fn unknown_host_scheme_or_components() {
+    for raw in [
+      "steamgamehelper://settings?ids=1",
+      "steamgamehelper://",
+      "steamgamehelper:import-family?ids=76561197960287930",
+      "https://import-family/?ids=76561197960287930",
+      "steamgamehelper://user@game/620",
+      "steamgamehelper://game:8080/620",
+      "steamgamehelper://game/620#x",
+    ] {
+      assert!(link(raw).is_err(), "{raw}");
+    }
+  }
+
+  #[test]
+  fn extra_path_segments() {
+    for raw in [
+      &format!("steamgamehelper://import-family/extra?ids={A}"),
+      "steamgamehelper://recommend/x?mood=calm",
+      "steamgamehelper://game/620/extra",
+      "steamgamehelper://game",
+      "steamgamehelper://game/",
+    ] {
+      assert!(link(raw).is_err(), "{raw}");
+    }
+  }
+
+  #[test]
+  fn duplicated_missing_or_foreign_params() {
+    for raw in [
+      &format!("steamgamehelper://import-family?ids={A}&ids={B}"),
+      &format!("steamgamehelper://import-family?ids={A}&x=1"),
+      "steamgamehelper://import-family",
+      "steamgamehelper://import-family?mood=calm",
+      "steamgamehelper://recommend?mood=a&mood=b",
+      "steamgamehelper://recommend",
+      "steamgamehelper://game/620?x=1",
+    ] {
+      assert!(link(raw).is_err(), "{raw}");
+    }
+  }
+
+  #[test]
+  fn bad_steamids() {
+    assert!(parse_family_ids("").is_err());
+    assert!(parse_family_ids(" , ,").is_err());
+    assert!(parse_family_ids("123").is_err());
+    // account id 0, група, зайва цифра
+    assert!(parse_family_ids("76561197960265728").is_err());
+    assert!(parse_family_ids("103582791429521412").is_err());
+    assert!(parse_family_ids(&format!("{A},7656119796028793x")).is_err());
+    let too_many = (1..=MAX_FAMILY_IDS as u32 + 1).map(|i| steamid::from_account_id(i).to_string()).collect::<Vec<_>>();
+    assert!(parse_family_ids(&too_many.join(",")).is_err());
+    assert_eq!(parse_family_ids(&too_many[..MAX_FAMILY_IDS].join(",")).map(|v| v.len()), Ok(MAX_FAMILY_IDS));
+  }
+
+  #[test]
+  fn mood_limits() {
+    assert_eq!(parse_mood("  спокійне  "), Ok("спокійне".into()));
+    assert!(parse_mood(&"я".repeat(MAX_MOOD_CHARS)).is_ok());
+    assert!(parse_mood(&"я".repeat(MAX_MOOD_CHARS + 1)).is_err());
+    assert!(parse_mood("   ").is_err());
+    assert!(parse_mood("line\nbreak").is_err());
+    assert!(parse_mood("esc\u{1b}[2J").is_err());
+  }
+
+  #[test]
+  fn appid_forms() {
+    assert_eq!(parse_appid("4294967295"), Ok(u32::MAX));
+    for raw in ["", "0", "00", "-1", "+1", "abc", "4294967296", "12345678901", "6 20"] {
+      assert!(parse_appid(raw).is_err(), "{raw:?}");
+    }
+  }
+}
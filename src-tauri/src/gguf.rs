@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+// ====================== Метадані GGUF ======================
+//
+// llama_cpp 0.3 не віддає метадані моделі назовні, тож читаємо заголовок .gguf
+// самі: magic "GGUF", версія (2/3), кількість тензорів, кількість пар ключ-значення,
+// далі самі пари й описи тензорів. Беремо кілька рядкових ключів і всі цілі
+// скаляри; масиви (токени — сотні тисяч рядків) пропускаємо без читання в пам'ять.
+// Кількість параметрів рахуємо з розмірностей тензорів: ключ general.size_label є не всюди.
+
+const MAGIC: &[u8; 4] = b"GGUF";
+// довші значення (напр. вбудована граматика) нам не потрібні
+const MAX_STRING: u64 = 256 * 1024;
+
+// типи значень GGUF
+const T_UINT8: u32 = 0;
+const T_INT8: u32 = 1;
+const T_UINT16: u32 = 2;
+const T_INT16: u32 = 3;
+const T_UINT32: u32 = 4;
+const T_INT32: u32 = 5;
+const T_FLOAT32: u32 = 6;
+const T_BOOL: u32 = 7;
+const T_STRING: u32 = 8;
+const T_ARRAY: u32 = 9;
+const T_UINT64: u32 = 10;
+const T_INT64: u32 = 11;
+const T_FLOAT64: u32 = 12;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GgufMeta {
+  /// general.architecture: llama, qwen2, phi3, gemma2…
+  pub architecture: String,
+  pub name: String,
+  /// tokenizer.chat_template (Jinja) — у старих конвертаціях відсутній
+  pub chat_template: Option<String>,
+  /// цілочисельні ключі як є: general.file_type, llama.context_length, llama.block_count…
+  pub ints: HashMap<String, u64>,
+  pub parameters: u64,
+}
+
+impl GgufMeta {
+  /// Ключ архітектури: `arch_int("context_length")` → `<architecture>.context_length`.
+  pub fn arch_int(&self, key: &str) -> Option<u64> {
+    self.ints.get(&format!("{}.{key}", self.architecture)).copied()
+  }
+
+  pub fn context_length(&self) -> Option<u64> {
+    self.arch_int("context_length")
+  }
+
+  /// Назва квантизації за general.file_type (enum llama_ftype).
+  pub fn quantization(&self) -> Option<&'static str> {
+    Some(match self.ints.get("general.file_type")? {
+      0 => "F32",
+      1 => "F16",
+      2 => "Q4_0",
+      3 => "Q4_1",
+      7 => "Q8_0",
+      8 => "Q5_0",
+      9 => "Q5_1",
+      10 => "Q2_K",
+      11 => "Q3_K_S",
+      12 => "Q3_K_M",
+      13 => "Q3_K_L",
+      14 => "Q4_K_S",
+      15 => "Q4_K_M",
+      16 => "Q5_K_S",
+      17 => "Q5_K_M",
+      18 => "Q6_K",
+      19 => "IQ2_XXS",
+      20 => "IQ2_XS",
+      21 => "Q2_K_S",
+      22 => "IQ3_XS",
+      23 => "IQ3_XXS",
+      24 => "IQ1_S",
+      25 => "IQ4_NL",
+      26 => "IQ3_S",
+      27 => "IQ3_M",
+      28 => "IQ2_S",
+      29 => "IQ2_M",
+      30 => "IQ4_XS",
+      31 => "IQ1_M",
+      32 => "BF16",
+      _ => return None,
+    })
+  }
+}
+
+fn u32_of(r: &mut impl Read) -> Result<u32, String> {
+  let mut b = [0u8; 4];
+  r.read_exact(&mut b).map_err(|e| e.to_string())?;
+  Ok(u32::from_le_bytes(b))
+}
+
+fn u64_of(r: &mut impl Read) -> Result<u64, String> {
+  let mut b = [0u8; 8];
+  r.read_exact(&mut b).map_err(|e| e.to_string())?;
+  Ok(u64::from_le_bytes(b))
+}
+
+/// Ціле значення скалярного типу; bool, float і решту пропускаємо.
+fn int_of(r: &mut BufReader<impl Read + Seek>, t: u32) -> Result<Option<u64>, String> {
+  let Some(n) = scalar_size(t).filter(|_| !matches!(t, T_BOOL | T_FLOAT32 | T_FLOAT64)) else {
+    skip_value(r, t, 0)?;
+    return Ok(None);
+  };
+  let mut b = [0u8; 8];
+  r.read_exact(&mut b[..n as usize]).map_err(|e| e.to_string())?;
+  // від'ємні значення для розмірів/лічильників не мають сенсу
+  let signed_negative = matches!(t, T_INT8 | T_INT16 | T_INT32 | T_INT64) && b[n as usize - 1] & 0x80 != 0;
+  Ok((!signed_negative).then(|| u64::from_le_bytes(b)))
+}
+
+fn skip(r: &mut BufReader<impl Read + Seek>, n: u64) -> Result<(), String> {
+  let n = i64::try_from(n).map_err(|_| "gguf: value too large")?;
+  r.seek_relative(n).map_err(|e| e.to_string())
+}
+
+fn string_of(r: &mut BufReader<impl Read + Seek>) -> Result<String, String> {
+  let len = u64_of(r)?;
+  if len > MAX_STRING {
+    return Err("gguf: string too long".into());
+  }
+  let mut b = vec![0u8; len as usize];
+  r.read_exact(&mut b).map_err(|e| e.to_string())?;
+  Ok(String::from_utf8_lossy(&b).into_owned())
+}
+
+fn scalar_size(t: u32) -> Option<u64> {
+  match t {
+    T_UINT8 | T_INT8 | T_BOOL => Some(1),
+    T_UINT16 | T_INT16 => Some(2),
+    T_UINT32 | T_INT32 | T_FLOAT32 => Some(4),
+    T_UINT64 | T_INT64 | T_FLOAT64 => Some(8),
+    _ => None,
+  }
+}
+
+fn skip_value(r: &mut BufReader<impl Read + Seek>, t: u32, depth: usize) -> Result<(), String> {
+  if let Some(n) = scalar_size(t) {
+    return skip(r, n);
+  }
+  match t {
+    T_STRING => {
+      let len = u64_of(r)?;
+      skip(r, len)
+    }
+    T_ARRAY if depth < 4 => {
+      let inner = u32_of(r)?;
+      let count = u64_of(r)?;
+      match scalar_size(inner) {
+        Some(n) => skip(r, n.checked_mul(count).ok_or("gguf: array too large")?),
+        None => (0..count).try_for_each(|_| skip_value(r, inner, depth + 1)),
+      }
+    }
+    _ => Err(format!("gguf: unknown value type {t}")),
+  }
+}
+
+pub fn parse_meta(input: impl Read + Seek) -> Result<GgufMeta, String> {
+  let mut r = BufReader::new(input);
+  let mut magic = [0u8; 4];
+  r.read_exact(&mut magic).map_err(|e| e.to_string())?;
+  if &magic != MAGIC {
+    return Err("not a GGUF file".into());
+  }
+  let version = u32_of(&mut r)?;
+  if !(2..=3).contains(&version) {
+    return Err(format!("unsupported GGUF version {version}"));
+  }
+  let tensors = u64_of(&mut r)?;
+  let kv_count = u64_of(&mut r)?;
+
+  let mut meta = GgufMeta::default();
+  for _ in 0..kv_count {
+    let key = string_of(&mut r)?;
+    let t = u32_of(&mut r)?;
+    match (key.as_str(), t) {
+      ("general.architecture", T_STRING) => meta.architecture = string_of(&mut r)?,
+      ("general.name", T_STRING) => meta.name = string_of(&mut r)?,
+      ("tokenizer.chat_template", T_STRING) => meta.chat_template = Some(string_of(&mut r)?),
+      (_, T_STRING | T_ARRAY) => skip_value(&mut r, t, 0)?,
+      _ => {
+        if let Some(v) = int_of(&mut r, t)? {
+          meta.ints.insert(key, v);
+        }
+      }
+    }
+  }
+
+  // описи тензорів: ім'я, розмірності, тип, зсув
+  for _ in 0..tensors {
+    let name_len = u64_of(&mut r)?;
+    skip(&mut r, name_len)?;
+    let n_dims = u32_of(&mut r)?;
+    if n_dims > 8 {
+      return Err(format!("gguf: tensor with {n_dims} dimensions"));
+    }
+    let mut elems = 1u64;
+    for _ in 0..n_dims {
+      elems = elems.saturating_mul(u64_of(&mut r)?);
+    }
+    meta.parameters = meta.parameters.saturating_add(elems);
+    skip(&mut r, 4 + 8)?;
+  }
+  Ok(meta)
+}
+
+pub fn read_meta(path: &Path) -> Result<GgufMeta, String> {
+  parse_meta(File::open(path).map_err(|e| e.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn kv_str(out: &mut Vec<u8>, key: &str, val: &str) {
+    out.extend((key.len() as u64).to_le_bytes());
+    out.extend(key.as_bytes());
+    out.extend(T_STRING.to_le_bytes());
+    out.extend((val.len() as u64).to_le_bytes());
+    out.extend(val.as_bytes());
+  }
+
+  #[test]
+  fn reads_wanted_keys_and_skips_arrays() {
+    let mut b = b"GGUF".to_vec();
+    b.extend(3u32.to_le_bytes());
+    b.extend(2u64.to_le_bytes());
+    b.extend(6u64.to_le_bytes());
+    kv_str(&mut b, "general.architecture", "llama");
+    kv_str(&mut b, "general.name", "Llama 3.2 3B Instruct");
+    let key = "general.file_type";
+    b.extend((key.len() as u64).to_le_bytes());
+    b.extend(key.as_bytes());
+    b.extend(T_UINT32.to_le_bytes());
+    b.extend(12u32.to_le_bytes());
+    // tokenizer.ggml.tokens: масив рядків
+    let key = "tokenizer.ggml.tokens";
+    b.extend((key.len() as u64).to_le_bytes());
+    b.extend(key.as_bytes());
+    b.extend(T_ARRAY.to_le_bytes());
+    b.extend(T_STRING.to_le_bytes());
+    b.extend(2u64.to_le_bytes());
+    for tok in ["<|eot_id|>", "hi"] {
+      b.extend((tok.len() as u64).to_le_bytes());
+      b.extend(tok.as_bytes());
+    }
+    // llama.context_length: u32
+    let key = "llama.context_length";
+    b.extend((key.len() as u64).to_le_bytes());
+    b.extend(key.as_bytes());
+    b.extend(T_UINT32.to_le_bytes());
+    b.extend(131_072u32.to_le_bytes());
+    kv_str(&mut b, "tokenizer.chat_template", "{{ '<|start_header_id|>' }}");
+    // тензори: [3072, 128256] і [3072]
+    for (name, dims) in [("token_embd.weight", &[3072u64, 128_256][..]), ("output_norm.weight", &[3072][..])] {
+      b.extend((name.len() as u64).to_le_bytes());
+      b.extend(name.as_bytes());
+      b.extend((dims.len() as u32).to_le_bytes());
+      dims.iter().for_each(|d| b.extend(d.to_le_bytes()));
+      b.extend(12u32.to_le_bytes());
+      b.extend(0u64.to_le_bytes());
+    }
+
+    let meta = parse_meta(Cursor::new(b)).unwrap();
+    assert_eq!(meta.architecture, "llama");
+    assert_eq!(meta.name, "Llama 3.2 3B Instruct");
+    assert_eq!(meta.chat_template.as_deref(), Some("{{ '<|start_header_id|>' }}"));
+    assert_eq!(meta.context_length(), Some(131_072));
+    assert_eq!(meta.quantization(), Some("Q3_K_M"));
+    assert_eq!(meta.parameters, 3072 * 128_256 + 3072);
+  }
+
+  #[test]
+  fn rejects_other_files() {
+    assert!(parse_meta(Cursor::new(b"GGML\x03\0\0\0".to_vec())).is_err());
+  }
+}
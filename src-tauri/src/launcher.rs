@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Runtime;
+
+use crate::profiles;
+
+// ====================== Запуск гри та історія сесій ======================
+//
+// Гру запускає сам Steam (steam://rungameid/<appid>), ми лише відкриваємо
+// посилання. steam:// URL збираються тільки в steam_url(): відома дія плюс
+// перевірений appid, без довільних рядків із вебв'ю. На Linux/Windows далі
+// чекаємо процес із теки гри (installdir з appmanifest_*.acf) і, коли він
+// зникає, пишемо сесію в play_history.json активного профілю.
+
+const HISTORY_FILE: &str = "play_history.json";
+const MAX_HISTORY: usize = 2000;
+pub const EVENT_SESSION_STARTED: &str = "game-session-started";
+pub const EVENT_SESSION_ENDED: &str = "game-session-ended";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PlaySession {
+  pub appid: i64,
+  pub name: String,
+  pub started_at: u64,
+  pub ended_at: u64,
+  pub duration_secs: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LaunchStarted {
+  pub appid: i64,
+  /// false — гра не встановлена або платформа без стеження: Steam покаже свій діалог
+  pub tracking: bool,
+}
+
+/// Дії Steam-клієнта для гри. `Run` — лише через launch_game, щоб сесія записалась.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SteamAction {
+  #[serde(skip_deserializing)]
+  Run,
+  Install,
+  Validate,
+  Store,
+  Details,
+}
+
+impl SteamAction {
+  fn verb(self) -> &'static str {
+    match self {
+      SteamAction::Run => "rungameid",
+      SteamAction::Install => "install",
+      SteamAction::Validate => "validate",
+      SteamAction::Store => "store",
+      SteamAction::Details => "nav/games/details",
+    }
+  }
+}
+
+pub fn steam_url(action: SteamAction, appid: i64) -> Result<String, String> {
+  if appid <= 0 || appid > i64::from(u32::MAX) {
+    return Err("appid must be a positive integer".into());
+  }
+  Ok(format!("steam://{}/{appid}", action.verb()))
+}
+
+fn open_steam_url<R: Runtime>(app: &tauri::AppHandle<R>, action: SteamAction, appid: i64) -> Result<(), String> {
+  use tauri_plugin_opener::OpenerExt;
+  app.opener()
+    .open_url(steam_url(action, appid)?, None::<String>)
+    .map_err(|e| e.to_string())
+}
+
+fn history_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(profiles::active_dir(app)?.join(HISTORY_FILE))
+}
+
+fn read_history(path: &Path) -> Vec<PlaySession> {
+  fs::read(path)
+    .ok()
+    .and_then(|b| serde_json::from_slice(&b).ok())
+    .unwrap_or_default()
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+fn append_session(path: &Path, session: PlaySession) -> Result<(), String> {
+  let mut all = read_history(path);
+  all.push(session);
+  if all.len() > MAX_HISTORY {
+    all.drain(..all.len() - MAX_HISTORY);
+  }
+  let data = serde_json::to_vec_pretty(&all).map_err(|e| e.to_string())?;
+  fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// Чи вказує шлях (exe або аргумент) усередину `common/<installdir>`.
+/// Порівнюємо рядком, а не canonicalize: під Proton аргумент має вигляд `Z:\home\...\common\Game\game.exe`,
+/// а ~/.steam/steam — симлінк на ~/.local/share/Steam.
+#[cfg(any(windows, target_os = "linux"))]
+fn mentions_installdir(path: &str, installdir: &str) -> bool {
+  let path = format!("{}/", path.replace('\\', "/").to_lowercase());
+  let needle = format!("/common/{}/", installdir.replace('\\', "/").trim_matches('/').to_lowercase());
+  path.contains(&needle)
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+mod watch {
+  use once_cell::sync::Lazy;
+  use std::collections::HashSet;
+  use std::path::PathBuf;
+  use std::sync::Mutex;
+  use std::time::{Duration, Instant};
+  use sysinfo::{ProcessRefreshKind, System, UpdateKind};
+  use tauri::{Emitter, Runtime};
+
+  use super::{append_session, mentions_installdir, PlaySession, EVENT_SESSION_ENDED, EVENT_SESSION_STARTED};
+  use crate::commands::chrono_now_u64;
+
+  const POLL: Duration = Duration::from_secs(3);
+  // Steam може спершу докачати оновлення чи показати EULA
+  const START_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+  // лаунчери перезапускають гру; сесія закінчена, лише коли процесу немає кілька опитувань поспіль
+  const GONE_POLLS: u32 = 3;
+
+  static WATCHING: Lazy<Mutex<HashSet<i64>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+  fn running(sys: &mut System, installdir: &str) -> bool {
+    sys.refresh_processes_specifics(
+      ProcessRefreshKind::new()
+        .with_exe(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet),
+    );
+    sys.processes().values().any(|p| {
+      p.exe().map(|e| mentions_installdir(&e.to_string_lossy(), installdir)).unwrap_or(false)
+        || p.cmd().iter().any(|a| mentions_installdir(a, installdir))
+    })
+  }
+
+  /// false — за цією грою вже стежить інший потік.
+  pub fn spawn<R: Runtime>(app: tauri::AppHandle<R>, appid: i64, name: String, installdir: String, history: PathBuf) -> bool {
+    let fresh = WATCHING.lock().map(|mut w| w.insert(appid)).unwrap_or(false);
+    if !fresh {
+      return false;
+    }
+
+    std::thread::spawn(move || {
+      let mut sys = System::new();
+      let waiting = Instant::now();
+      while !running(&mut sys, &installdir) {
+        if waiting.elapsed() > START_TIMEOUT {
+          log::info!("app {appid}: process did not start, session not recorded");
+          if let Ok(mut w) = WATCHING.lock() { w.remove(&appid); }
+          return;
+        }
+        std::thread::sleep(POLL);
+      }
+
+      let started_at = chrono_now_u64();
+      let _ = app.emit(EVENT_SESSION_STARTED, appid);
+      let mut gone = 0;
+      while gone < GONE_POLLS {
+        std::thread::sleep(POLL);
+        gone = if running(&mut sys, &installdir) { 0 } else { gone + 1 };
+      }
+      // останні опитування «без процесу» у сесію не входять
+      let ended_at = chrono_now_u64().saturating_sub(POLL.as_secs() * u64::from(GONE_POLLS - 1)).max(started_at);
+
+      let session = PlaySession { appid, name, started_at, ended_at, duration_secs: ended_at - started_at };
+      if let Err(e) = append_session(&history, session.clone()) {
+        log::warn!("play history write failed: {e}");
+      }
+      let _ = app.emit(EVENT_SESSION_ENDED, &session);
+      if let Ok(mut w) = WATCHING.lock() { w.remove(&appid); }
+    });
+    true
+  }
+}
+
+#[tauri::command]
+pub async fn launch_game<R: Runtime>(app: tauri::AppHandle<R>, appid: i64) -> Result<LaunchStarted, String> {
+  open_steam_url(&app, SteamAction::Run, appid)?;
+
+  #[cfg(any(windows, target_os = "linux"))]
+  {
+    let history = history_path(&app)?;
+    let manifest = tauri::async_runtime::spawn_blocking(move || {
+      crate::scanner::find_manifest(&crate::commands::roots_from_detect_roots(), appid)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    // маніфест без теки гри — встановлення не завершене, процес шукати ніде
+    if let Some(m) = manifest.filter(|m| m.install_path().is_some_and(|p| p.is_dir())) {
+      let tracking = watch::spawn(app, appid, m.name, m.installdir, history);
+      return Ok(LaunchStarted { appid, tracking });
+    }
+  }
+  Ok(LaunchStarted { appid, tracking: false })
+}
+
+/// Встановити, перевірити файли, сторінка в магазині чи в бібліотеці клієнта.
+#[tauri::command]
+pub async fn open_steam_action<R: Runtime>(app: tauri::AppHandle<R>, appid: i64, action: SteamAction) -> Result<(), String> {
+  open_steam_url(&app, action, appid)
+}
+
+/// Сесії активного профілю, найновіші першими; `appid` — лише для однієї гри.
+#[tauri::command]
+pub async fn get_play_history<R: Runtime>(app: tauri::AppHandle<R>, appid: Option<i64>) -> Result<Vec<PlaySession>, String> {
+  let mut all = read_history(&history_path(&app)?);
+  if let Some(id) = appid {
+    all.retain(|s| s.appid == id);
+  }
+  all.reverse();
+  Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn steam_urls_are_built_from_known_verbs_only() {
+    assert_eq!(steam_url(SteamAction::Install, 620).unwrap(), "steam://install/620");
+    assert_eq!(steam_url(SteamAction::Details, 620).unwrap(), "steam://nav/games/details/620");
+    assert!(steam_url(SteamAction::Store, 0).is_err());
+    assert!(steam_url(SteamAction::Validate, i64::from(u32::MAX) + 1).is_err());
+    // запуск із вебв'ю — тільки через launch_game
+    assert!(serde_json::from_str::<SteamAction>("\"run\"").is_err());
+    assert_eq!(serde_json::from_str::<SteamAction>("\"validate\"").unwrap(), SteamAction::Validate);
+  }
+
+  #[test]
+  #[cfg(any(windows, target_os = "linux"))]
+  fn installdir_matches_native_and_proton_paths() {
+    assert!(mentions_installdir("/home/u/.local/share/Steam/steamapps/common/Hades/Hades", "Hades"));
+    assert!(mentions_installdir(r"Z:\home\u\.steam\steam\steamapps\common\Hades II\Hades2.exe", "Hades II"));
+    assert!(mentions_installdir(r"D:\SteamLibrary\steamapps\common\hades", "Hades"));
+    assert!(!mentions_installdir("/home/u/.local/share/Steam/steamapps/common/Hades II/Hades2", "Hades"));
+    assert!(!mentions_installdir("/usr/bin/hades", "Hades"));
+  }
+}
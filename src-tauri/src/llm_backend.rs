@@ -1,19 +1,21 @@
+use serde::{Deserialize, Serialize};
 use std::{
   fs,
   path::{Path, PathBuf},
-  sync::{Mutex, OnceLock},
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
 };
 
+use crate::chat_template::{ChatFormat, ChatMessage};
+use crate::gguf::{self, GgufMeta};
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Mode { Disabled, Enabled }
 
-// ====== ваш абсолютний шлях до .gguf (за потреби змініть) ======
-#[cfg(windows)]
-const HARDCODED_MODEL_FILE: &str = r#"C:\Users\AMD\Desktop\SteamGameHelper\apps\desktop\src-tauri\resources\models\Llama-3.2-3B-Instruct-Q3_K_M.gguf"#;
-#[cfg(not(windows))]
-const HARDCODED_MODEL_FILE: &str = r#""#;
-
-static MODEL_PATH: OnceLock<PathBuf> = OnceLock::new();
+// вибір із налаштувань (models::select_model) або перший знайдений pick_model()
+static MODEL_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
 
 fn exe_dir() -> PathBuf {
   std::env::current_exe()
@@ -22,16 +24,6 @@ fn exe_dir() -> PathBuf {
     .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
 }
 
-fn hardcoded_model_file() -> Option<PathBuf> {
-  if !HARDCODED_MODEL_FILE.trim().is_empty() {
-    let p = PathBuf::from(HARDCODED_MODEL_FILE);
-    if p.is_file() && p.extension().and_then(|s| s.to_str()) == Some("gguf") {
-      return Some(p);
-    }
-  }
-  None
-}
-
 fn env_model_file() -> Option<PathBuf> {
   let raw = std::env::var("SGH_MODEL_FILE").ok()?;
   let p = PathBuf::from(raw);
@@ -42,7 +34,7 @@ fn env_model_file() -> Option<PathBuf> {
   }
 }
 
-fn candidate_dirs() -> Vec<PathBuf> {
+pub(crate) fn candidate_dirs() -> Vec<PathBuf> {
   if let Ok(p) = std::env::var("SGH_MODELS_DIR") {
     return vec![PathBuf::from(p)];
   }
@@ -53,30 +45,48 @@ fn candidate_dirs() -> Vec<PathBuf> {
   v
 }
 
-fn find_any_gguf(dir: &Path) -> Option<PathBuf> {
-  let entries = fs::read_dir(dir).ok()?;
-  for e in entries.flatten() {
-    let p = e.path();
-    if p.is_file() && p.extension().and_then(|s| s.to_str()) == Some("gguf") {
-      return Some(p);
+/// .gguf у теці за іменем — порядок read_dir залежить від ФС.
+fn list_gguf(dir: &Path) -> Vec<PathBuf> {
+  let Ok(entries) = fs::read_dir(dir) else { return vec![]; };
+  let mut v: Vec<PathBuf> = entries
+    .flatten()
+    .map(|e| e.path())
+    .filter(|p| p.is_file() && p.extension().and_then(|s| s.to_str()) == Some("gguf"))
+    .collect();
+  v.sort();
+  v
+}
+
+/// Усі моделі, з яких можна вибрати: SGH_MODEL_FILE і теки моделей.
+pub fn model_files() -> Vec<PathBuf> {
+  let mut v: Vec<PathBuf> = env_model_file().into_iter().collect();
+  for d in candidate_dirs() {
+    for p in list_gguf(&d) {
+      if !v.contains(&p) { v.push(p); }
     }
   }
-  None
+  v
 }
 
 fn pick_model() -> Option<PathBuf> {
-  if let Some(p) = hardcoded_model_file() { return Some(p); }
-  if let Some(p) = env_model_file()      { return Some(p); }
-  for d in candidate_dirs() {
-    if let Some(p) = find_any_gguf(&d) { return Some(p); }
-  }
-  None
+  model_files().into_iter().next()
+}
+
+/// Поточна модель: вибрана користувачем, інакше перша знайдена.
+pub fn model_path() -> Option<PathBuf> {
+  let mut g = MODEL_PATH.lock().ok()?;
+  if g.is_none() { *g = pick_model(); }
+  g.clone()
+}
+
+/// `None` — повернутись до автовибору. Завантажена модель зміниться на наступному запиті.
+pub fn set_model_path(path: Option<PathBuf>) {
+  if let Ok(mut g) = MODEL_PATH.lock() { *g = path; }
 }
 
 pub fn init_if_available() -> Mode {
-  if MODEL_PATH.get().is_some() { return Mode::Enabled; }
-  match pick_model() {
-    Some(p) => { let _ = MODEL_PATH.set(p); Mode::Enabled }
+  match model_path() {
+    Some(_) => Mode::Enabled,
     None => Mode::Disabled,
   }
 }
@@ -107,7 +117,8 @@ fn hardware_tuning() -> HwTuning {
 
   // total_memory() у 0.30 повертає KiB
   let total_mem_gib: u64 = sys.total_memory() / 1024 / 1024;
-  let cores = num_cpus::get();
+  // генерація впирається в пам'ять: гіперпотоки лише заважають одне одному
+  let cores = num_cpus::get_physical();
 
   let mut tune = HwTuning {
     n_threads: cores.clamp(2, 16),
@@ -138,120 +149,714 @@ fn hardware_tuning() -> HwTuning {
   tune
 }
 
+/// Контекст сесії: з авто-тюнінгу, але не довший, ніж модель бачила в навчанні.
+pub(crate) fn context_for(meta: Option<&GgufMeta>) -> usize {
+  let n_ctx = hardware_tuning().n_ctx;
+  match meta.and_then(GgufMeta::context_length) {
+    Some(trained) => n_ctx.min(trained as usize).max(512),
+    None => n_ctx,
+  }
+}
+
+/// Контекст для вибраної моделі.
+pub fn context_tokens() -> usize {
+  let meta = model_path().and_then(|p| gguf::read_meta(&p).ok());
+  context_for(meta.as_ref())
+}
+
+pub fn thread_count() -> usize {
+  hardware_tuning().n_threads
+}
+
+// ---------- чи влізе модель у пам'ять ----------
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryFit {
+  pub required_bytes: u64,
+  pub available_bytes: u64,
+  pub fits: bool,
+}
+
+// буфери обчислень, токенізатор тощо
+const RUNTIME_OVERHEAD: u64 = 256 * 1024 * 1024;
+
+impl MemoryFit {
+  /// Ваги (файл цілком) + KV-кеш у f16 на `n_ctx` токенів + запас.
+  pub fn of(file_size: u64, meta: &GgufMeta, n_ctx: u64, available_bytes: u64) -> Self {
+    let kv = match (meta.arch_int("block_count"), meta.arch_int("embedding_length")) {
+      (Some(layers), Some(embd)) => {
+        let heads = meta.arch_int("attention.head_count").unwrap_or(1).max(1);
+        let kv_heads = meta.arch_int("attention.head_count_kv").unwrap_or(heads);
+        // K і V по 2 байти на елемент; з GQA кеш менший у heads / kv_heads разів
+        (2 * 2 * layers * n_ctx * embd).saturating_mul(kv_heads) / heads
+      }
+      // без розмірів архітектури — грубо, як частка від ваг
+      _ => file_size / 8,
+    };
+    let required_bytes = file_size.saturating_add(kv).saturating_add(RUNTIME_OVERHEAD);
+    Self { required_bytes, available_bytes, fits: required_bytes <= available_bytes }
+  }
+}
+
+/// Вільна оперативна пам'ять у байтах.
+pub fn available_memory() -> u64 {
+  let mut sys = sysinfo::System::new();
+  sys.refresh_memory();
+  sys.available_memory()
+}
+
+pub fn check_memory(path: &Path) -> Result<MemoryFit, String> {
+  let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+  let meta = gguf::read_meta(path)?;
+  Ok(MemoryFit::of(size, &meta, context_for(Some(&meta)) as u64, available_memory()))
+}
+
+// ---------- результат генерації ----------
+
+/// Чому модель перестала писати.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+  /// кінець репліки: EOS або стоп-послідовність шаблону
+  Eos,
+  MaxTokens,
+  WallTime,
+  Cancelled,
+}
+
+/// Параметри одного запиту поверх авто-тюнінгу.
+#[derive(Clone, Debug, Default)]
+pub struct GenOptions {
+  /// None — формат, визначений за метаданими моделі
+  pub template: Option<ChatFormat>,
+  /// GBNF з правилом root: вибірка токенів лише в межах граматики
+  pub grammar: Option<String>,
+  /// None — max_tokens з hardware_tuning; ліміт часу масштабується разом із ним
+  pub max_tokens: Option<usize>,
+  /// відповідь — JSON-об'єкт; для серверів без граматик (response_format)
+  pub json: bool,
+}
+
+/// Лічильники одного запиту; бенчмарк рахує з них токени за секунду.
+#[derive(Serialize, Clone, Copy, Debug, Default)]
+pub struct GenStats {
+  pub prompt_tokens: usize,
+  /// з них уже були в KV-кеші теплої сесії й не обчислювались
+  pub cached_tokens: usize,
+  pub prompt_ms: u64,
+  pub gen_tokens: usize,
+  pub gen_ms: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Generation {
+  pub text: String,
+  pub stop: StopReason,
+  pub stats: GenStats,
+}
+
+// ---------- бекенди ----------
+
+/// Звідки беруться відповіді: з налаштувань (prefs.json → llm_backend).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+  /// .gguf через llama_cpp у цьому процесі
+  #[default]
+  Local,
+  /// Ollama, LM Studio, llama-server, vLLM за ai_base_url (openai_compat)
+  OpenaiCompat,
+}
+
+/// Блокуючий виклик — запускати в spawn_blocking.
+pub trait LlmBackend: Send + Sync {
+  /// `messages` — розмова, що закінчується реплікою користувача.
+  /// `on_chunk` отримує текст у міру генерації; повертає false, якщо слухач зник.
+  fn generate(
+    &self,
+    system_prompt: &str,
+    messages: &[ChatMessage],
+    opts: &GenOptions,
+    ticket: &Ticket,
+    on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String>;
+}
+
+/// Вибрана модель (model_path) у пам'яті процесу; запити чекають своєї черги на ній.
+pub struct LocalLlama;
+
+impl LlmBackend for LocalLlama {
+  fn generate(
+    &self,
+    system_prompt: &str,
+    messages: &[ChatMessage],
+    opts: &GenOptions,
+    ticket: &Ticket,
+    on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String> {
+    inner::generate(system_prompt, messages, opts, ticket, on_chunk)
+  }
+}
+
+// ---------- черга запитів і скасування ----------
+//
+// Модель одна, і генерація тримає її мʼютекс до кінця. Кожен запит отримує
+// Ticket з id ще до того, як стане в чергу, тож скасувати можна і той, що
+// генерує, і той, що чекає.
+
+/// Що робити з новим запитом, коли модель уже зайнята.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+  /// одразу помилка LLM_BUSY
+  Reject,
+  /// чекати своєї черги
+  #[default]
+  Queue,
+  /// скасувати всі попередні й піти наступним
+  Replace,
+}
+
+pub const LLM_BUSY: &str = "LLM is busy with another request";
+const LLM_CANCELLED: &str = "LLM request cancelled";
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Облік запитів у черзі й у роботі. Глобальна одна (QUEUE); тести заводять власну.
+pub struct Queue {
+  in_flight: Mutex<Vec<(u64, Arc<AtomicBool>)>>,
+}
+
+static QUEUE: Queue = Queue::new();
+
+impl Queue {
+  pub const fn new() -> Self {
+    Self { in_flight: Mutex::new(Vec::new()) }
+  }
+
+  pub fn begin(&'static self, policy: QueuePolicy) -> Result<Ticket, String> {
+    let mut g = self.in_flight.lock().map_err(|_| "LLM queue poisoned".to_string())?;
+    match policy {
+      QueuePolicy::Reject if !g.is_empty() => return Err(LLM_BUSY.into()),
+      QueuePolicy::Replace => g.iter().for_each(|(_, c)| c.store(true, Ordering::SeqCst)),
+      _ => {}
+    }
+    let ticket = Ticket { id: NEXT_ID.fetch_add(1, Ordering::SeqCst), cancel: Arc::new(AtomicBool::new(false)), queue: self };
+    g.push((ticket.id, ticket.cancel.clone()));
+    Ok(ticket)
+  }
+
+  /// `id: None` — усі запити. Повертає, чи було що скасовувати.
+  pub fn cancel(&self, id: Option<u64>) -> bool {
+    let Ok(g) = self.in_flight.lock() else { return false; };
+    let mut hit = false;
+    for (_, c) in g.iter().filter(|(gid, _)| id.map_or(true, |want| *gid == want)) {
+      c.store(true, Ordering::SeqCst);
+      hit = true;
+    }
+    hit
+  }
+}
+
+/// Запит у черзі або в роботі; drop знімає його з обліку.
+pub struct Ticket {
+  pub id: u64,
+  cancel: Arc<AtomicBool>,
+  queue: &'static Queue,
+}
+
+impl Ticket {
+  pub fn is_cancelled(&self) -> bool {
+    self.cancel.load(Ordering::SeqCst)
+  }
+
+  /// Поза глобальною чергою: тести бекендів не заважають одне одному.
+  #[cfg(test)]
+  pub(crate) fn detached() -> Self {
+    static DETACHED: Queue = Queue::new();
+    Self { id: 0, cancel: Arc::new(AtomicBool::new(false)), queue: &DETACHED }
+  }
+}
+
+impl Drop for Ticket {
+  fn drop(&mut self) {
+    if let Ok(mut g) = self.queue.in_flight.lock() {
+      g.retain(|(id, _)| *id != self.id);
+    }
+  }
+}
+
+pub fn begin(policy: QueuePolicy) -> Result<Ticket, String> {
+  QUEUE.begin(policy)
+}
+
+pub fn cancel(id: Option<u64>) -> bool {
+  QUEUE.cancel(id)
+}
+
+/// Скільки токенів `prompt` можна взяти з кешу сесії, що вже містить `cached`.
+/// Останній токен промпту обчислюємо завжди: вибірці потрібні його логіти.
+fn reusable_prefix<T: PartialEq>(cached: &[T], prompt: &[T]) -> usize {
+  let shared = cached.iter().zip(prompt).take_while(|(a, b)| a == b).count();
+  shared.min(prompt.len().saturating_sub(1))
+}
+
+// страховка від безкінечного виводу, незалежно від max_tokens
+pub(crate) const MAX_OUTPUT_BYTES: usize = 12_000;
+
+/// Накопичує вивід і віддає назовні лише текст, який точно не є початком стоп-послідовності:
+/// при стрімінгу в UI не повинно потрапити навіть `<|en`.
+#[cfg(any(feature = "llm", test))]
+struct StopFilter<'a> {
+  stops: &'a [&'a str],
+  out: String,
+  sent: usize,
+}
+
+#[cfg(any(feature = "llm", test))]
+impl<'a> StopFilter<'a> {
+  fn new(stops: &'a [&'a str]) -> Self {
+    Self { stops, out: String::new(), sent: 0 }
+  }
+
+  /// Повертає (готовий до показу шматок, чи трапилась стоп-послідовність).
+  fn push(&mut self, piece: &str) -> (String, bool) {
+    self.out.push_str(piece);
+    if let Some(pos) = self.stops.iter().filter_map(|s| self.out.find(s)).min() {
+      self.out.truncate(pos);
+      let ready = self.out.get(self.sent..).unwrap_or_default().to_string();
+      self.sent = self.out.len();
+      return (ready, true);
+    }
+    let held = self
+      .stops
+      .iter()
+      .flat_map(|s| (1..s.len()).filter(|k| s.is_char_boundary(*k)).map(|k| &s[..k]))
+      .filter(|prefix| self.out.ends_with(prefix))
+      .map(str::len)
+      .max()
+      .unwrap_or(0);
+    let safe = (self.out.len() - held).max(self.sent);
+    let ready = self.out[self.sent..safe].to_string();
+    self.sent = safe;
+    (ready, false)
+  }
+
+  fn len(&self) -> usize {
+    self.out.len()
+  }
+
+  /// Залишок, який ще не віддали, і повний текст.
+  fn finish(self) -> (String, String) {
+    let rest = self.out[self.sent..].to_string();
+    (rest, self.out)
+  }
+}
+
 #[cfg(feature = "llm")]
 mod inner {
   use super::*;
+  use std::cell::Cell;
   use std::time::{Duration, Instant};
-  use llama_cpp::{LlamaModel, LlamaParams, SessionParams};
-  use llama_cpp::standard_sampler::StandardSampler;
-
-  static MODEL: OnceLock<Mutex<Option<LlamaModel>>> = OnceLock::new();
-
-  fn ensure_loaded() -> Result<(), String> {
-    MODEL.get_or_init(|| Mutex::new(None));
+  use llama_cpp::{LlamaModel, LlamaParams, LlamaSession, SessionParams, Token, TokensToStrings};
+  use llama_cpp::grammar::LlamaGrammar;
+  use llama_cpp::standard_sampler::{SamplerStage, StandardSampler};
+
+  /// Модель разом із форматом і контекстом, визначеними з її метаданих, і теплою сесією.
+  struct Loaded {
+    path: PathBuf,
+    model: LlamaModel,
+    format: ChatFormat,
+    n_ctx: usize,
+    // KV-кеш переживає запити: system prompt обчислюється один раз
+    session: Option<LlamaSession>,
+  }
 
-    if super::MODEL_PATH.get().is_none() {
-      if let Some(p) = super::pick_model() {
-        let _ = super::MODEL_PATH.set(p);
+  impl Loaded {
+    fn session(&mut self, t: &HwTuning) -> Result<&mut LlamaSession, String> {
+      if self.session.is_none() {
+        let session = self.model.create_session(session_params(t, self.n_ctx)).map_err(|e| e.to_string())?;
+        self.session = Some(session);
       }
+      self.session.as_mut().ok_or_else(|| "LLM session missing".to_string())
     }
-    let path = super::MODEL_PATH
-      .get()
-      .ok_or("Model path not set")?
-      .to_string_lossy()
-      .to_string();
-
-    {
-      let g = MODEL.get().unwrap().lock().map_err(|_| "LLM mutex poisoned")?;
-      if g.is_some() { return Ok(()); }
+  }
+
+  static MODEL: Mutex<Option<Loaded>> = Mutex::new(None);
+  // та сама модель для підрахунку токенів: MODEL зайнятий, поки йде генерація
+  static TOKENIZER: Mutex<Option<(PathBuf, LlamaModel)>> = Mutex::new(None);
+
+  fn set_tokenizer(v: Option<(PathBuf, LlamaModel)>) {
+    if let Ok(mut g) = TOKENIZER.lock() { *g = v; }
+  }
+
+  fn lock() -> Result<std::sync::MutexGuard<'static, Option<Loaded>>, String> {
+    MODEL.lock().map_err(|_| "LLM mutex poisoned".to_string())
+  }
+
+  /// Завантажити вибрану модель, якщо в пам'яті інша або жодної.
+  pub fn ensure_loaded() -> Result<(), String> {
+    let path = super::model_path().ok_or("Model path not set")?;
+    if lock()?.as_ref().is_some_and(|l| l.path == path) {
+      return Ok(());
     }
+    load(&path)
+  }
 
-    let model = LlamaModel::load_from_file(&path, LlamaParams::default())
+  fn open(path: &Path) -> Result<Loaded, String> {
+    // потоків у LlamaParams 0.3 немає: llama.cpp задає їх на контекст (session_params)
+    let model = LlamaModel::load_from_file(path, LlamaParams::default())
       .map_err(|e| e.to_string())?;
-
-    let mut g = MODEL.get().unwrap().lock().map_err(|_| "LLM mutex poisoned")?;
-    *g = Some(model);
-    Ok(())
+    let meta = gguf::read_meta(path)
+      .map_err(|e| log::warn!("GGUF metadata unreadable ({e}), falling back to ChatML"))
+      .ok();
+    let format = meta.as_ref().map_or(ChatFormat::Chatml, ChatFormat::detect);
+    let n_ctx = super::context_for(meta.as_ref());
+    log::info!("LLM chat template: {format:?}, context: {n_ctx}");
+    set_tokenizer(Some((path.to_path_buf(), model.clone())));
+    Ok(Loaded { path: path.to_path_buf(), model, format, n_ctx, session: None })
   }
 
-  fn feed_prompt_in_chunks<F>(mut advance: F, prompt: &str) -> Result<(), String>
-  where
-    F: FnMut(&str) -> Result<(), String>,
-  {
-    const CHUNK: usize = 2000;
-    if prompt.len() <= CHUNK {
-      return advance(prompt);
+  /// Завантажити `path` замість поточної моделі, якщо вистачає пам'яті.
+  /// Чекає, поки закінчиться поточна генерація. Поточна модель лишається в
+  /// пам'яті, якщо нова не проходить перевірку; невдале завантаження повертає її.
+  pub fn load(path: &Path) -> Result<(), String> {
+    let mut g = lock()?;
+    let old = g.as_ref().map(|l| l.path.clone());
+    // те, що звільнить поточна модель, рахуємо вільним: інакше вона з'їдає «вільну» пам'ять
+    let reclaim = old.as_deref().and_then(|p| super::check_memory(p).ok()).map_or(0, |f| f.required_bytes);
+    let fit = super::check_memory(path)?;
+    let available = fit.available_bytes.saturating_add(reclaim);
+    if fit.required_bytes > available {
+      return Err(format!(
+        "Not enough memory for the model: needs ~{} MiB, {} MiB available",
+        fit.required_bytes >> 20,
+        available >> 20
+      ));
     }
-    let mut start = 0;
-    while start < prompt.len() {
-      let end = (start + CHUNK).min(prompt.len());
-      let mut cut = end;
-      if end < prompt.len() {
-        if let Some(prev_nl) = prompt[start..end].rfind('\n') {
-          cut = start + prev_nl + 1;
-        } else if let Some(prev_sp) = prompt[start..end].rfind(' ') {
-          cut = start + prev_sp + 1;
+
+    *g = None;
+    set_tokenizer(None);
+    match open(path) {
+      Ok(l) => {
+        *g = Some(l);
+        Ok(())
+      }
+      Err(e) => {
+        if let Some(prev) = old.filter(|p| p != path) {
+          match open(&prev) {
+            Ok(l) => *g = Some(l),
+            Err(e2) => log::warn!("previous model reload failed: {e2}"),
+          }
         }
+        Err(e)
       }
-      if cut <= start { cut = end; }
-      advance(&prompt[start..cut])?;
-      start = cut;
     }
-    Ok(())
   }
 
-  pub fn answer(system_prompt: &str, user_prompt: &str) -> Result<String, String> {
-    ensure_loaded()?;
+  /// Повертає, чи була модель у пам'яті.
+  pub fn unload() -> Result<bool, String> {
+    let mut g = lock()?;
+    set_tokenizer(None);
+    Ok(g.take().is_some())
+  }
 
-    let t = super::hardware_tuning();
+  /// Токенізатор вибраної моделі, якщо вона вже в пам'яті; не чекає на генерацію.
+  pub fn token_counter() -> Option<crate::prompt::CountFn> {
+    let current = super::model_path()?;
+    let g = TOKENIZER.lock().ok()?;
+    let (_, model) = g.as_ref().filter(|(p, _)| *p == current)?;
+    let model = model.clone();
+    Some(Box::new(move |text: &str| {
+      model.tokenize_bytes(text, false, true).map_or_else(|_| crate::prompt::approx_tokens(text), |t| t.len())
+    }))
+  }
 
+  pub fn loaded_path() -> Option<PathBuf> {
+    lock().ok()?.as_ref().map(|l| l.path.clone())
+  }
+
+  fn session_params(t: &HwTuning, n_ctx: usize) -> SessionParams {
     let mut sp = SessionParams::default();
-    sp.n_ctx   = t.n_ctx as u32;    // ⬅️ каст до u32
-    sp.n_batch = t.n_batch as u32;  // ⬅️ каст до u32
-    #[allow(unused_variables)]
-    {
-      let _ = t.n_threads;
-      // якщо у вашій версії є поле:
-      // sp.n_threads = t.n_threads as u32;
+    sp.n_ctx = n_ctx as u32;
+    sp.n_batch = t.n_batch as u32;
+    sp.n_threads = t.n_threads as u32;
+    // обробка промпту — окремий пул потоків
+    sp.n_threads_batch = t.n_threads as u32;
+    sp
+  }
+
+  fn tokenize(model: &LlamaModel, prompt: &str) -> Result<Vec<Token>, String> {
+    // як advance_context: без BOS (він у шаблоні), спецтокени розпізнаються
+    let tokens = model.tokenize_bytes(prompt, false, true).map_err(|e| e.to_string())?;
+    if tokens.is_empty() {
+      return Err("Empty prompt".into());
+    }
+    Ok(tokens)
+  }
+
+  /// Довести контекст сесії до `tokens`, обчислюючи лише хвіст після спільного префікса.
+  /// Повертає, скільки токенів узято з кешу. Між батчами перевіряє скасування.
+  fn prefill(session: &mut LlamaSession, tokens: &[Token], batch: usize, ticket: Option<&Ticket>) -> Result<usize, String> {
+    let shared = super::reusable_prefix(&session.context(), tokens);
+    session.truncate_context(shared).map_err(|e| e.to_string())?;
+    for part in tokens[shared..].chunks(batch.max(1)) {
+      if ticket.is_some_and(Ticket::is_cancelled) { return Err(LLM_CANCELLED.into()); }
+      session.advance_context_with_tokens(part).map_err(|e| e.to_string())?;
     }
+    Ok(shared)
+  }
 
-    let mut g = MODEL.get().unwrap().lock().map_err(|_| "LLM mutex poisoned".to_string())?;
-    let model = g.as_mut().ok_or("LLM not loaded")?;
-    let mut session = model.create_session(sp).map_err(|e| e.to_string())?;
+  fn cancelled() -> Generation {
+    Generation { text: String::new(), stop: StopReason::Cancelled, stats: GenStats::default() }
+  }
 
-    let prompt = format!(
-      "<|system|>{}<|end|><|user|>{}<|end|><|assistant|>",
-      system_prompt.trim(),
-      user_prompt.trim()
-    );
+  fn sampler(grammar: Option<&str>) -> Result<StandardSampler, String> {
+    let Some(src) = grammar else { return Ok(StandardSampler::default()); };
+    let grammar: LlamaGrammar = src.parse().map_err(|e| format!("grammar: {e}"))?;
+    // ті самі стадії, що й у StandardSampler::default(), з граматикою першою
+    Ok(StandardSampler::new_softmax(vec![
+      SamplerStage::from_grammar(grammar, None),
+      SamplerStage::RepetitionPenalty { repetition_penalty: 1.1, frequency_penalty: 0.0, presence_penalty: 0.0, last_n: 64 },
+      SamplerStage::TopK(40),
+      SamplerStage::TopP(0.95),
+      SamplerStage::MinP(0.05),
+      SamplerStage::Temperature(0.8),
+    ], 1))
+  }
 
-    feed_prompt_in_chunks(|chunk| session.advance_context(chunk).map_err(|e| e.to_string()), &prompt)?;
+  /// Покласти system prompt у KV-кеш теплої сесії, щоб перший запит його не чекав.
+  /// Повертає довжину префікса в токенах.
+  pub fn warm_up(system_prompt: &str, template: Option<ChatFormat>) -> Result<usize, String> {
+    ensure_loaded()?;
+    let t = super::hardware_tuning();
+    let mut g = lock()?;
+    let loaded = g.as_mut().ok_or("LLM not loaded")?;
+    let format = template.unwrap_or(loaded.format);
+    // порожня репліка: у Gemma і Mistral system живе всередині першої репліки користувача
+    let tokens = tokenize(&loaded.model, &format.render(system_prompt, &[ChatMessage::user("")]))?;
+    let session = loaded.session(&t)?;
+    if let Err(e) = prefill(session, &tokens, t.n_batch, None) {
+      loaded.session = None;
+      return Err(e);
+    }
+    Ok(tokens.len())
+  }
 
-    let sampler = StandardSampler::default();
+  pub fn generate(
+    system_prompt: &str,
+    messages: &[ChatMessage],
+    opts: &GenOptions,
+    ticket: &Ticket,
+    on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String> {
+    ensure_loaded()?;
+
+    let t = super::hardware_tuning();
+    let max_tokens = opts.max_tokens.unwrap_or(t.max_tokens).max(1);
+    let wall_time_ms = t.wall_time_ms * max_tokens as u64 / t.max_tokens.max(1) as u64;
+    let sampler = sampler(opts.grammar.as_deref())?;
+
+    // тут запит і стоїть у черзі
+    let mut g = lock()?;
+    if ticket.is_cancelled() { return Ok(cancelled()); }
+    let loaded = g.as_mut().ok_or("LLM not loaded")?;
+    let format = opts.template.unwrap_or(loaded.format);
+    let prompt_tokens = tokenize(&loaded.model, &format.render(system_prompt, messages))?;
+    // prompt::assemble рахує з запасом, але без токенізатора лише приблизно
+    if prompt_tokens.len() + max_tokens > loaded.n_ctx {
+      return Err(format!(
+        "Prompt is {} tokens: with {max_tokens} for the answer it exceeds the {}-token context",
+        prompt_tokens.len(),
+        loaded.n_ctx
+      ));
+    }
+    let mut stats = GenStats { prompt_tokens: prompt_tokens.len(), ..Default::default() };
+
+    let session = loaded.session(&t)?;
+    let started = Instant::now();
+    // довгий промпт обробляється секундами — prefill перевіряє скасування між батчами
+    stats.cached_tokens = match prefill(session, &prompt_tokens, t.n_batch, Some(ticket)) {
+      Ok(n) => n,
+      Err(_) if ticket.is_cancelled() => return Ok(cancelled()),
+      Err(e) => {
+        // невідомо, що лишилось у KV-кеші, — наступний запит почне з нової сесії
+        loaded.session = None;
+        return Err(e);
+      }
+    };
+    stats.prompt_ms = started.elapsed().as_millis() as u64;
+
+    let started = Instant::now();
     let handle = session
-      .start_completing_with(sampler, t.max_tokens)
+      .start_completing_with(sampler, max_tokens)
       .map_err(|e| e.to_string())?;
 
-    let deadline = Instant::now() + Duration::from_millis(t.wall_time_ms);
-    let mut out = String::new();
-
-    for piece in handle.into_strings() {
-      out.push_str(&piece);
-      if out.contains("<|end|>") { break; }
-      if Instant::now() >= deadline { break; }
-      if out.len() > 12_000 { break; }
+    let deadline = started + Duration::from_millis(wall_time_ms);
+    let eos = session.model().eos();
+    let produced = Cell::new(0usize);
+    let saw_eos = Cell::new(false);
+    let tokens = handle.inspect(|tok| {
+      produced.set(produced.get() + 1);
+      if *tok == eos { saw_eos.set(true); }
+    });
+
+    let mut filter = StopFilter::new(format.stops());
+    let mut stop = None;
+    // дроп ітератора (і CompletionHandle) зупиняє потік генерації в llama_cpp
+    for piece in TokensToStrings::new(tokens, session.model()) {
+      if ticket.is_cancelled() { stop = Some(StopReason::Cancelled); break; }
+      if saw_eos.get() { stop = Some(StopReason::Eos); break; }
+      let (ready, hit_stop) = filter.push(&piece);
+      if !ready.is_empty() && !on_chunk(&ready) { stop = Some(StopReason::Cancelled); break; }
+      if hit_stop { stop = Some(StopReason::Eos); break; }
+      if Instant::now() >= deadline { stop = Some(StopReason::WallTime); break; }
+      if filter.len() > MAX_OUTPUT_BYTES { stop = Some(StopReason::MaxTokens); break; }
+    }
+    // потік сам закінчився: або EOS, або вичерпано max_tokens
+    let stop = stop.unwrap_or(if produced.get() >= max_tokens { StopReason::MaxTokens } else { StopReason::Eos });
+    stats.gen_tokens = produced.get();
+    stats.gen_ms = started.elapsed().as_millis() as u64;
+
+    // у кеші лишаємо лише промпт: позиції згенерованих токенів потік llama_cpp 0.3
+    // пише неточно, а наступний промпт однаково розходиться з ними після відповіді
+    if let Err(e) = session.truncate_context(prompt_tokens.len()) {
+      log::warn!("LLM session reset: {e}");
+      loaded.session = None;
     }
 
-    let out = out.split("<|end|>").next().unwrap_or(&out).trim().to_string();
-    Ok(out)
+    let (rest, text) = filter.finish();
+    if stop != StopReason::Cancelled && !rest.is_empty() {
+      on_chunk(&rest);
+    }
+    Ok(Generation { text: text.trim().to_string(), stop, stats })
   }
 }
 
 #[cfg(not(feature = "llm"))]
 mod inner {
-  pub fn answer(_system_prompt: &str, _user_prompt: &str) -> Result<String, String> {
+  use std::path::{Path, PathBuf};
+  use super::{ChatFormat, ChatMessage, GenOptions, Generation, Ticket};
+
+  pub fn generate(
+    _system_prompt: &str,
+    _messages: &[ChatMessage],
+    _opts: &GenOptions,
+    _ticket: &Ticket,
+    _on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String> {
+    Err("LLM feature disabled (run with --features llm)".into())
+  }
+
+  pub fn load(_path: &Path) -> Result<(), String> {
+    Err("LLM feature disabled (run with --features llm)".into())
+  }
+
+  pub fn ensure_loaded() -> Result<(), String> {
+    Err("LLM feature disabled (run with --features llm)".into())
+  }
+
+  pub fn warm_up(_system_prompt: &str, _template: Option<ChatFormat>) -> Result<usize, String> {
     Err("LLM feature disabled (run with --features llm)".into())
   }
+
+  pub fn unload() -> Result<bool, String> {
+    Ok(false)
+  }
+
+  pub fn loaded_path() -> Option<PathBuf> {
+    None
+  }
+
+  pub fn token_counter() -> Option<crate::prompt::CountFn> {
+    None
+  }
 }
 
-pub use inner::answer;
+pub use inner::{ensure_loaded, load, loaded_path, token_counter, unload, warm_up};
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn queue_policies() {
+    // власна черга: інші тести, що беруть квитки, паралельно сюди не потраплять
+    static Q: Queue = Queue::new();
+    let first = Q.begin(QueuePolicy::Queue).unwrap();
+    assert_eq!(Q.begin(QueuePolicy::Reject).err().as_deref(), Some(LLM_BUSY));
+
+    let queued = Q.begin(QueuePolicy::Queue).unwrap();
+    assert!(Q.cancel(Some(queued.id)));
+    assert!(queued.is_cancelled() && !first.is_cancelled());
+
+    let next = Q.begin(QueuePolicy::Replace).unwrap();
+    assert!(first.is_cancelled() && !next.is_cancelled());
+
+    drop((first, queued, next));
+    assert!(!Q.cancel(None));
+    assert!(Q.begin(QueuePolicy::Reject).is_ok());
+  }
+
+  #[test]
+  fn warm_session_reuses_only_the_shared_prefix() {
+    let system = [1, 2, 3, 4];
+    let first = [&system[..], &[10, 11]].concat();
+    let second = [&system[..], &[20, 21, 22]].concat();
+    assert_eq!(reusable_prefix(&[], &first), 0);
+    assert_eq!(reusable_prefix(&system, &first), 4);
+    assert_eq!(reusable_prefix(&first, &second), 4);
+    // той самий промпт: останній токен однаково переобчислюється
+    assert_eq!(reusable_prefix(&first, &first), first.len() - 1);
+    assert_eq!(reusable_prefix(&second, &system), 3);
+  }
+
+  #[test]
+  fn memory_estimate_counts_weights_and_kv_cache() {
+    let mut meta = GgufMeta { architecture: "llama".into(), ..Default::default() };
+    // Llama 3.2 3B: 28 шарів, 3072, 24 голови, 8 KV-голів
+    for (k, v) in [("block_count", 28), ("embedding_length", 3072), ("attention.head_count", 24), ("attention.head_count_kv", 8)] {
+      meta.ints.insert(format!("llama.{k}"), v);
+    }
+    let gib = 1u64 << 30;
+    let fit = MemoryFit::of(gib + gib / 2, &meta, 4096, 4 * gib);
+    let kv = 2 * 2 * 28 * 4096 * 3072 / 3;
+    assert_eq!(fit.required_bytes, gib + gib / 2 + kv + RUNTIME_OVERHEAD);
+    assert!(fit.fits);
+    assert!(!MemoryFit::of(gib + gib / 2, &meta, 4096, 2 * gib).fits);
+    // без розмірів архітектури — частка від файлу
+    let bare = GgufMeta::default();
+    assert_eq!(MemoryFit::of(8 * gib, &bare, 4096, 0).required_bytes, 9 * gib + RUNTIME_OVERHEAD);
+  }
+
+  fn stream(pieces: &[&str]) -> (Vec<String>, bool, String) {
+    let mut f = StopFilter::new(&["<|end|>", "<|eot_id|>"]);
+    let mut shown = vec![];
+    let mut stopped = false;
+    for p in pieces {
+      let (ready, hit) = f.push(p);
+      if !ready.is_empty() { shown.push(ready); }
+      if hit { stopped = true; break; }
+    }
+    let (rest, text) = f.finish();
+    if !rest.is_empty() { shown.push(rest); }
+    (shown, stopped, text)
+  }
+
+  #[test]
+  fn stop_sequence_split_across_pieces_is_never_shown() {
+    let (shown, stopped, text) = stream(&["Hades", " — ", "ро", "галик<|e", "nd|>", "ignored"]);
+    assert!(stopped);
+    assert_eq!(text, "Hades — рогалик");
+    assert_eq!(shown.concat(), text);
+    assert!(shown.iter().all(|s| !s.contains('<')));
+  }
+
+  #[test]
+  fn held_back_prefix_is_released_when_it_is_not_a_stop() {
+    let (shown, stopped, text) = stream(&["a <", "b> <|x"]);
+    assert!(!stopped);
+    assert_eq!(shown, vec!["a ", "<b> <|x"]);
+    assert_eq!(text, "a <b> <|x");
+  }
+}
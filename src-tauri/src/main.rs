@@ -1,38 +1,113 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod accounts;
+mod appcache;
+mod browser_ext;
+mod chat_template;
 mod commands;
+mod conversation;
+mod deeplink;
+mod gguf;
+mod launcher;
 mod llm_backend;
+mod models;
+mod nativehost;
+mod offline;
+mod openai_compat;
+mod picks;
+mod profiles;
+mod prompt;
+mod redact;
+mod refresh;
+mod scan_progress;
+mod scanner;
+mod secrets;
+mod steamid;
+mod url_policy;
+mod validate;
+mod vdf;
 
 fn main() {
+  // браузер запускає нас як native messaging host: без вікна, лише stdin/stdout
+  let args: Vec<String> = std::env::args().collect();
+  if nativehost::is_host_invocation(&args) {
+    nativehost::run();
+    return;
+  }
+
   tauri::Builder::default()
+    // першим: другий процес (посилання steamgamehelper:// на Windows/Linux) віддає
+    // argv цьому й виходить; з фічею deep-link URL доходять до on_open_url
+    .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| deeplink::focus_main(app)))
     // плагіни (за потреби можна й інші)
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_opener::init())
+    .plugin(tauri_plugin_deep_link::init())
+    .setup(|app| {
+      // дані з теки під старим identifier — до будь-якого читання налаштувань
+      commands::migrate_legacy_data_dir(app.handle());
+      // steamgamehelper:// (імпорт family з браузера, рекомендація, картка гри)
+      deeplink::init(app.handle());
+      // фонове оновлення кешу бібліотеки з Steam Web API
+      refresh::spawn(app.handle().clone());
+      // family_inbox.json від native messaging host
+      nativehost::spawn_inbox_watcher(app.handle().clone());
+      // модель, вибрана в менеджері моделей
+      models::init(app.handle());
+      Ok(())
+    })
     // РЕЄСТРАЦІЯ ВСІХ КОМАНД (важливо: тут є llm_chat)
     .invoke_handler(tauri::generate_handler![
       // settings/cache
       commands::get_settings,
       commands::save_settings,
+      validate::validate_settings,
       commands::load_library_cache,
       commands::save_library_cache,
       commands::load_cached_inputs,
 
+      // profiles
+      profiles::list_profiles,
+      profiles::create_profile,
+      profiles::switch_profile,
+      profiles::rename_profile,
+      profiles::delete_profile,
+
       // links/helpers
       commands::open_apikey_page,
       commands::open_family_page,
       commands::open_url_external,
-      commands::ensure_browser_helper,
+      browser_ext::ensure_browser_helper,
       commands::open_extensions_manager,
+      deeplink::take_pending_deep_links,
+      nativehost::install_native_host,
+      nativehost::get_family_members,
 
       // steam / scan
       commands::detect_accounts,
+      accounts::local_playtime_breakdown,
       commands::auto_detect_steam_roots,
       commands::scan_library_unified,
       commands::fetch_full_library,
+      commands::cancel_scan,
+      refresh::get_refresh_status,
+      launcher::launch_game,
+      launcher::open_steam_action,
+      launcher::get_play_history,
       commands::resolve_steamids_from_text,
+      commands::get_player_summary,
 
       // === LLM ===
       commands::llm_chat,
+      commands::llm_chat_stream,
+      commands::cancel_llm,
+      conversation::get_conversation,
+      conversation::delete_conversation,
+      models::list_models,
+      models::select_model,
+      models::unload_model,
+      models::reload_model,
+      models::benchmark_model,
     ])
     .run(tauri::generate_context!())
     .expect("error while running SteamGameHelper");
@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Runtime;
+
+use crate::chat_template::ChatMessage;
+use crate::commands::{chrono_now_u64, load_prefs_internal, save_prefs_internal, LLM_SYSTEM_PROMPT};
+use crate::gguf;
+use crate::llm_backend::{self, GenOptions, GenStats, LlmBackend, LocalLlama, MemoryFit, QueuePolicy};
+
+// ====================== Менеджер моделей ======================
+//
+// Список .gguf із тек моделей із метаданими із заголовка (архітектура,
+// параметри, квантизація, контекст) і оцінкою пам'яті, вибір моделі
+// (prefs.json → llm_model), вивантаження та перезавантаження. Перед
+// завантаженням llm_backend::load сам звіряє оцінку з вільною пам'яттю.
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ModelInfo {
+  pub path: String,
+  pub file_name: String,
+  pub size_bytes: u64,
+  pub architecture: String,
+  pub name: String,
+  pub parameters: u64,
+  pub quantization: Option<String>,
+  pub context_length: Option<u64>,
+  pub memory: Option<MemoryFit>,
+  pub selected: bool,
+  pub loaded: bool,
+  /// заголовок не прочитався: файл пошкоджений, недокачаний або не GGUF
+  pub error: Option<String>,
+}
+
+/// Оцінка пам'яті — з контекстом саме цього файлу, а не вибраної моделі.
+fn describe(path: &Path, available: u64) -> ModelInfo {
+  let mut info = ModelInfo {
+    path: path.to_string_lossy().into_owned(),
+    file_name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+    size_bytes: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    selected: llm_backend::model_path().as_deref() == Some(path),
+    loaded: llm_backend::loaded_path().as_deref() == Some(path),
+    ..Default::default()
+  };
+  match gguf::read_meta(path) {
+    Ok(meta) => {
+      let n_ctx = llm_backend::context_for(Some(&meta)) as u64;
+      info.memory = Some(MemoryFit::of(info.size_bytes, &meta, n_ctx, available));
+      info.quantization = meta.quantization().map(str::to_string);
+      info.context_length = meta.context_length();
+      info.parameters = meta.parameters;
+      info.architecture = meta.architecture;
+      info.name = meta.name;
+    }
+    Err(e) => info.error = Some(e),
+  }
+  info
+}
+
+fn describe_one(path: &Path) -> ModelInfo {
+  describe(path, llm_backend::available_memory())
+}
+
+/// Вибір із prefs.json — при старті, до першого запиту до моделі.
+pub fn init<R: Runtime>(app: &tauri::AppHandle<R>) {
+  let saved = load_prefs_internal(app).ok().and_then(|p| p.llm_model);
+  match saved.map(PathBuf::from) {
+    Some(p) if p.is_file() => llm_backend::set_model_path(Some(p)),
+    Some(p) => log::warn!("selected model is missing, falling back to auto: {}", p.display()),
+    None => {}
+  }
+}
+
+#[tauri::command]
+pub async fn list_models() -> Result<Vec<ModelInfo>, String> {
+  tauri::async_runtime::spawn_blocking(|| {
+    let available = llm_backend::available_memory();
+    let mut files = llm_backend::model_files();
+    // вибрана може лежати поза теками моделей
+    if let Some(p) = llm_backend::model_path().filter(|p| !files.contains(p)) {
+      files.insert(0, p);
+    }
+    files.iter().map(|p| describe(p, available)).collect()
+  })
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// `path: None` — знову автовибір. Нова модель завантажиться на наступному запиті або через reload_model.
+#[tauri::command]
+pub async fn select_model<R: Runtime>(app: tauri::AppHandle<R>, path: Option<String>) -> Result<Option<ModelInfo>, String> {
+  let path = match path {
+    Some(raw) => {
+      // без canonicalize: шлях має збігатися з тим, що віддав list_models
+      let p = PathBuf::from(raw.trim());
+      if !p.is_absolute() || !p.is_file() || p.extension().and_then(|s| s.to_str()) != Some("gguf") {
+        return Err("Not a .gguf file".into());
+      }
+      gguf::read_meta(&p).map_err(|e| format!("Invalid model file: {e}"))?;
+      Some(p)
+    }
+    None => None,
+  };
+
+  let mut prefs = load_prefs_internal(&app)?;
+  prefs.llm_model = path.as_ref().map(|p| p.to_string_lossy().into_owned());
+  save_prefs_internal(&app, &prefs)?;
+  llm_backend::set_model_path(path.clone());
+
+  Ok(path.map(|p| describe_one(&p)))
+}
+
+/// Звільнити пам'ять: поточна генерація скасовується. Наступний запит завантажить модель знову.
+#[tauri::command]
+pub async fn unload_model() -> Result<bool, String> {
+  llm_backend::cancel(None);
+  tauri::async_runtime::spawn_blocking(llm_backend::unload)
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Завантажити вибрану модель зараз (з перевіркою пам'яті) і прогріти system prompt,
+/// а не на першому запиті.
+#[tauri::command]
+pub async fn reload_model<R: Runtime>(app: tauri::AppHandle<R>) -> Result<ModelInfo, String> {
+  let path = llm_backend::model_path().ok_or("No GGUF model found")?;
+  let template = load_prefs_internal(&app)?.llm_template;
+  llm_backend::cancel(None);
+  tauri::async_runtime::spawn_blocking(move || {
+    llm_backend::load(&path)?;
+    // не вийшло — перший запит обчислить промпт повністю, як раніше
+    if let Err(e) = llm_backend::warm_up(LLM_SYSTEM_PROMPT, template) {
+      log::warn!("LLM warm-up failed: {e}");
+    }
+    Ok(describe_one(&path))
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
+
+// ====================== Бенчмарк ======================
+
+const BENCH_MAX_TOKENS: usize = 64;
+const BENCH_CANDIDATES: usize = 40;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Benchmark {
+  pub model: String,
+  pub n_threads: usize,
+  pub n_ctx: usize,
+  pub stats: GenStats,
+  /// лише по токенах, яких не було в кеші
+  pub prompt_tokens_per_sec: f64,
+  pub gen_tokens_per_sec: f64,
+}
+
+fn per_sec(tokens: usize, ms: u64) -> f64 {
+  if ms == 0 { 0.0 } else { tokens as f64 * 1000.0 / ms as f64 }
+}
+
+/// Запит, схожий на справжній: той самий system prompt і таблиця кандидатів.
+/// Мітка часу на початку репліки — щоб із кешу бралась лише system-частина.
+fn bench_prompt() -> String {
+  let rows = (0..BENCH_CANDIDATES)
+    .map(|i| format!("{}\tGame {i}\t{}\t{}\troguelike,coop", 1000 + i, i % 2, i * 37 % 600))
+    .collect::<Vec<_>>()
+    .join("\n");
+  format!("benchmark {}: щось коротке на вечір\n\n[candidates_tsv]\n{rows}", chrono_now_u64())
+}
+
+/// Швидкість локальної моделі: обробка промпту й генерація, токенів за секунду.
+/// Стає в ту саму чергу, що й запити асистента.
+#[tauri::command]
+pub async fn benchmark_model<R: Runtime>(app: tauri::AppHandle<R>) -> Result<Benchmark, String> {
+  let path = llm_backend::model_path().ok_or("No GGUF model found")?;
+  let opts = GenOptions {
+    template: load_prefs_internal(&app)?.llm_template,
+    max_tokens: Some(BENCH_MAX_TOKENS),
+    ..Default::default()
+  };
+  let ticket = llm_backend::begin(QueuePolicy::Queue)?;
+  let g = tauri::async_runtime::spawn_blocking(move || {
+    LocalLlama.generate(LLM_SYSTEM_PROMPT, &[ChatMessage::user(bench_prompt())], &opts, &ticket, &mut |_| true)
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+
+  let s = g.stats;
+  Ok(Benchmark {
+    model: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+    n_threads: llm_backend::thread_count(),
+    n_ctx: llm_backend::context_tokens(),
+    stats: s,
+    prompt_tokens_per_sec: per_sec(s.prompt_tokens - s.cached_tokens, s.prompt_ms),
+    gen_tokens_per_sec: per_sec(s.gen_tokens, s.gen_ms),
+  })
+}
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Emitter, Runtime};
+
+use crate::browser_ext::{self, Variant, FIREFOX_EXTENSION_ID};
+use crate::commands;
+use crate::deeplink;
+use crate::profiles;
+use crate::steamid;
+
+// ====================== Native messaging host ======================
+//
+// Браузер запускає той самий бінарник як host і спілкується через stdin/stdout:
+// 4 байти довжини (порядок байтів платформи) + JSON. Host не має AppHandle і
+// може працювати, коли застосунок закритий, тому лише перевіряє повідомлення
+// й кладе його у family_inbox.json; запущений застосунок забирає inbox сам.
+//
+// Chromium передає першим аргументом origin (`chrome-extension://<id>/`),
+// Firefox — шлях до маніфесту host і id розширення.
+
+pub const HOST_NAME: &str = "com.steamgamehelper.family";
+// має збігатися з "identifier" у tauri.conf.json (перевіряє тест): host обчислює app_data_dir без Tauri
+const APP_IDENTIFIER: &str = "com.steamgamehelper.app";
+const INBOX_FILE: &str = "family_inbox.json";
+const MEMBERS_FILE: &str = "family_members.json";
+pub const EVENT_FAMILY_MEMBERS: &str = "family-members-updated";
+
+// сторінка family — щонайбільше 6 учасників; запас на майбутнє, але не мегабайти
+const MAX_MESSAGE_BYTES: u32 = 64 * 1024;
+const MAX_MEMBERS: usize = 32;
+const MAX_PERSONA_CHARS: usize = 64;
+const INBOX_POLL: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FamilyRole {
+  Organizer,
+  Adult,
+  Child,
+  #[default]
+  Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct FamilyMember {
+  pub steamid64: String,
+  pub persona: String,
+  pub role: FamilyRole,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HostRequest {
+  Ping,
+  Family { members: Vec<FamilyMember> },
+}
+
+#[derive(Serialize)]
+struct HostReply {
+  ok: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  accepted: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  version: Option<&'static str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  error: Option<String>,
+}
+
+impl HostReply {
+  fn err(e: impl Into<String>) -> Self {
+    Self { ok: false, accepted: None, version: None, error: Some(e.into()) }
+  }
+}
+
+/// Чи запустив нас браузер як native messaging host.
+pub fn is_host_invocation(args: &[String]) -> bool {
+  args.iter().skip(1).any(|a| a.starts_with("chrome-extension://") || a == FIREFOX_EXTENSION_ID)
+}
+
+fn host_data_dir() -> Result<PathBuf, String> {
+  let base = dirs::data_dir().ok_or("No data dir")?;
+  let dir = base.join(APP_IDENTIFIER).join("steamgamehelper");
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  Ok(dir)
+}
+
+fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+  let mut len = [0u8; 4];
+  match input.read_exact(&mut len) {
+    Ok(()) => {}
+    // браузер закрив порт — штатне завершення
+    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+    Err(e) => return Err(e),
+  }
+  let len = u32::from_ne_bytes(len);
+  if len > MAX_MESSAGE_BYTES {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "message too large"));
+  }
+  let mut buf = vec![0u8; len as usize];
+  input.read_exact(&mut buf)?;
+  Ok(Some(buf))
+}
+
+fn write_message(out: &mut impl Write, reply: &HostReply) -> io::Result<()> {
+  let body = serde_json::to_vec(reply)?;
+  out.write_all(&(body.len() as u32).to_ne_bytes())?;
+  out.write_all(&body)?;
+  out.flush()
+}
+
+fn validate_members(members: Vec<FamilyMember>) -> Result<Vec<FamilyMember>, String> {
+  if members.is_empty() || members.len() > MAX_MEMBERS {
+    return Err(format!("expected 1..={MAX_MEMBERS} members"));
+  }
+  let mut out: Vec<FamilyMember> = Vec::with_capacity(members.len());
+  for mut m in members {
+    m.steamid64 = m.steamid64.trim().to_string();
+    steamid::parse_steamid64(&m.steamid64).map_err(|e| format!("{}: {}", m.steamid64, e.message()))?;
+    m.persona = m.persona.chars().filter(|c| !c.is_control()).take(MAX_PERSONA_CHARS).collect::<String>().trim().to_string();
+    // сторінка family не показує учасника двічі — дубль означає зіпсоване повідомлення
+    if out.iter().any(|o| o.steamid64 == m.steamid64) {
+      return Err(format!("{}: duplicate member", m.steamid64));
+    }
+    out.push(m);
+  }
+  Ok(out)
+}
+
+/// Запис через тимчасовий файл: застосунок не прочитає напівзаписаний inbox.
+fn write_inbox(members: &[FamilyMember]) -> Result<(), String> {
+  let dir = host_data_dir()?;
+  let tmp = dir.join(format!("{INBOX_FILE}.tmp"));
+  let data = serde_json::to_vec_pretty(members).map_err(|e| e.to_string())?;
+  fs::write(&tmp, data).map_err(|e| e.to_string())?;
+  fs::rename(&tmp, dir.join(INBOX_FILE)).map_err(|e| e.to_string())
+}
+
+fn handle_request(raw: &[u8]) -> HostReply {
+  let req: HostRequest = match serde_json::from_slice(raw) {
+    Ok(r) => r,
+    Err(e) => return HostReply::err(format!("bad request: {e}")),
+  };
+  match req {
+    HostRequest::Ping => HostReply { ok: true, accepted: None, version: Some(env!("CARGO_PKG_VERSION")), error: None },
+    HostRequest::Family { members } => match validate_members(members).and_then(|m| write_inbox(&m).map(|_| m.len())) {
+      Ok(n) => HostReply { ok: true, accepted: Some(n), version: None, error: None },
+      Err(e) => HostReply::err(e),
+    },
+  }
+}
+
+/// Цикл host-режиму; повертається, коли браузер закриває stdin.
+pub fn run() {
+  let mut input = io::stdin().lock();
+  let mut output = io::stdout().lock();
+  loop {
+    let reply = match read_message(&mut input) {
+      Ok(Some(raw)) => handle_request(&raw),
+      Ok(None) => return,
+      Err(e) => {
+        let _ = write_message(&mut output, &HostReply::err(e.to_string()));
+        return;
+      }
+    };
+    if write_message(&mut output, &reply).is_err() {
+      return;
+    }
+  }
+}
+
+// ====================== Бік застосунку: inbox ======================
+
+fn members_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(profiles::active_dir(app)?.join(MEMBERS_FILE))
+}
+
+fn confirm_text(members: &[FamilyMember]) -> String {
+  let list: Vec<String> = members
+    .iter()
+    .map(|m| if m.persona.is_empty() { m.steamid64.clone() } else { format!("{} ({})", m.persona, m.steamid64) })
+    .collect();
+  format!("Розширення браузера передало учасників Steam Family:\n\n{}\n\nДодати до family-списку?", list.join("\n"))
+}
+
+/// Викликається з потоку watcher: діалог підтвердження блокує лише його.
+fn ingest_inbox<R: Runtime>(app: &tauri::AppHandle<R>, inbox: &Path) -> Result<(), String> {
+  let bytes = fs::read(inbox).map_err(|e| e.to_string())?;
+  let _ = fs::remove_file(inbox);
+  // inbox пише інший процес — перевіряємо вдруге
+  let members = validate_members(serde_json::from_slice(&bytes).map_err(|e| e.to_string())?)?;
+
+  // як і steamgamehelper://import-family: нічого не зберігаємо без «OK»
+  if !deeplink::confirm_blocking(app, confirm_text(&members)) {
+    log::info!("family inbox declined");
+    return Ok(());
+  }
+  let ids: Vec<String> = members.iter().map(|m| m.steamid64.clone()).collect();
+  deeplink::import_family(app, &ids)?;
+
+  let data = serde_json::to_vec_pretty(&members).map_err(|e| e.to_string())?;
+  fs::write(members_path(app)?, data).map_err(|e| e.to_string())?;
+  let _ = app.emit(EVENT_FAMILY_MEMBERS, &members);
+  Ok(())
+}
+
+/// Стежить за family_inbox.json, поки працює застосунок.
+pub fn spawn_inbox_watcher<R: Runtime>(app: tauri::AppHandle<R>) {
+  std::thread::spawn(move || loop {
+    if let Ok(inbox) = commands::data_dir(&app).map(|d| d.join(INBOX_FILE)) {
+      if inbox.exists() {
+        if let Err(e) = ingest_inbox(&app, &inbox) {
+          log::warn!("family inbox rejected: {e}");
+        }
+      }
+    }
+    std::thread::sleep(INBOX_POLL);
+  });
+}
+
+#[tauri::command]
+pub async fn get_family_members<R: Runtime>(app: tauri::AppHandle<R>) -> Result<Vec<FamilyMember>, String> {
+  let p = members_path(&app)?;
+  if !p.exists() {
+    return Ok(vec![]);
+  }
+  let bytes = fs::read(p).map_err(|e| e.to_string())?;
+  Ok(serde_json::from_slice(&bytes).unwrap_or_default())
+}
+
+// ====================== Маніфести host ======================
+
+/// Id розпакованого розширення в Chromium: sha256 абсолютного шляху, 32 символи a–p.
+pub fn chromium_unpacked_id(dir: &Path) -> String {
+  let real = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+  #[cfg(windows)]
+  let bytes: Vec<u8> = {
+    let s = real.to_string_lossy();
+    let s = s.strip_prefix(r"\\?\").unwrap_or(&s).to_string();
+    // Chromium нормалізує літеру диска до верхнього регістру і хешує UTF-16LE
+    let mut chars: Vec<char> = s.chars().collect();
+    if chars.len() > 1 && chars[1] == ':' {
+      chars[0] = chars[0].to_ascii_uppercase();
+    }
+    chars.into_iter().collect::<String>().encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+  };
+  #[cfg(not(windows))]
+  let bytes: Vec<u8> = real.to_string_lossy().as_bytes().to_vec();
+
+  Sha256::digest(&bytes)[..16]
+    .iter()
+    .flat_map(|b| [b >> 4, b & 0xF])
+    .map(|n| (b'a' + n) as char)
+    .collect()
+}
+
+fn chromium_manifest(exe: &Path, extension_id: &str) -> serde_json::Value {
+  serde_json::json!({
+    "name": HOST_NAME,
+    "description": "SteamGameHelper family import",
+    "path": exe,
+    "type": "stdio",
+    "allowed_origins": [format!("chrome-extension://{extension_id}/")],
+  })
+}
+
+fn firefox_manifest(exe: &Path) -> serde_json::Value {
+  serde_json::json!({
+    "name": HOST_NAME,
+    "description": "SteamGameHelper family import",
+    "path": exe,
+    "type": "stdio",
+    "allowed_extensions": [FIREFOX_EXTENSION_ID],
+  })
+}
+
+/// Теки NativeMessagingHosts для (Chromium-подібні, Firefox). На Windows — реєстр замість тек.
+#[cfg(not(windows))]
+fn manifest_dirs() -> (Vec<PathBuf>, Vec<PathBuf>) {
+  let Some(home) = dirs::home_dir() else { return (vec![], vec![]); };
+  if cfg!(target_os = "macos") {
+    let base = home.join("Library/Application Support");
+    (
+      ["Google/Chrome", "Chromium", "BraveSoftware/Brave-Browser", "Microsoft Edge"]
+        .iter()
+        .map(|b| base.join(b).join("NativeMessagingHosts"))
+        .collect(),
+      vec![base.join("Mozilla/NativeMessagingHosts")],
+    )
+  } else {
+    let cfg = dirs::config_dir().unwrap_or_else(|| home.join(".config"));
+    (
+      ["google-chrome", "chromium", "BraveSoftware/Brave-Browser", "microsoft-edge"]
+        .iter()
+        .map(|b| cfg.join(b).join("NativeMessagingHosts"))
+        .collect(),
+      vec![home.join(".mozilla/native-messaging-hosts")],
+    )
+  }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct NativeHostInstall {
+  pub chromium_extension_id: String,
+  /// Куди записано/зареєстровано маніфести.
+  pub installed: Vec<String>,
+}
+
+fn write_json(path: &Path, v: &serde_json::Value) -> Result<(), String> {
+  let data = serde_json::to_vec_pretty(v).map_err(|e| e.to_string())?;
+  fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// Генерує маніфести host для Chromium і Firefox і реєструє їх для поточного користувача.
+/// `extension_id` — якщо розширення встановлене не з теки browser_helper.
+#[tauri::command]
+pub async fn install_native_host<R: Runtime>(
+  app: tauri::AppHandle<R>,
+  extension_id: Option<String>,
+) -> Result<NativeHostInstall, String> {
+  let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+  let ext_id = match extension_id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+    Some(id) if id.len() == 32 && id.bytes().all(|b| (b'a'..=b'p').contains(&b)) => id,
+    Some(_) => return Err("Chromium extension id must be 32 letters a-p".into()),
+    None => chromium_unpacked_id(&browser_ext::extension_dir(&app, Variant::Chromium)?),
+  };
+
+  let gen_dir = commands::data_dir(&app)?.join("native_host");
+  fs::create_dir_all(&gen_dir).map_err(|e| e.to_string())?;
+  let chromium_path = gen_dir.join(format!("{HOST_NAME}.chromium.json"));
+  let firefox_path = gen_dir.join(format!("{HOST_NAME}.firefox.json"));
+  write_json(&chromium_path, &chromium_manifest(&exe, &ext_id))?;
+  write_json(&firefox_path, &firefox_manifest(&exe))?;
+
+  let mut installed = vec![];
+
+  #[cfg(not(windows))]
+  {
+    let (chromium_dirs, firefox_dirs) = manifest_dirs();
+    let targets = chromium_dirs.into_iter().map(|d| (d, &chromium_path))
+      .chain(firefox_dirs.into_iter().map(|d| (d, &firefox_path)));
+    for (dir, src) in targets {
+      // браузер не встановлено — тека профілю відсутня
+      if !dir.parent().is_some_and(|p| p.exists()) { continue; }
+      let dst = dir.join(format!("{HOST_NAME}.json"));
+      let res = fs::create_dir_all(&dir).and_then(|_| fs::copy(src, &dst));
+      match res {
+        Ok(_) => installed.push(dst.to_string_lossy().to_string()),
+        Err(e) => log::warn!("native host manifest {}: {e}", dst.display()),
+      }
+    }
+  }
+
+  #[cfg(windows)]
+  {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let keys = [
+      ("Software\\Google\\Chrome\\NativeMessagingHosts", &chromium_path),
+      ("Software\\Chromium\\NativeMessagingHosts", &chromium_path),
+      ("Software\\BraveSoftware\\Brave-Browser\\NativeMessagingHosts", &chromium_path),
+      ("Software\\Microsoft\\Edge\\NativeMessagingHosts", &chromium_path),
+      ("Software\\Mozilla\\NativeMessagingHosts", &firefox_path),
+    ];
+    for (base, manifest) in keys {
+      let path = format!("{base}\\{HOST_NAME}");
+      match hkcu.create_subkey(&path).and_then(|(k, _)| k.set_value("", &manifest.to_string_lossy().to_string())) {
+        Ok(()) => installed.push(format!("HKCU\\{path}")),
+        Err(e) => log::warn!("native host registry {path}: {e}"),
+      }
+    }
+  }
+
+  Ok(NativeHostInstall { chromium_extension_id: ext_id, installed })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn frame(body: &[u8]) -> Vec<u8> {
+    let mut v = (body.len() as u32).to_ne_bytes().to_vec();
+    v.extend_from_slice(body);
+    v
+  }
+
+  fn member(id: &str) -> FamilyMember {
+    FamilyMember { steamid64: id.into(), persona: "p".into(), role: FamilyRole::Adult }
+  }
+
+  #[test]
+  fn identifier_matches_tauri_conf() {
+    let conf: serde_json::Value = serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+    assert_eq!(conf["identifier"].as_str(), Some(APP_IDENTIFIER));
+  }
+
+  #[test]
+  fn message_round_trip() {
+    let reply = HostReply { ok: true, accepted: Some(2), version: None, error: None };
+    let mut buf = vec![];
+    write_message(&mut buf, &reply).unwrap();
+    write_message(&mut buf, &HostReply::err("x")).unwrap();
+
+    let mut input = Cursor::new(buf);
+    let first: serde_json::Value = serde_json::from_slice(&read_message(&mut input).unwrap().unwrap()).unwrap();
+    assert_eq!(first, serde_json::json!({ "ok": true, "accepted": 2 }));
+    let second: serde_json::Value = serde_json::from_slice(&read_message(&mut input).unwrap().unwrap()).unwrap();
+    assert_eq!(second, serde_json::json!({ "ok": false, "error": "x" }));
+    // браузер закрив порт між повідомленнями
+    assert!(read_message(&mut input).unwrap().is_none());
+  }
+
+  #[test]
+  fn oversize_length_is_rejected_before_reading() {
+    let mut input = Cursor::new((MAX_MESSAGE_BYTES + 1).to_ne_bytes().to_vec());
+    assert_eq!(read_message(&mut input).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    let mut input = Cursor::new(u32::MAX.to_ne_bytes().to_vec());
+    assert_eq!(read_message(&mut input).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    let mut input = Cursor::new(frame(&vec![b' '; MAX_MESSAGE_BYTES as usize]));
+    assert_eq!(read_message(&mut input).unwrap().map(|b| b.len()), Some(MAX_MESSAGE_BYTES as usize));
+  }
+
+  #[test]
+  fn eof_inside_header_or_body() {
+    // обрізаний заголовок — як закритий порт
+    assert!(read_message(&mut Cursor::new(vec![5u8, 0])).unwrap().is_none());
+    let mut cut = frame(br#"{"type":"ping"}"#);
+    cut.truncate(cut.len() - 3);
+    assert_eq!(read_message(&mut Cursor::new(cut)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+  }
+
+  #[test]
+  fn members_are_validated() {
+    let ok = validate_members(vec![
+      FamilyMember { steamid64: " 76561197960287930 ".into(), persona: " A\u{7}lice\n".into(), role: FamilyRole::Organizer },
+      member("76561198000000000"),
+    ])
+    .unwrap();
+    assert_eq!(ok[0].steamid64, "76561197960287930");
+    assert_eq!(ok[0].persona, "Alice");
+    assert_eq!(ok.len(), 2);
+
+    let long = FamilyMember { persona: "я".repeat(MAX_PERSONA_CHARS * 2), ..member("76561197960287930") };
+    assert_eq!(validate_members(vec![long]).unwrap()[0].persona.chars().count(), MAX_PERSONA_CHARS);
+  }
+
+  #[test]
+  fn invalid_or_duplicate_members_are_rejected() {
+    assert!(validate_members(vec![]).is_err());
+    assert!(validate_members(vec![member("123")]).is_err());
+    assert!(validate_members(vec![member("76561197960265728")]).is_err());
+    assert!(validate_members(vec![member("76561197960287930"), member("103582791429521412")]).is_err());
+    assert!(validate_members(vec![member("76561197960287930"), member(" 76561197960287930")]).is_err());
+    let too_many = (1..=MAX_MEMBERS as u32 + 1).map(|i| member(&steamid::from_account_id(i).to_string())).collect();
+    assert!(validate_members(too_many).is_err());
+  }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::accounts;
+use crate::appcache;
+use crate::commands::LibraryGame;
+use crate::scan_progress::{CancelToken, ScanProgress, ScanReporter};
+use crate::scanner;
+use crate::vdf::{self, Vdf};
+
+// ====================== Офлайн-бібліотека ======================
+//
+// Без ключа чи мережі бібліотеку складаємо з файлів Steam: встановлені
+// маніфести, localconfig.vdf акаунта (що запускалося і скільки), ліцензії
+// там, де їх видно у відкритому вигляді, а імена — з appcache/appinfo.vdf.
+// Кожна гра позначена тим, наскільки ми певні, що вона є в бібліотеці.
+
+/// Наскільки певно гра належить акаунту.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+  /// лише ліцензія з пакета: може бути DLC/демо, яке ніколи не запускали
+  Low,
+  /// запускалася цим акаунтом (localconfig.vdf), але зараз не встановлена
+  Medium,
+  /// встановлена (appmanifest_*.acf)
+  High,
+  /// з Steam Web API
+  #[default]
+  Verified,
+}
+
+/// Пакети ліцензій із будь-якого блоку "Licenses" у текстових vdf — формат між версіями клієнта різниться.
+fn license_packages(roots: &[PathBuf], user_dir: Option<&PathBuf>) -> HashSet<u32> {
+  fn walk(node: &Vdf, out: &mut HashSet<u32>) {
+    for (k, v) in node.entries() {
+      if k.eq_ignore_ascii_case("Licenses") {
+        out.extend(v.entries().iter().filter_map(|(id, _)| id.parse::<u32>().ok()));
+      } else {
+        walk(v, out);
+      }
+    }
+  }
+
+  let mut files: Vec<PathBuf> = roots.iter().map(|r| r.join("config").join("config.vdf")).collect();
+  if let Some(dir) = user_dir {
+    files.push(dir.join("config").join("localconfig.vdf"));
+  }
+  let mut out = HashSet::new();
+  for f in files {
+    let Ok(txt) = fs::read_to_string(&f) else { continue; };
+    match vdf::parse(&txt) {
+      Ok(v) => walk(&v, &mut out),
+      Err(e) => log::warn!("{}: {e}", f.display()),
+    }
+  }
+  // 0 — базовий пакет Steam, є в усіх
+  out.remove(&0);
+  out
+}
+
+/// `account` — 32-бітний id; без нього беруться лише встановлені ігри.
+pub fn build(
+  roots: &[PathBuf],
+  account: Option<u32>,
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<Vec<LibraryGame>, String> {
+  let mut all: HashMap<i64, LibraryGame> = HashMap::new();
+  let game = |appid: i64, name: String, confidence: Confidence| LibraryGame {
+    appid,
+    name,
+    installed: confidence == Confidence::High,
+    shared_from: None,
+    playtime_minutes: None,
+    confidence,
+  };
+
+  for m in scanner::installed_manifests(roots, progress, cancel)? {
+    all.insert(m.appid, game(m.appid, m.name, Confidence::High));
+  }
+
+  let user_dir = account.and_then(|acc| {
+    accounts::userdata_dirs(roots).into_iter().find(|(id, _)| *id == acc).map(|(_, d)| d)
+  });
+  cancel.check()?;
+
+  let local = user_dir.as_ref().and_then(|d| accounts::read_localconfig(d));
+  progress.emit(ScanProgress::UserStats {
+    files: usize::from(local.is_some()),
+    apps: local.as_ref().map(|c| c.apps.len()).unwrap_or(0),
+  });
+  for (appid, st) in local.map(|c| c.apps).unwrap_or_default() {
+    let played = st.playtime_minutes.unwrap_or(0) > 0 || st.last_played_unix.is_some();
+    let g = match all.get_mut(&appid) {
+      Some(g) => g,
+      None if played => all.entry(appid).or_insert_with(|| game(appid, String::new(), Confidence::Medium)),
+      None => continue,
+    };
+    g.playtime_minutes = st.playtime_minutes.or(g.playtime_minutes);
+  }
+
+  if account.is_some() {
+    let packages = license_packages(roots, user_dir.as_ref());
+    if !packages.is_empty() {
+      cancel.check()?;
+      for root in roots {
+        let apps = appcache::read_package_apps(&root.join("appcache").join("packageinfo.vdf"), &packages);
+        for appid in apps {
+          all.entry(appid).or_insert_with(|| game(appid, String::new(), Confidence::Low));
+        }
+      }
+    }
+  }
+
+  // імена для того, що не з маніфестів; заодно відсіюємо DLC, інструменти, саундтреки
+  let unknown: HashSet<i64> = all.values().filter(|g| g.confidence != Confidence::High).map(|g| g.appid).collect();
+  if !unknown.is_empty() {
+    cancel.check()?;
+    let mut info = HashMap::new();
+    for root in roots {
+      let rest: HashSet<i64> = unknown.iter().filter(|id| !info.contains_key(*id)).copied().collect();
+      if rest.is_empty() { break; }
+      info.extend(appcache::read_appinfo(&root.join("appcache").join("appinfo.vdf"), &rest));
+    }
+    all.retain(|appid, g| {
+      if g.confidence == Confidence::High { return true; }
+      match info.get(appid) {
+        Some(i) if !i.kind.is_empty() && i.kind != "game" => false,
+        Some(i) => { g.name = i.name.clone(); true }
+        // без appinfo ліцензія — просто число; запущене лишаємо з appid замість назви
+        None => {
+          g.name = format!("App {appid}");
+          g.confidence == Confidence::Medium
+        }
+      }
+    });
+  }
+
+  Ok(all.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::appcache::tests::{appinfo, packageinfo};
+
+  #[test]
+  fn confidence_follows_the_source() {
+    let root = std::env::temp_dir().join(format!("sgh_offline_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let put = |rel: &str, bytes: &[u8]| {
+      let p = root.join(rel);
+      fs::create_dir_all(p.parent().unwrap()).unwrap();
+      fs::write(p, bytes).unwrap();
+    };
+    put("steamapps/appmanifest_10.acf", br#""AppState" { "appid" "10" "name" "Installed Game" "installdir" "ig" }"#);
+    put("userdata/22202/config/localconfig.vdf", br#""UserLocalConfigStore"
+{
+  "Licenses" { "5" { "Type" "0" } }
+  "Software" { "Valve" { "Steam" { "apps" {
+    "10" { "Playtime" "99" }
+    "20" { "Playtime" "30" }
+    "30" { "cloud" { "quota" "1" } }
+  } } } }
+}"#);
+    put("appcache/packageinfo.vdf", &packageinfo(0x0656_5528, &[(5, &[20, 40, 50, 60])]));
+    put("appcache/appinfo.vdf", &appinfo(0x0756_4429, &[(20, "Played Game", "Game"), (40, "Licensed Game", "game"), (50, "OST", "Music")]));
+
+    let roots = [root.clone()];
+    let (report, cancel) = (ScanReporter::default(), CancelToken::default());
+    let mut games = build(&roots, Some(22202), &report, &cancel).unwrap();
+    games.sort_by_key(|g| g.appid);
+    let got: Vec<(i64, &str, Confidence, bool, Option<u32>)> = games
+      .iter()
+      .map(|g| (g.appid, g.name.as_str(), g.confidence, g.installed, g.playtime_minutes))
+      .collect();
+    assert_eq!(got, vec![
+      (10, "Installed Game", Confidence::High, true, Some(99)),
+      (20, "Played Game", Confidence::Medium, false, Some(30)),
+      (40, "Licensed Game", Confidence::Low, false, None),
+    ]);
+
+    // без акаунта — лише встановлене
+    let only_installed = build(&roots, None, &report, &cancel).unwrap();
+    assert_eq!(only_installed.len(), 1);
+    assert_eq!(only_installed[0].confidence, Confidence::High);
+
+    cancel.cancel();
+    assert!(build(&roots, Some(22202), &report, &cancel).is_err());
+    let _ = fs::remove_dir_all(&root);
+  }
+}
@@ -0,0 +1,332 @@
+use serde_json::{json, Value};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::chat_template::ChatMessage;
+use crate::llm_backend::{GenOptions, GenStats, Generation, LlmBackend, StopReason, Ticket, MAX_OUTPUT_BYTES};
+use crate::redact;
+
+// ====================== OpenAI-сумісний сервер ======================
+//
+// POST {ai_base_url}/chat/completions зі stream: true — так уміють Ollama,
+// LM Studio, llama-server і vLLM. Шаблон чату застосовує сервер, тож
+// шлемо ролі як є. Граматики в цьому API немає: замість неї JSON mode
+// (response_format), а вивід однаково перевіряє picks::parse.
+
+// сервер на тому ж CPU може довго обробляти промпт
+const WALL_TIME: Duration = Duration::from_secs(180);
+// як часто між шматками перевіряємо скасування
+const POLL: Duration = Duration::from_millis(250);
+const MAX_ERROR_BODY: usize = 300;
+/// Контекст сервера через цей API не дізнатись; типовий num_ctx Ollama.
+/// Промпт, довший за контекст, сервер мовчки обрізає спереду разом із system.
+pub const DEFAULT_CONTEXT_TOKENS: usize = 4096;
+
+pub struct OpenAiCompat {
+  endpoint: String,
+  api_key: Option<String>,
+  /// порожня — сервер бере завантажену модель (llama-server, LM Studio)
+  model: String,
+}
+
+/// `http://localhost:11434` → `.../v1/chat/completions`; `.../v1` і повний шлях теж підходять.
+pub fn endpoint(base_url: &str) -> Result<String, String> {
+  let base = base_url.trim().trim_end_matches('/');
+  if base.is_empty() {
+    return Err("AI base URL is not set".into());
+  }
+  let url = reqwest::Url::parse(base).map_err(|e| format!("Invalid AI base URL: {e}"))?;
+  if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+    return Err("AI base URL must be http(s)://host[:port]".into());
+  }
+  Ok(if base.ends_with("/chat/completions") {
+    base.to_string()
+  } else if url.path() == "/" {
+    format!("{base}/v1/chat/completions")
+  } else {
+    format!("{base}/chat/completions")
+  })
+}
+
+/// Рядок SSE-потоку chat.completions.
+#[derive(Debug, PartialEq)]
+enum Event {
+  Delta { text: String, finish: Option<String> },
+  Error(String),
+  Done,
+}
+
+fn error_message(v: &Value) -> String {
+  v.pointer("/error/message")
+    .or_else(|| v.get("error"))
+    .map(|e| e.as_str().map(String::from).unwrap_or_else(|| e.to_string()))
+    .unwrap_or_default()
+}
+
+/// Коментарі, `event:` і порожні рядки — None.
+fn parse_line(line: &str) -> Option<Event> {
+  let data = line.strip_prefix("data:")?.trim();
+  if data == "[DONE]" {
+    return Some(Event::Done);
+  }
+  let v: Value = serde_json::from_str(data).ok()?;
+  if v.get("error").is_some() {
+    return Some(Event::Error(error_message(&v)));
+  }
+  let choice = v.get("choices")?.get(0)?;
+  let text = choice.pointer("/delta/content").and_then(Value::as_str).unwrap_or_default().to_string();
+  let finish = choice.get("finish_reason").and_then(Value::as_str).map(String::from);
+  Some(Event::Delta { text, finish })
+}
+
+fn stop_of(finish: Option<&str>) -> StopReason {
+  match finish {
+    Some("length") => StopReason::MaxTokens,
+    _ => StopReason::Eos,
+  }
+}
+
+/// Чекає `fut`, поки запит не скасовано і не минув `deadline`.
+async fn wait<T>(fut: impl Future<Output = T>, ticket: &Ticket, deadline: Instant) -> Result<T, StopReason> {
+  let mut fut = std::pin::pin!(fut);
+  loop {
+    if ticket.is_cancelled() {
+      return Err(StopReason::Cancelled);
+    }
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      return Err(StopReason::WallTime);
+    }
+    if let Ok(v) = tokio::time::timeout(left.min(POLL), &mut fut).await {
+      return Ok(v);
+    }
+  }
+}
+
+impl OpenAiCompat {
+  pub fn new(base_url: &str, api_key: Option<String>, model: &str) -> Result<Self, String> {
+    Ok(Self { endpoint: endpoint(base_url)?, api_key, model: model.trim().to_string() })
+  }
+
+  fn body(&self, system: &str, messages: &[ChatMessage], opts: &GenOptions) -> Value {
+    let messages: Vec<Value> = std::iter::once(json!({ "role": "system", "content": system.trim() }))
+      .chain(messages.iter().map(|m| json!({ "role": m.role, "content": m.text.trim() })))
+      .collect();
+    let mut body = json!({ "messages": messages, "stream": true, "temperature": 0.8 });
+    if !self.model.is_empty() {
+      body["model"] = json!(self.model);
+    }
+    if let Some(n) = opts.max_tokens {
+      body["max_tokens"] = json!(n);
+    }
+    if opts.json {
+      body["response_format"] = json!({ "type": "json_object" });
+    }
+    body
+  }
+
+  pub async fn complete(
+    &self,
+    system: &str,
+    messages: &[ChatMessage],
+    opts: &GenOptions,
+    ticket: &Ticket,
+    on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String> {
+    let deadline = Instant::now() + WALL_TIME;
+    let client = reqwest::Client::builder()
+      .connect_timeout(Duration::from_secs(10))
+      .build()
+      .map_err(redact::http_err)?;
+    let mut req = client.post(&self.endpoint).json(&self.body(system, messages, opts));
+    if let Some(key) = &self.api_key {
+      req = req.bearer_auth(key);
+    }
+
+    let mut resp = match wait(req.send(), ticket, deadline).await {
+      Ok(r) => r.map_err(redact::http_err)?,
+      Err(stop) => return Ok(Generation { text: String::new(), stop, stats: GenStats::default() }),
+    };
+    let status = resp.status();
+    if !status.is_success() {
+      let body = resp.text().await.unwrap_or_default();
+      let msg = serde_json::from_str::<Value>(&body).map(|v| error_message(&v)).unwrap_or(body);
+      let msg: String = msg.chars().take(MAX_ERROR_BODY).collect();
+      return Err(redact::scrub(&format!("LLM server returned {status}: {msg}")));
+    }
+
+    // stream проігноровано — звичайна відповідь одним JSON
+    let is_sse = resp
+      .headers()
+      .get(reqwest::header::CONTENT_TYPE)
+      .and_then(|v| v.to_str().ok())
+      .is_some_and(|v| v.starts_with("text/event-stream"));
+    if !is_sse {
+      let v: Value = match wait(resp.json(), ticket, deadline).await {
+        Ok(r) => r.map_err(redact::http_err)?,
+        Err(stop) => return Ok(Generation { text: String::new(), stop, stats: GenStats::default() }),
+      };
+      if v.get("error").is_some() {
+        return Err(redact::scrub(&format!("LLM server error: {}", error_message(&v))));
+      }
+      let choice = v.pointer("/choices/0").ok_or("LLM server response has no choices")?;
+      let text = choice.pointer("/message/content").and_then(Value::as_str).unwrap_or_default().trim().to_string();
+      if !text.is_empty() {
+        on_chunk(&text);
+      }
+      let stop = stop_of(choice.get("finish_reason").and_then(Value::as_str));
+      return Ok(Generation { text, stop, stats: GenStats::default() });
+    }
+
+    let mut text = String::new();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut stop = None;
+    'read: while stop.is_none() {
+      let chunk = match wait(resp.chunk(), ticket, deadline).await {
+        Ok(c) => c.map_err(redact::http_err)?,
+        Err(s) => { stop = Some(s); break; }
+      };
+      let Some(chunk) = chunk else { break; };
+      buf.extend_from_slice(&chunk);
+      // рядки цілком: шматок мережі може розрізати і рядок, і UTF-8
+      while let Some(nl) = buf.iter().position(|b| *b == b'\n') {
+        let line: Vec<u8> = buf.drain(..=nl).collect();
+        match parse_line(String::from_utf8_lossy(&line).trim_end()) {
+          Some(Event::Delta { text: piece, finish }) => {
+            text.push_str(&piece);
+            if !piece.is_empty() && !on_chunk(&piece) { stop = Some(StopReason::Cancelled); break 'read; }
+            if finish.is_some() { stop = Some(stop_of(finish.as_deref())); }
+            if text.len() > MAX_OUTPUT_BYTES { stop = Some(StopReason::MaxTokens); break 'read; }
+          }
+          Some(Event::Error(e)) => return Err(redact::scrub(&format!("LLM server error: {e}"))),
+          Some(Event::Done) => break 'read,
+          None => {}
+        }
+      }
+    }
+    // потік закрився без finish_reason
+    Ok(Generation { text: text.trim().to_string(), stop: stop.unwrap_or(StopReason::Eos), stats: GenStats::default() })
+  }
+}
+
+impl LlmBackend for OpenAiCompat {
+  fn generate(
+    &self,
+    system_prompt: &str,
+    messages: &[ChatMessage],
+    opts: &GenOptions,
+    ticket: &Ticket,
+    on_chunk: &mut dyn FnMut(&str) -> bool,
+  ) -> Result<Generation, String> {
+    // викликають із spawn_blocking, як і локальну модель
+    tauri::async_runtime::block_on(self.complete(system_prompt, messages, opts, ticket, on_chunk))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{BufRead, BufReader, Read, Write};
+  use std::net::TcpListener;
+
+  #[test]
+  fn endpoint_accepts_common_base_urls() {
+    assert_eq!(endpoint("http://localhost:11434").unwrap(), "http://localhost:11434/v1/chat/completions");
+    assert_eq!(endpoint(" http://127.0.0.1:1234/v1/ ").unwrap(), "http://127.0.0.1:1234/v1/chat/completions");
+    assert_eq!(endpoint("https://h/openai/v1/chat/completions").unwrap(), "https://h/openai/v1/chat/completions");
+    assert!(endpoint("").is_err());
+    assert!(endpoint("file:///etc/passwd").is_err());
+    assert!(endpoint("localhost:8080").is_err());
+  }
+
+  /// Один запит: повертає тіло запиту, відповідає `reply` кількома записами.
+  fn mock_server(reply: Vec<String>) -> (String, std::thread::JoinHandle<(String, Value)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let (mut head, mut len) = (String::new(), 0);
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+          len = v.trim().parse().unwrap();
+        }
+        if line == "\r\n" { break; }
+        head.push_str(&line);
+      }
+      let mut body = vec![0; len];
+      reader.read_exact(&mut body).unwrap();
+      for part in reply {
+        stream.write_all(part.as_bytes()).unwrap();
+        stream.flush().unwrap();
+      }
+      (head, serde_json::from_slice(&body).unwrap())
+    });
+    (base, handle)
+  }
+
+  fn run(backend: &OpenAiCompat, opts: &GenOptions) -> (Result<Generation, String>, Vec<String>) {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let mut chunks = vec![];
+    let ticket = Ticket::detached();
+    let res = rt.block_on(backend.complete(" sys ", &[ChatMessage::user("щось коротке")], opts, &ticket, &mut |t| {
+      chunks.push(t.to_string());
+      true
+    }));
+    (res, chunks)
+  }
+
+  #[test]
+  fn streams_chat_completions_from_mock_server() {
+    let sse = "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+               data: {\"choices\":[{\"delta\":{\"content\":\"{\\\"picks\\\":\"}}]}\n\n\
+               : keep-alive\n\n\
+               data: {\"choices\":[{\"delta\":{\"content\":\"[]}\"},\"finish_reason\":\"stop\"}]}\n\n\
+               data: [DONE]\n\n";
+    // розрізаємо посеред рядка, щоб перевірити буферизацію
+    let (a, b) = sse.split_at(60);
+    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+    let (base, server) = mock_server(vec![head.into(), a.into(), b.into()]);
+
+    let backend = OpenAiCompat::new(&base, Some("sk-test".into()), "qwen2.5:3b").unwrap();
+    let opts = GenOptions { json: true, max_tokens: Some(64), ..Default::default() };
+    let (res, chunks) = run(&backend, &opts);
+    let g = res.unwrap();
+    assert_eq!(g.text, "{\"picks\":[]}");
+    assert_eq!(g.stop, StopReason::Eos);
+    assert_eq!(chunks, vec!["{\"picks\":", "[]}"]);
+
+    let (head, body) = server.join().unwrap();
+    assert!(head.starts_with("POST /v1/chat/completions "));
+    assert!(head.to_ascii_lowercase().contains("authorization: bearer sk-test"));
+    assert_eq!(body["model"], "qwen2.5:3b");
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["max_tokens"], 64);
+    assert_eq!(body["response_format"]["type"], "json_object");
+    assert_eq!(body["messages"][0], json!({ "role": "system", "content": "sys" }));
+    assert_eq!(body["messages"][1], json!({ "role": "user", "content": "щось коротке" }));
+  }
+
+  #[test]
+  fn non_streaming_reply_and_server_errors() {
+    let json_reply = r#"{"choices":[{"message":{"content":" Hades "},"finish_reason":"length"}]}"#;
+    let head = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n", json_reply.len());
+    let (base, server) = mock_server(vec![head, json_reply.into()]);
+    let (res, chunks) = run(&OpenAiCompat::new(&base, None, "").unwrap(), &GenOptions::default());
+    let g = res.unwrap();
+    assert_eq!((g.text.as_str(), g.stop), ("Hades", StopReason::MaxTokens));
+    assert_eq!(chunks, vec!["Hades"]);
+    let (head, body) = server.join().unwrap();
+    assert!(!head.to_ascii_lowercase().contains("authorization"));
+    assert!(body.get("model").is_none() && body.get("response_format").is_none());
+
+    let err = r#"{"error":{"message":"model 'x' not found"}}"#;
+    let head = format!("HTTP/1.1 404 Not Found\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n", err.len());
+    let (base, server) = mock_server(vec![head, err.into()]);
+    let (res, _) = run(&OpenAiCompat::new(&base, None, "x").unwrap(), &GenOptions::default());
+    assert_eq!(res.unwrap_err(), "LLM server returned 404 Not Found: model 'x' not found");
+    server.join().unwrap();
+  }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// ====================== Рекомендації: граматика, перевірка, запасний варіант ======================
+//
+// Модель пише {"picks":[{"appid":..,"reason":".."}]} під GBNF-граматикою, у якій
+// appid — лише один із кандидатів. Вивід однаково перевіряємо: вигадані й
+// повторені appid відкидаємо, назву та «встановлено» беремо з бібліотеки, а не
+// з відповіді. Якщо нічого корисного не лишилось — евристичний топ кандидатів.
+
+pub const MAX_PICKS: usize = 7;
+const MAX_REASON_CHARS: usize = 120;
+// JSON на 7 ігор із причинами — кілька сотень токенів, більше за звичайний max_tokens
+pub const PICKS_MAX_TOKENS: usize = 448;
+
+#[derive(Clone, Debug)]
+pub struct Candidate {
+  pub appid: i64,
+  pub name: String,
+  pub installed: bool,
+  pub playmin: u32,
+  pub genres: Vec<String>,
+  pub score: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Recommendation {
+  pub appid: i64,
+  pub name: String,
+  pub reason: String,
+  pub installed: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PickSource {
+  Llm,
+  /// модель недоступна або її вивід не вдалося використати
+  Heuristic,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Picks {
+  pub source: PickSource,
+  pub picks: Vec<Recommendation>,
+}
+
+impl Picks {
+  /// Відповідь асистента для історії розмови — у тій самій схемі, що й граматика.
+  pub fn as_reply(&self) -> String {
+    let picks = self
+      .picks
+      .iter()
+      .map(|p| serde_json::json!({ "appid": p.appid, "reason": p.reason }))
+      .collect::<Vec<_>>();
+    serde_json::json!({ "picks": picks }).to_string()
+  }
+}
+
+/// GBNF для відповіді з appid лише з `cands`. Діапазонів у класах символів
+/// уникаємо: парсер граматик llama_cpp 0.3 розбирає `a-z` як три окремі символи.
+pub fn grammar(cands: &[Candidate]) -> String {
+  let appids = cands
+    .iter()
+    .map(|c| format!("\"{}\"", c.appid))
+    .collect::<Vec<_>>()
+    .join(" | ");
+  // до MAX_PICKS елементів: {m,n} цей парсер теж не знає, тож вкладені "?"
+  let mut list = String::from("pick");
+  for _ in 1..MAX_PICKS {
+    list = format!("pick (ws \",\" ws {list})?");
+  }
+  format!(
+    "root ::= \"{{\" ws \"\\\"picks\\\":\" ws \"[\" ws {list} ws \"]\" ws \"}}\"\n\
+     pick ::= \"{{\" ws \"\\\"appid\\\":\" ws appid ws \",\" ws \"\\\"reason\\\":\" ws reason ws \"}}\"\n\
+     appid ::= {appids}\n\
+     reason ::= \"\\\"\" char+ \"\\\"\"\n\
+     char ::= [^\"\\\\\\n\\r\\t] | \"\\\\\" [\"\\\\nt]\n\
+     ws ::= \" \"?\n"
+  )
+}
+
+fn clip(s: &str, max: usize) -> String {
+  let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+  match s.char_indices().nth(max) {
+    Some((i, _)) => format!("{}…", s[..i].trim_end()),
+    None => s,
+  }
+}
+
+/// Відповідь моделі → рекомендації лише з кандидатів, без повторів.
+/// Текст без граматики (інший бекенд) теж годиться, якщо в ньому є JSON-об'єкт.
+pub fn parse(text: &str, cands: &[Candidate]) -> Vec<Recommendation> {
+  #[derive(Deserialize)]
+  struct Raw {
+    picks: Vec<RawPick>,
+  }
+  #[derive(Deserialize)]
+  struct RawPick {
+    appid: serde_json::Value,
+    #[serde(default)]
+    reason: String,
+  }
+
+  let (Some(start), Some(end)) = (text.find('{'), text.rfind('}')) else {
+    return vec![];
+  };
+  let Ok(raw) = serde_json::from_str::<Raw>(text.get(start..=end).unwrap_or_default()) else {
+    return vec![];
+  };
+
+  let mut seen = HashSet::new();
+  raw
+    .picks
+    .into_iter()
+    .filter_map(|p| {
+      // деякі моделі пишуть appid рядком
+      let appid = p.appid.as_i64().or_else(|| p.appid.as_str()?.trim().parse().ok())?;
+      let c = cands.iter().find(|c| c.appid == appid)?;
+      let reason = clip(&p.reason, MAX_REASON_CHARS);
+      if reason.is_empty() || !seen.insert(appid) {
+        return None;
+      }
+      Some(Recommendation { appid, name: c.name.clone(), reason, installed: c.installed })
+    })
+    .take(MAX_PICKS)
+    .collect()
+}
+
+/// Найкращі за оцінкою кандидати (`cands` уже відсортовані) з причинами з тих самих ознак.
+pub fn heuristic(cands: &[Candidate], n: usize) -> Vec<Recommendation> {
+  cands
+    .iter()
+    .take(n)
+    .map(|c| {
+      let mut why = vec![];
+      if c.installed { why.push("installed".to_string()); }
+      match c.playmin {
+        0 => why.push("never played".into()),
+        1..=9 => why.push("barely tried".into()),
+        _ => {}
+      }
+      why.extend(c.genres.iter().filter(|g| g.as_str() != "misc").take(2).cloned());
+      Recommendation { appid: c.appid, name: c.name.clone(), reason: why.join(" · "), installed: c.installed }
+    })
+    .collect()
+}
+
+/// Вивід моделі, а якщо з нього нічого не вийшло — евристика.
+pub fn resolve(text: Option<&str>, cands: &[Candidate]) -> Picks {
+  let picks = text.map(|t| parse(t, cands)).unwrap_or_default();
+  if picks.is_empty() {
+    Picks { source: PickSource::Heuristic, picks: heuristic(cands, MAX_PICKS) }
+  } else {
+    Picks { source: PickSource::Llm, picks }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cand(appid: i64, name: &str, installed: bool, playmin: u32) -> Candidate {
+    Candidate { appid, name: name.into(), installed, playmin, genres: vec!["roguelike".into()], score: 0.0 }
+  }
+
+  #[test]
+  fn made_up_and_repeated_games_are_dropped() {
+    let cands = [cand(1145360, "Hades", true, 0), cand(620, "Portal 2", false, 5)];
+    let out = r#"Ось: {"picks":[
+      {"appid":1145360,"reason":"коротка  забіг"},
+      {"appid":999999,"reason":"вигадана"},
+      {"appid":"620","reason":"кооп"},
+      {"appid":1145360,"reason":"ще раз"},
+      {"appid":620,"reason":""}
+    ]}"#;
+    let picks = parse(out, &cands);
+    assert_eq!(picks.len(), 2);
+    assert_eq!(picks[0], Recommendation { appid: 1145360, name: "Hades".into(), reason: "коротка забіг".into(), installed: true });
+    assert_eq!((picks[1].appid, picks[1].name.as_str(), picks[1].installed), (620, "Portal 2", false));
+  }
+
+  #[test]
+  fn unusable_output_falls_back_to_heuristic() {
+    let cands = [cand(620, "Portal 2", true, 0), cand(400, "Portal", false, 300)];
+    for text in [None, Some("не JSON"), Some(r#"{"picks":[{"appid":1,"reason":"x"}]}"#), Some(r#"{"picks":[{"appid":620,"#)] {
+      let r = resolve(text, &cands);
+      assert_eq!(r.source, PickSource::Heuristic, "{text:?}");
+      assert_eq!(r.picks[0].reason, "installed · never played · roguelike");
+    }
+    assert_eq!(resolve(Some(r#"{"picks":[{"appid":400,"reason":"класика"}]}"#), &cands).source, PickSource::Llm);
+  }
+
+  #[test]
+  fn grammar_lists_only_candidate_appids() {
+    let g = grammar(&[cand(620, "Portal 2", true, 0), cand(400, "Portal", false, 0)]);
+    assert!(g.contains("appid ::= \"620\" | \"400\"\n"));
+    assert_eq!(g.matches("pick (ws").count(), MAX_PICKS - 1);
+    assert!(g.contains(r#"char ::= [^"\\\n\r\t] | "\\" ["\\nt]"#));
+    assert!(g.lines().all(|l| l.contains(" ::= ")));
+  }
+}
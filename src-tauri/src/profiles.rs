@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Runtime};
+
+use crate::commands::{self, AccountHint, UserPrefs};
+use crate::scan_progress;
+
+// ====================== Профілі ======================
+//
+// Кожен профіль має свій головний SteamID, family-список і власну теку
+// profiles/<id>/ з library_cache.json, app_meta.json і refresh_state.json.
+// Верхньорівневі main_steam_id64/family_ids у prefs.json — дзеркало активного
+// профілю, тож решта коду читає їх як і раніше.
+
+pub const EVENT_PROFILE_SWITCHED: &str = "profile-switched";
+const DEFAULT_ID: &str = "default";
+// файли, що до профілів лежали прямо в data_dir
+const LEGACY_FILES: &[&str] = &["library_cache.json", "app_meta.json", "refresh_state.json"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Profile {
+  pub id: String,
+  pub name: String,
+  pub main_steam_id64: String,
+  pub family_ids: Vec<String>,
+  pub created_at: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProfilesView {
+  pub active: String,
+  pub profiles: Vec<Profile>,
+  /// Локальні акаунти Steam, для яких профілю ще немає — для створення в один клік.
+  pub suggestions: Vec<AccountHint>,
+}
+
+fn is_safe_id(id: &str) -> bool {
+  !id.is_empty() && id.len() <= 48 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn slug(name: &str) -> String {
+  let mut s = String::new();
+  for c in name.trim().chars() {
+    if c.is_ascii_alphanumeric() {
+      s.push(c.to_ascii_lowercase());
+    } else if !s.ends_with('-') && !s.is_empty() {
+      s.push('-');
+    }
+  }
+  let s = s.trim_end_matches('-').chars().take(32).collect::<String>();
+  if s.is_empty() { "profile".into() } else { s }
+}
+
+fn unique_id(prefs: &UserPrefs, name: &str) -> String {
+  let base = slug(name);
+  let mut id = base.clone();
+  let mut n = 2;
+  while prefs.profiles.iter().any(|p| p.id == id) {
+    id = format!("{base}-{n}");
+    n += 1;
+  }
+  id
+}
+
+fn profiles_root<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(commands::data_dir(app)?.join("profiles"))
+}
+
+fn dir_for<R: Runtime>(app: &tauri::AppHandle<R>, id: &str) -> Result<PathBuf, String> {
+  if !is_safe_id(id) {
+    return Err(format!("Invalid profile id: {id}"));
+  }
+  let dir = profiles_root(app)?.join(id);
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  Ok(dir)
+}
+
+/// Тека активного профілю (кеші бібліотеки/метаданих/стан фонового оновлення).
+pub fn active_dir<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  let prefs = commands::load_prefs_internal(app)?;
+  dir_for(app, &prefs.active_profile)
+}
+
+/// Після читання prefs.json: гарантує хоча б один профіль і заповнює дзеркальні поля.
+/// Повертає true, якщо prefs треба зберегти (була міграція).
+pub(crate) fn sync_on_load<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &mut UserPrefs) -> bool {
+  let (changed, migrated) = normalize(prefs, commands::chrono_now_u64());
+  if migrated {
+    if let (Ok(data), Ok(dst)) = (commands::data_dir(app), dir_for(app, DEFAULT_ID)) {
+      move_legacy_files(&data, &dst);
+    }
+  }
+  changed
+}
+
+/// Без файлів: (чи змінились prefs, чи це перший запуск після prefs.json без профілів).
+fn normalize(prefs: &mut UserPrefs, now: u64) -> (bool, bool) {
+  let mut changed = false;
+  let migrated = prefs.profiles.is_empty();
+  if migrated {
+    prefs.profiles.push(Profile {
+      id: DEFAULT_ID.into(),
+      name: "Default".into(),
+      main_steam_id64: prefs.main_steam_id64.clone(),
+      family_ids: prefs.family_ids.clone(),
+      created_at: now,
+    });
+    prefs.active_profile = DEFAULT_ID.into();
+    changed = true;
+  }
+  if !prefs.profiles.iter().any(|p| p.id == prefs.active_profile) {
+    prefs.active_profile = prefs.profiles[0].id.clone();
+    changed = true;
+  }
+  if let Some(p) = prefs.profiles.iter().find(|p| p.id == prefs.active_profile) {
+    prefs.main_steam_id64 = p.main_steam_id64.clone();
+    prefs.family_ids = p.family_ids.clone();
+  }
+  (changed, migrated)
+}
+
+/// Перед записом prefs.json: зміни дзеркальних полів ідуть в активний профіль.
+pub(crate) fn sync_on_save(prefs: &mut UserPrefs) {
+  let (main, fam) = (prefs.main_steam_id64.clone(), prefs.family_ids.clone());
+  if let Some(p) = prefs.profiles.iter_mut().find(|p| p.id == prefs.active_profile) {
+    p.main_steam_id64 = main;
+    p.family_ids = fam;
+  }
+}
+
+/// Кеші з data_dir у теку профілю; те, що вже є в профілі, не перезаписуємо.
+fn move_legacy_files(data: &Path, dst: &Path) {
+  for f in LEGACY_FILES {
+    let src = data.join(f);
+    if src.exists() && !dst.join(f).exists() {
+      if let Err(e) = fs::rename(&src, dst.join(f)) {
+        log::warn!("profile migration: {f}: {e}");
+      }
+    }
+  }
+}
+
+// loginusers.vdf і userdata/ — файловий ввід-вивід, не на потоці рантайму
+async fn local_accounts() -> Result<Vec<AccountHint>, String> {
+  tauri::async_runtime::spawn_blocking(commands::detect_accounts_sync)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn view(prefs: UserPrefs, accounts: Vec<AccountHint>) -> ProfilesView {
+  let suggestions = accounts
+    .into_iter()
+    .filter(|a| !prefs.profiles.iter().any(|p| p.main_steam_id64 == a.steamid64))
+    .collect();
+  ProfilesView { active: prefs.active_profile, profiles: prefs.profiles, suggestions }
+}
+
+fn activate<R: Runtime>(app: &tauri::AppHandle<R>, prefs: &mut UserPrefs, id: &str) -> Result<(), String> {
+  let p = prefs.profiles.iter().find(|p| p.id == id).cloned().ok_or("Profile not found")?;
+  // скан/фонове оновлення старого профілю інакше запише кеш у новий
+  scan_progress::cancel_active();
+  prefs.active_profile = p.id.clone();
+  prefs.main_steam_id64 = p.main_steam_id64;
+  prefs.family_ids = p.family_ids;
+  commands::save_prefs_internal(app, prefs)?;
+  let _ = app.emit(EVENT_PROFILE_SWITCHED, &prefs.active_profile);
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn list_profiles<R: Runtime>(app: tauri::AppHandle<R>) -> Result<ProfilesView, String> {
+  let prefs = commands::load_prefs_internal(&app)?;
+  Ok(view(prefs, local_accounts().await?))
+}
+
+/// `name` за замовчуванням — persona з loginusers.vdf (кнопка «створити з виявленого акаунта»).
+#[tauri::command]
+pub async fn create_profile<R: Runtime>(
+  app: tauri::AppHandle<R>,
+  name: Option<String>,
+  main_steam_id64: String,
+  family_ids: Option<Vec<String>>,
+  activate_now: Option<bool>,
+) -> Result<Profile, String> {
+  let mut prefs = commands::load_prefs_internal(&app)?;
+  let main = main_steam_id64.trim().to_string();
+  let name = match name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()) {
+    Some(n) => n,
+    None => local_accounts()
+      .await?
+      .into_iter()
+      .find(|a| a.steamid64 == main && !a.persona.is_empty())
+      .map(|a| a.persona)
+      .unwrap_or_else(|| if main.is_empty() { "Profile".into() } else { main.clone() }),
+  };
+
+  let profile = Profile {
+    id: unique_id(&prefs, &name),
+    name,
+    main_steam_id64: main,
+    family_ids: commands::dedup_ids(family_ids.unwrap_or_default()),
+    created_at: commands::chrono_now_u64(),
+  };
+  dir_for(&app, &profile.id)?;
+  prefs.profiles.push(profile.clone());
+
+  if activate_now.unwrap_or(true) {
+    activate(&app, &mut prefs, &profile.id)?;
+  } else {
+    commands::save_prefs_internal(&app, &prefs)?;
+  }
+  Ok(profile)
+}
+
+#[tauri::command]
+pub async fn switch_profile<R: Runtime>(app: tauri::AppHandle<R>, id: String) -> Result<ProfilesView, String> {
+  let mut prefs = commands::load_prefs_internal(&app)?;
+  if prefs.active_profile != id {
+    activate(&app, &mut prefs, &id)?;
+  }
+  Ok(view(prefs, local_accounts().await?))
+}
+
+#[tauri::command]
+pub async fn rename_profile<R: Runtime>(app: tauri::AppHandle<R>, id: String, name: String) -> Result<(), String> {
+  let mut prefs = commands::load_prefs_internal(&app)?;
+  let name = name.trim().to_string();
+  if name.is_empty() {
+    return Err("Profile name is empty".into());
+  }
+  let p = prefs.profiles.iter_mut().find(|p| p.id == id).ok_or("Profile not found")?;
+  p.name = name;
+  commands::save_prefs_internal(&app, &prefs)
+}
+
+#[tauri::command]
+pub async fn delete_profile<R: Runtime>(app: tauri::AppHandle<R>, id: String) -> Result<ProfilesView, String> {
+  let mut prefs = commands::load_prefs_internal(&app)?;
+  if !prefs.profiles.iter().any(|p| p.id == id) {
+    return Err("Profile not found".into());
+  }
+  if prefs.profiles.len() == 1 {
+    return Err("Cannot delete the only profile".into());
+  }
+
+  let was_active = prefs.active_profile == id;
+  prefs.profiles.retain(|p| p.id != id);
+  if was_active {
+    let next = prefs.profiles[0].id.clone();
+    activate(&app, &mut prefs, &next)?;
+  } else {
+    commands::save_prefs_internal(&app, &prefs)?;
+  }
+
+  let dir = profiles_root(&app)?.join(&id);
+  if is_safe_id(&id) && dir.exists() {
+    fs::remove_dir_all(dir).map_err(|e| e.to_string())?;
+  }
+  Ok(view(prefs, local_accounts().await?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn prefs_with(ids: &[&str]) -> UserPrefs {
+    UserPrefs {
+      profiles: ids.iter().map(|id| Profile { id: id.to_string(), ..Default::default() }).collect(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn slug_is_a_safe_id() {
+    assert_eq!(slug("Bob's Gaming PC"), "bob-s-gaming-pc");
+    assert_eq!(slug("  --Alice--  "), "alice");
+    assert_eq!(slug("Марко"), "profile");
+    assert_eq!(slug(""), "profile");
+    assert_eq!(slug("Kid 2 / Steam Deck"), "kid-2-steam-deck");
+    let long = slug(&"x".repeat(100));
+    assert_eq!(long.len(), 32);
+    for name in ["Bob's Gaming PC", "Марко", "../../etc", &"a b".repeat(40)] {
+      assert!(is_safe_id(&slug(name)), "{name}");
+    }
+  }
+
+  #[test]
+  fn unique_id_appends_a_counter() {
+    let prefs = prefs_with(&["default", "bob", "bob-2"]);
+    assert_eq!(unique_id(&prefs, "Alice"), "alice");
+    assert_eq!(unique_id(&prefs, "Bob"), "bob-3");
+    assert_eq!(unique_id(&prefs_with(&["profile"]), "Марко"), "profile-2");
+  }
+
+  #[test]
+  fn single_profile_prefs_migrate_into_default() {
+    let mut prefs = UserPrefs {
+      main_steam_id64: "76561197960287930".into(),
+      family_ids: vec!["76561197960287931".into()],
+      ..Default::default()
+    };
+    assert_eq!(normalize(&mut prefs, 1_700_000_000), (true, true));
+    assert_eq!(prefs.active_profile, DEFAULT_ID);
+    assert_eq!(prefs.profiles.len(), 1);
+    let p = &prefs.profiles[0];
+    assert_eq!((p.id.as_str(), p.created_at), (DEFAULT_ID, 1_700_000_000));
+    assert_eq!(p.main_steam_id64, "76561197960287930");
+    assert_eq!(p.family_ids, vec!["76561197960287931"]);
+    // повторне читання вже нічого не мігрує
+    assert_eq!(normalize(&mut prefs, 1_800_000_000), (false, false));
+    assert_eq!(prefs.profiles[0].created_at, 1_700_000_000);
+  }
+
+  #[test]
+  fn mirror_follows_the_active_profile() {
+    let mut prefs = prefs_with(&["a", "b"]);
+    prefs.profiles[0].main_steam_id64 = "A".into();
+    prefs.profiles[1].main_steam_id64 = "B".into();
+    prefs.active_profile = "gone".into();
+    prefs.main_steam_id64 = "stale".into();
+    assert_eq!(normalize(&mut prefs, 0), (true, false));
+    assert_eq!((prefs.active_profile.as_str(), prefs.main_steam_id64.as_str()), ("a", "A"));
+
+    prefs.main_steam_id64 = "A2".into();
+    sync_on_save(&mut prefs);
+    assert_eq!(prefs.profiles[0].main_steam_id64, "A2");
+    assert_eq!(prefs.profiles[1].main_steam_id64, "B");
+  }
+
+  #[test]
+  fn legacy_files_move_without_overwriting() {
+    let data = std::env::temp_dir().join(format!("sgh_profiles_{}", std::process::id()));
+    let dst = data.join("profiles").join(DEFAULT_ID);
+    fs::create_dir_all(&dst).unwrap();
+    fs::write(data.join("library_cache.json"), "old cache").unwrap();
+    fs::write(data.join("app_meta.json"), "old meta").unwrap();
+    fs::write(dst.join("app_meta.json"), "profile meta").unwrap();
+    fs::write(data.join("prefs.json"), "{}").unwrap();
+
+    move_legacy_files(&data, &dst);
+    assert_eq!(fs::read_to_string(dst.join("library_cache.json")).unwrap(), "old cache");
+    assert!(!data.join("library_cache.json").exists());
+    assert_eq!(fs::read_to_string(dst.join("app_meta.json")).unwrap(), "profile meta");
+    assert!(data.join("app_meta.json").exists());
+    assert!(data.join("prefs.json").exists() && !dst.join("prefs.json").exists());
+    let _ = fs::remove_dir_all(&data);
+  }
+}
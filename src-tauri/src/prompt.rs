@@ -0,0 +1,235 @@
+use serde::Serialize;
+
+use crate::chat_template::ChatMessage;
+use crate::conversation::Conversation;
+use crate::picks::Candidate;
+
+// ====================== Промпт під бюджет токенів ======================
+//
+// Контекст моделі ділять system prompt, питання з профілем, хвіст розмови,
+// таблиця кандидатів і місце під відповідь (max_tokens). Питання й профіль
+// ідуть завжди; історія бере не більше HISTORY_SHARE залишку, кандидати —
+// решту, у порядку оцінки. Рахуємо токенізатором моделі, якщо вона вже в
+// пам'яті, інакше грубо за символами із запасом. Що не влізло — у PromptDebug.
+
+// розмітка шаблону навколо репліки: <|im_start|>user\n … <|im_end|>\n
+const PER_MESSAGE_OVERHEAD: usize = 8;
+// історії — не більше чверті того, що лишилось після питання й профілю
+const HISTORY_SHARE: usize = 4;
+// оцінка за символами може недорахувати, а переповнений контекст — це помилка декодування
+const APPROX_SLACK_PERCENT: usize = 15;
+
+pub const CANDIDATES_HEADER: &str = "appid\tname\tinstalled\tplaymin\tgenres\n";
+
+/// Груба оцінка без токенізатора: ~3 символи на токен (кирилиця дробиться дрібніше за латиницю).
+pub fn approx_tokens(text: &str) -> usize {
+  text.chars().count() / 3 + 1
+}
+
+/// Кількість токенів у тексті за токенізатором моделі.
+pub type CountFn = Box<dyn Fn(&str) -> usize + Send>;
+
+/// Токенізатор завантаженої моделі (llm_backend::token_counter) або оцінка.
+pub struct TokenCounter {
+  model: Option<CountFn>,
+}
+
+impl TokenCounter {
+  pub fn new(model: Option<CountFn>) -> Self {
+    Self { model }
+  }
+
+  pub fn approx() -> Self {
+    Self { model: None }
+  }
+
+  pub fn count(&self, text: &str) -> usize {
+    match &self.model {
+      Some(f) => f(text),
+      None => approx_tokens(text),
+    }
+  }
+
+  fn is_exact(&self) -> bool {
+    self.model.is_some()
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+  pub n_ctx: usize,
+  /// місце під відповідь
+  pub max_tokens: usize,
+}
+
+/// Як зібрано промпт: для налагодження в UI і в логах.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PromptDebug {
+  /// "model" — токенізатор моделі, "approx" — оцінка за символами
+  pub tokenizer: &'static str,
+  pub n_ctx: usize,
+  pub max_tokens: usize,
+  /// system + історія + поточна репліка, з розміткою шаблону
+  pub prompt_tokens: usize,
+  pub candidates_total: usize,
+  pub candidates_kept: usize,
+  pub history_total: usize,
+  pub history_kept: usize,
+  /// що відкинули і чому; порожньо — влізло все
+  pub dropped: Vec<String>,
+}
+
+pub struct Assembled {
+  /// хвіст історії і поточна репліка користувача останньою
+  pub messages: Vec<ChatMessage>,
+  /// лише ті, що потрапили в таблицю: з них і граматика, і евристика
+  pub cands: Vec<Candidate>,
+  pub debug: PromptDebug,
+}
+
+fn candidate_row(c: &Candidate) -> String {
+  format!(
+    "{}\t{}\t{}\t{}\t{}\n",
+    c.appid,
+    c.name.replace(['\t', '\n'], " "),
+    if c.installed { 1 } else { 0 },
+    c.playmin,
+    c.genres.join(","),
+  )
+}
+
+/// `ranked` — кандидати від найкращого; `question` уже без секретів (redact::scrub).
+pub fn assemble(
+  system: &str,
+  question: &str,
+  profile_json: &str,
+  ranked: &[Candidate],
+  conversation: Option<&Conversation>,
+  budget: Budget,
+  counter: &TokenCounter,
+) -> Result<Assembled, String> {
+  let cost = |text: &str| counter.count(text) + PER_MESSAGE_OVERHEAD;
+  let head = format!("{}\n\n[profile]\n{}\n\n[candidates_tsv]\n{CANDIDATES_HEADER}", question.trim(), profile_json.trim());
+
+  let mut total = budget.n_ctx.saturating_sub(budget.max_tokens);
+  if !counter.is_exact() {
+    total -= total * APPROX_SLACK_PERCENT / 100;
+  }
+  let fixed = cost(system) + cost(&head);
+  let mut left = total
+    .checked_sub(fixed)
+    .ok_or_else(|| format!("The question does not fit into the model context ({fixed} of {total} tokens)"))?;
+
+  let all_history = conversation.map(|c| c.messages.as_slice()).unwrap_or_default();
+  let history = conversation.map(|c| c.window(left / HISTORY_SHARE, cost)).unwrap_or_default();
+  left -= history.iter().map(|m| cost(&m.text)).sum::<usize>();
+
+  // строго за оцінкою: коротший рядок нижче в списку не обганяє кращого
+  let mut rows = String::new();
+  let mut cands = Vec::new();
+  for c in ranked {
+    let row = candidate_row(c);
+    let n = counter.count(&row);
+    if n > left {
+      break;
+    }
+    left -= n;
+    rows.push_str(&row);
+    cands.push(c.clone());
+  }
+  if cands.is_empty() && !ranked.is_empty() {
+    return Err("No candidate fits into the model context".into());
+  }
+
+  let mut dropped = vec![];
+  if cands.len() < ranked.len() {
+    dropped.push(format!(
+      "{} of {} candidates did not fit into {} context tokens",
+      ranked.len() - cands.len(),
+      ranked.len(),
+      budget.n_ctx
+    ));
+  }
+  if history.len() < all_history.len() {
+    dropped.push(format!("{} earlier messages left out of the prompt", all_history.len() - history.len()));
+  }
+
+  let mut messages = history.to_vec();
+  messages.push(ChatMessage::user(format!("{head}{rows}")));
+  let debug = PromptDebug {
+    tokenizer: if counter.is_exact() { "model" } else { "approx" },
+    n_ctx: budget.n_ctx,
+    max_tokens: budget.max_tokens,
+    prompt_tokens: cost(system) + messages.iter().map(|m| cost(&m.text)).sum::<usize>(),
+    candidates_total: ranked.len(),
+    candidates_kept: cands.len(),
+    history_total: all_history.len(),
+    history_kept: history.len(),
+    dropped,
+  };
+  Ok(Assembled { messages, cands, debug })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::picks::{PickSource, Picks, Recommendation};
+
+  fn cands(n: i64) -> Vec<Candidate> {
+    (0..n)
+      .map(|i| Candidate {
+        appid: 1000 + i,
+        name: format!("Game {i}"),
+        installed: i % 2 == 0,
+        playmin: 30,
+        genres: vec!["roguelike".into(), "coop".into()],
+        score: -(i as f32),
+      })
+      .collect()
+  }
+
+  // по токену на слово — щоб рахунок у тесті був передбачуваним
+  fn words() -> TokenCounter {
+    TokenCounter::new(Some(Box::new(|t: &str| t.split_whitespace().count())))
+  }
+
+  #[test]
+  fn candidates_fill_what_is_left_in_order() {
+    let budget = Budget { n_ctx: 120, max_tokens: 40 };
+    let a = assemble("sys", "щось коротке", "{}", &cands(30), None, budget, &words()).unwrap();
+    // system 1+8, питання з профілем і шапкою таблиці 10+8 — на рядки по 6 токенів лишається 53
+    assert_eq!(a.cands.len(), 8);
+    assert_eq!(a.cands.last().unwrap().appid, 1007);
+    let user = &a.messages.last().unwrap().text;
+    assert!(user.contains("1000\tGame 0\t1\t30\troguelike,coop\n"));
+    assert!(!user.contains("Game 8"));
+    assert_eq!(a.debug.tokenizer, "model");
+    assert_eq!((a.debug.candidates_total, a.debug.candidates_kept), (30, 8));
+    assert_eq!(a.debug.dropped, vec!["22 of 30 candidates did not fit into 120 context tokens"]);
+    assert!(a.debug.prompt_tokens <= budget.n_ctx - budget.max_tokens);
+
+    let all = assemble("sys", "x", "{}", &cands(3), None, Budget { n_ctx: 4096, max_tokens: 448 }, &words()).unwrap();
+    assert_eq!(all.cands.len(), 3);
+    assert!(all.debug.dropped.is_empty());
+    assert!(assemble("sys", "x", "{}", &cands(3), None, Budget { n_ctx: 20, max_tokens: 10 }, &words()).is_err());
+  }
+
+  #[test]
+  fn history_gets_a_share_and_approx_keeps_slack() {
+    let mut c = Conversation::default();
+    for i in 0..10 {
+      let picks = Picks { source: PickSource::Llm, picks: vec![Recommendation { appid: i, name: "g".into(), reason: "r".into(), installed: true }] };
+      c.record(&"слово ".repeat(20), &picks, i as u64);
+    }
+    let budget = Budget { n_ctx: 400, max_tokens: 100 };
+    let a = assemble("sys", "ще", "{}", &cands(50), Some(&c), budget, &words()).unwrap();
+    assert!(a.debug.history_kept > 0 && a.debug.history_kept < 20);
+    let history_tokens: usize = a.messages[..a.debug.history_kept].iter().map(|m| words().count(&m.text) + PER_MESSAGE_OVERHEAD).sum();
+    assert!(history_tokens <= 300 / HISTORY_SHARE);
+    assert!(a.debug.dropped.iter().any(|d| d.ends_with("earlier messages left out of the prompt")));
+
+    let approx = assemble("sys", "ще", "{}", &cands(50), None, budget, &TokenCounter::approx()).unwrap();
+    assert_eq!(approx.debug.tokenizer, "approx");
+    assert!(approx.debug.prompt_tokens <= 300 - 300 * APPROX_SLACK_PERCENT / 100);
+  }
+}
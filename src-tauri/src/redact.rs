@@ -0,0 +1,92 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::sync::Mutex;
+
+// ====================== Редагування секретів ======================
+//
+// Steam Web API приймає ключ лише у query string, а `reqwest::Error` друкує URL.
+// Усе, що йде назовні (помилки в UI, логи, refresh_state.json, промпти LLM),
+// проганяємо через `scrub`: спершу відомі значення ключів, потім шаблони.
+
+pub const MASK: &str = "[REDACTED]";
+
+static KNOWN: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+static RE_QUERY: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r#"(?i)([?&](?:key|api_key|apikey|access_token|token)=)[^&\s"'<>)]+"#).unwrap()
+});
+static RE_BEARER: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)(bearer\s+)[A-Za-z0-9._~+/=-]+"#).unwrap());
+// формат ключа Steam Web API — 32 hex-символи
+static RE_STEAM_KEY: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b[0-9A-Fa-f]{32}\b").unwrap());
+
+/// Запам'ятати значення секрету, щоб вирізати його навіть без відомого шаблону.
+pub fn remember(secret: &str) {
+  let s = secret.trim();
+  // короткі рядки дали б хибні збіги у звичайному тексті
+  if s.len() < 8 { return; }
+  if let Ok(mut g) = KNOWN.lock() {
+    if !g.iter().any(|k| k == s) {
+      g.push(s.to_string());
+    }
+  }
+}
+
+pub fn scrub(text: &str) -> String {
+  let mut out = text.to_string();
+  if let Ok(g) = KNOWN.lock() {
+    for k in g.iter() {
+      if out.contains(k.as_str()) {
+        out = out.replace(k.as_str(), MASK);
+      }
+    }
+  }
+  let out = RE_QUERY.replace_all(&out, format!("${{1}}{MASK}"));
+  let out = RE_BEARER.replace_all(&out, format!("${{1}}{MASK}"));
+  RE_STEAM_KEY.replace_all(&out, MASK).into_owned()
+}
+
+/// Замість `e.to_string()` для помилок reqwest: без URL і з вичищеними ключами.
+pub fn http_err(e: reqwest::Error) -> String {
+  scrub(&e.without_url().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const KEY: &str = "0123456789ABCDEF0123456789ABCDEF";
+
+  #[test]
+  fn scrubs_known_and_pattern_keys() {
+    remember("sk-local-ai-secret-value");
+    let s = scrub(&format!(
+      "GET https://api.steampowered.com/x/?key={KEY}&steamid=1 failed; ai=sk-local-ai-secret-value; Authorization: Bearer abc.def"
+    ));
+    assert!(!s.contains(KEY));
+    assert!(!s.contains("sk-local-ai-secret-value"));
+    assert!(!s.contains("abc.def"));
+    assert!(s.contains("steamid=1"));
+  }
+
+  #[test]
+  fn failed_request_error_has_no_key() {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let err = rt.block_on(async {
+      // порт 9 (discard) локально закритий — гарантований connect error з URL у тексті
+      reqwest::Client::new()
+        .get(format!("http://127.0.0.1:9/IPlayerService/GetOwnedGames/v1/?key={KEY}&steamid=76561197960287930"))
+        .send()
+        .await
+        .unwrap_err()
+    });
+    assert!(err.to_string().contains(KEY), "reqwest itself should leak the URL");
+    let msg = http_err(err);
+    assert!(!msg.contains(KEY), "{msg}");
+  }
+
+  #[test]
+  fn key_pasted_into_prompt_is_removed() {
+    let prompt = format!("порадь гру, мій ключ {KEY}");
+    assert!(!scrub(&prompt).contains(KEY));
+  }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{Emitter, Runtime};
+
+use crate::commands::{self, ScanMode};
+use crate::profiles;
+use crate::redact;
+use crate::scan_progress::{self, ScanReporter, SCAN_CANCELLED};
+
+// ====================== Фонове оновлення бібліотеки ======================
+//
+// Раз на TICK перевіряємо, чи кеш застарів (last_success + інтервал із prefs),
+// і лише тоді йдемо в Steam Web API. Після помилок — експоненційний backoff,
+// щоб не впертися в ліміти ключа (HTTP 429).
+
+pub const EVENT_LIBRARY_REFRESHED: &str = "library-refreshed";
+pub const DEFAULT_INTERVAL_HOURS: u32 = 6;
+
+const TICK: Duration = Duration::from_secs(5 * 60);
+const MIN_INTERVAL_SECS: u64 = 60 * 60;
+const BACKOFF_BASE_SECS: u64 = 15 * 60;
+const BACKOFF_MAX_SECS: u64 = 24 * 60 * 60;
+const RATE_LIMIT_BACKOFF_SECS: u64 = 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RefreshState {
+  pub last_attempt_unix: u64,
+  pub last_success_unix: u64,
+  /// "ok" | "error" | "skipped"
+  pub last_outcome: String,
+  pub last_error: Option<String>,
+  pub consecutive_failures: u32,
+  pub next_due_unix: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LibraryRefreshed {
+  pub games: usize,
+  pub at_unix: u64,
+}
+
+fn state_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(profiles::active_dir(app)?.join("refresh_state.json"))
+}
+
+pub fn load_state<R: Runtime>(app: &tauri::AppHandle<R>) -> RefreshState {
+  let Ok(p) = state_path(app) else { return RefreshState::default(); };
+  fs::read(p)
+    .ok()
+    .and_then(|b| serde_json::from_slice(&b).ok())
+    .unwrap_or_default()
+}
+
+fn save_state<R: Runtime>(app: &tauri::AppHandle<R>, st: &RefreshState) -> Result<(), String> {
+  let p = state_path(app)?;
+  let data = serde_json::to_vec_pretty(st).map_err(|e| e.to_string())?;
+  fs::write(p, data).map_err(|e| e.to_string())
+}
+
+/// 0 у prefs вимикає фонове оновлення.
+fn interval_secs(hours: Option<u32>) -> Option<u64> {
+  match hours.unwrap_or(DEFAULT_INTERVAL_HOURS) {
+    0 => None,
+    h => Some((h as u64 * 3600).max(MIN_INTERVAL_SECS)),
+  }
+}
+
+fn backoff_secs(failures: u32, err: &str) -> u64 {
+  let exp = BACKOFF_BASE_SECS.saturating_mul(1u64 << failures.min(10));
+  let secs = exp.min(BACKOFF_MAX_SECS);
+  if err.contains("429") { secs.max(RATE_LIMIT_BACKOFF_SECS) } else { secs }
+}
+
+/// Якщо стану ще немає, а кеш є — вважаємо моментом успіху mtime кешу.
+fn last_success<R: Runtime>(app: &tauri::AppHandle<R>, st: &RefreshState) -> u64 {
+  if st.last_success_unix > 0 { return st.last_success_unix; }
+  commands::cache_path(app)
+    .ok()
+    .and_then(|p| fs::metadata(p).ok())
+    .and_then(|m| m.modified().ok())
+    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+async fn tick<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<(), String> {
+  let prefs = commands::load_prefs_internal(app)?;
+  let Some(interval) = interval_secs(prefs.refresh_interval_hours) else { return Ok(()); };
+  let Ok(api_key) = commands::steam_api_key(app) else { return Ok(()); };
+  if prefs.main_steam_id64.trim().is_empty() {
+    return Ok(());
+  }
+
+  let mut st = load_state(app);
+  let now = commands::chrono_now_u64();
+  if now < st.next_due_unix { return Ok(()); }
+  if now < last_success(app, &st) + interval {
+    return Ok(());
+  }
+  // ручний скан має пріоритет — не конкуруємо за ліміти ключа; а ручний,
+  // що почнеться після цього, скасує наш (SCAN_CANCELLED нижче)
+  let Some(cancel) = scan_progress::try_begin_scan() else { return Ok(()); };
+
+  st.last_attempt_unix = now;
+  let res = commands::run_scan(
+    api_key,
+    prefs.main_steam_id64.clone(),
+    prefs.family_ids.clone(),
+    ScanMode::Online,
+    ScanReporter::default(),
+    cancel,
+  )
+  .await;
+
+  match res {
+    Ok(games) if games.is_empty() => {
+      // порожня відповідь (приватний профіль/збій API) не має затирати кеш
+      st.last_outcome = "skipped".into();
+      st.last_error = Some("Steam API returned no games".into());
+      st.next_due_unix = now + interval;
+    }
+    Ok(games) => {
+      commands::save_library_cache_internal(app, &games)?;
+      st.last_success_unix = now;
+      st.last_outcome = "ok".into();
+      st.last_error = None;
+      st.consecutive_failures = 0;
+      st.next_due_unix = now + interval;
+      let _ = app.emit(EVENT_LIBRARY_REFRESHED, LibraryRefreshed { games: games.len(), at_unix: now });
+    }
+    Err(e) if e == SCAN_CANCELLED => {
+      // перебив ручний скан — спробуємо на наступному тіку без штрафу
+      st.last_outcome = "skipped".into();
+      st.last_error = Some(e);
+    }
+    Err(e) => {
+      st.last_outcome = "error".into();
+      st.next_due_unix = now + backoff_secs(st.consecutive_failures, &e);
+      st.consecutive_failures = st.consecutive_failures.saturating_add(1);
+      st.last_error = Some(redact::scrub(&e));
+    }
+  }
+  save_state(app, &st)
+}
+
+pub fn spawn<R: Runtime>(app: tauri::AppHandle<R>) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      if let Err(e) = tick(&app).await {
+        log::warn!("background refresh: {}", redact::scrub(&e));
+      }
+      tokio::time::sleep(TICK).await;
+    }
+  });
+}
+
+#[tauri::command]
+pub async fn get_refresh_status<R: Runtime>(app: tauri::AppHandle<R>) -> Result<RefreshState, String> {
+  Ok(load_state(&app))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn interval_default_disabled_and_floor() {
+    assert_eq!(interval_secs(None), Some(DEFAULT_INTERVAL_HOURS as u64 * 3600));
+    assert_eq!(interval_secs(Some(0)), None);
+    assert_eq!(interval_secs(Some(1)), Some(MIN_INTERVAL_SECS));
+    assert_eq!(interval_secs(Some(24)), Some(24 * 3600));
+  }
+
+  #[test]
+  fn backoff_doubles_up_to_cap() {
+    assert_eq!(backoff_secs(0, "timeout"), BACKOFF_BASE_SECS);
+    assert_eq!(backoff_secs(1, "timeout"), 2 * BACKOFF_BASE_SECS);
+    assert_eq!(backoff_secs(3, "timeout"), 8 * BACKOFF_BASE_SECS);
+    assert_eq!(backoff_secs(7, "timeout"), BACKOFF_MAX_SECS);
+    // зсув обмежений — без переповнення навіть після тисяч невдач
+    assert_eq!(backoff_secs(u32::MAX, "timeout"), BACKOFF_MAX_SECS);
+  }
+
+  #[test]
+  fn rate_limit_waits_at_least_an_hour() {
+    assert_eq!(backoff_secs(0, "HTTP 429 Too Many Requests"), RATE_LIMIT_BACKOFF_SECS);
+    assert_eq!(backoff_secs(7, "HTTP 429"), BACKOFF_MAX_SECS);
+  }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc, Mutex, OnceLock,
+};
+use tauri::ipc::Channel;
+use tokio::sync::Notify;
+
+// ====================== Події прогресу скану ======================
+
+pub const SCAN_CANCELLED: &str = "Scan cancelled";
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum ScanProgress {
+  Started { accounts: usize },
+  /// Steam API недоступне або вимкнене — будуємо бібліотеку з локальних файлів.
+  Offline { reason: String },
+  ApiAccount { steamid64: String, index: usize, total: usize, games: usize },
+  LibraryWalk { path: String, index: usize, total: usize },
+  Manifests { count: usize },
+  UserStats { files: usize, apps: usize },
+  Finished { games: usize },
+  Cancelled,
+}
+
+/// Обгортка над необов'язковим каналом: команди без `on_progress` просто нічого не шлють.
+#[derive(Clone, Default)]
+pub struct ScanReporter {
+  chan: Option<Channel<ScanProgress>>,
+}
+
+impl ScanReporter {
+  pub fn new(chan: Option<Channel<ScanProgress>>) -> Self {
+    Self { chan }
+  }
+
+  pub fn emit(&self, ev: ScanProgress) {
+    if let Some(c) = &self.chan {
+      // вікно могли закрити посеред скану — це не помилка скану
+      let _ = c.send(ev);
+    }
+  }
+}
+
+// ====================== Скасування ======================
+
+#[derive(Clone, Default)]
+pub struct CancelToken {
+  flag: Arc<AtomicBool>,
+  notify: Arc<Notify>,
+}
+
+impl CancelToken {
+  pub fn cancel(&self) {
+    self.flag.store(true, Ordering::SeqCst);
+    self.notify.notify_waiters();
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.flag.load(Ordering::SeqCst)
+  }
+
+  /// Для синхронних циклів (WalkDir, парсинг vdf).
+  pub fn check(&self) -> Result<(), String> {
+    if self.is_cancelled() { Err(SCAN_CANCELLED.into()) } else { Ok(()) }
+  }
+
+  /// Для `tokio::select!` навколо HTTP-запитів: дроп футури обриває запит.
+  pub async fn cancelled(&self) {
+    loop {
+      let n = self.notify.notified();
+      if self.is_cancelled() { return; }
+      n.await;
+    }
+  }
+}
+
+static ACTIVE: OnceLock<Mutex<Option<CancelToken>>> = OnceLock::new();
+
+fn active() -> &'static Mutex<Option<CancelToken>> {
+  ACTIVE.get_or_init(|| Mutex::new(None))
+}
+
+/// Реєструє новий скан. Попередній (якщо ще йде) скасовується — одночасно крутиться лише один.
+pub fn begin_scan() -> CancelToken {
+  let token = CancelToken::default();
+  if let Ok(mut g) = active().lock() {
+    if let Some(prev) = g.replace(token.clone()) {
+      prev.cancel();
+    }
+  }
+  token
+}
+
+/// Як begin_scan, але лише коли жоден скан не йде: фонове оновлення не
+/// скасовує ручний скан. Перевірка й реєстрація — під одним замком.
+pub fn try_begin_scan() -> Option<CancelToken> {
+  let mut g = active().lock().ok()?;
+  if g.is_some() {
+    return None;
+  }
+  let token = CancelToken::default();
+  *g = Some(token.clone());
+  Some(token)
+}
+
+pub fn end_scan(token: &CancelToken) {
+  if let Ok(mut g) = active().lock() {
+    if g.as_ref().is_some_and(|t| Arc::ptr_eq(&t.flag, &token.flag)) {
+      *g = None;
+    }
+  }
+}
+
+pub fn cancel_active() -> bool {
+  match active().lock() {
+    Ok(g) => match g.as_ref() {
+      Some(t) => { t.cancel(); true }
+      None => false,
+    },
+    Err(_) => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn try_begin_scan_does_not_preempt_a_running_scan() {
+    let manual = begin_scan();
+    assert!(try_begin_scan().is_none());
+    assert!(!manual.is_cancelled());
+    end_scan(&manual);
+
+    let background = try_begin_scan().unwrap();
+    // ручний скан, як і раніше, перебиває фоновий
+    let manual = begin_scan();
+    assert!(background.is_cancelled());
+    end_scan(&background);
+    assert!(try_begin_scan().is_none());
+    end_scan(&manual);
+  }
+}
@@ -0,0 +1,258 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::scan_progress::{CancelToken, ScanProgress, ScanReporter};
+
+// ====================== Бібліотеки Steam (steamapps) ======================
+//
+// appmanifest_*.acf завжди лежать прямо в steamapps/, тому дерево не обходимо:
+// common/, workshop/ і shadercache/ можуть містити сотні тисяч файлів.
+
+static RE_APPID: Lazy<Regex> = Lazy::new(|| Regex::new(r#""appid"\s*"(\d+)""#).unwrap());
+static RE_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r#""name"\s*"([^"]+)""#).unwrap());
+static RE_INSTALLDIR: Lazy<Regex> = Lazy::new(|| Regex::new(r#""installdir"\s*"([^"]+)""#).unwrap());
+static RE_LF_PATH: Lazy<Regex> = Lazy::new(|| Regex::new(r#""path"\s*"([^"]+)""#).unwrap());
+
+#[derive(Clone, Debug)]
+pub struct AppManifest {
+  pub appid: i64,
+  pub name: String,
+  /// тека гри в `<steamapps>/common/`; у старих маніфестах буває відсутня
+  pub installdir: String,
+  /// steamapps/, де лежить маніфест
+  pub library: PathBuf,
+}
+
+impl AppManifest {
+  pub fn install_path(&self) -> Option<PathBuf> {
+    if self.installdir.is_empty() { return None; }
+    Some(self.library.join("common").join(&self.installdir))
+  }
+}
+
+pub fn is_manifest(p: &Path) -> bool {
+  p.file_name()
+    .and_then(|s| s.to_str())
+    .map(|f| f.starts_with("appmanifest_") && f.ends_with(".acf"))
+    .unwrap_or(false)
+}
+
+pub fn parse_manifest(s: &str) -> Option<(i64, String, String)> {
+  let appid = RE_APPID.captures(s)
+    .and_then(|c| c.get(1))
+    .and_then(|m| m.as_str().parse::<i64>().ok())?;
+  let name = RE_NAME.captures(s)
+    .and_then(|c| c.get(1))
+    .map(|m| m.as_str().to_string())?;
+  let installdir = RE_INSTALLDIR.captures(s)
+    .and_then(|c| c.get(1))
+    .map(|m| m.as_str().to_string())
+    .unwrap_or_default();
+  Some((appid, name, installdir))
+}
+
+fn read_manifest(file: &Path) -> Option<AppManifest> {
+  let txt = fs::read_to_string(file).ok()?;
+  let (appid, name, installdir) = parse_manifest(&txt)?;
+  let library = file.parent()?.to_path_buf();
+  Some(AppManifest { appid, name, installdir, library })
+}
+
+/// `libraryfolders.vdf` → список `<path>/steamapps`. Шляхи у vdf екрановані (`C:\\Games`).
+pub fn parse_libraryfolders(path: &Path) -> Option<Vec<PathBuf>> {
+  let txt = fs::read_to_string(path).ok()?;
+  let mut out = vec![];
+  for cap in RE_LF_PATH.captures_iter(&txt) {
+    let raw = cap.get(1)?.as_str().replace("\\\\", "\\");
+    out.push(PathBuf::from(raw).join("steamapps"));
+  }
+  if out.is_empty() { None } else { Some(out) }
+}
+
+/// Ключ для дедуплікації: той самий каталог може прийти з кореня, з libraryfolders.vdf
+/// (там завжди є і сам корінь) і через симлінк ~/.steam/steam → ~/.local/share/Steam.
+fn dir_key(p: &Path) -> String {
+  let real = fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+  let s = real.to_string_lossy().replace('\\', "/");
+  let s = s.trim_end_matches('/').to_string();
+  if cfg!(windows) { s.to_lowercase() } else { s }
+}
+
+/// Усі унікальні steamapps/ для заданих коренів Steam.
+pub fn library_dirs(roots: &[PathBuf]) -> Vec<PathBuf> {
+  let mut seen = HashSet::<String>::new();
+  let mut out = vec![];
+  for root in roots {
+    let steamapps = root.join("steamapps");
+    if !steamapps.is_dir() { continue; }
+
+    let mut cands = vec![steamapps.clone()];
+    if let Some(extra) = parse_libraryfolders(&steamapps.join("libraryfolders.vdf")) {
+      cands.extend(extra);
+    }
+    for dir in cands {
+      if !dir.is_dir() { continue; }
+      if seen.insert(dir_key(&dir)) {
+        out.push(dir);
+      }
+    }
+  }
+  out
+}
+
+/// Маніфести лише з верхнього рівня steamapps/.
+pub fn list_manifests(dir: &Path) -> Vec<PathBuf> {
+  let Ok(entries) = fs::read_dir(dir) else { return vec![]; };
+  entries
+    .flatten()
+    .map(|e| e.path())
+    .filter(|p| is_manifest(p) && p.is_file())
+    .collect()
+}
+
+/// Паралельний парсинг: читання .acf на HDD/мережевих дисках домінує над regex.
+pub fn parse_manifests(paths: &[PathBuf], cancel: &CancelToken) -> Result<Vec<AppManifest>, String> {
+  if paths.is_empty() { return Ok(vec![]); }
+  let workers = num_cpus::get().clamp(1, 8).min(paths.len());
+  let chunk = paths.len().div_ceil(workers);
+
+  let parsed: Vec<AppManifest> = std::thread::scope(|s| {
+    let handles: Vec<_> = paths
+      .chunks(chunk)
+      .map(|part| {
+        s.spawn(move || {
+          let mut out = Vec::with_capacity(part.len());
+          for file in part {
+            if cancel.is_cancelled() { break; }
+            if let Some(m) = read_manifest(file) {
+              out.push(m);
+            }
+          }
+          out
+        })
+      })
+      .collect();
+    handles.into_iter().flat_map(|h| h.join().unwrap_or_default()).collect()
+  });
+  cancel.check()?;
+
+  let mut seen = HashSet::<i64>::new();
+  Ok(parsed.into_iter().filter(|m| seen.insert(m.appid)).collect())
+}
+
+/// Встановлені ігри з усіх бібліотек: unique steamapps → маніфести depth=1 → паралельний парсинг.
+pub fn installed_manifests(
+  roots: &[PathBuf],
+  progress: &ScanReporter,
+  cancel: &CancelToken,
+) -> Result<Vec<AppManifest>, String> {
+  let dirs = library_dirs(roots);
+  let total = dirs.len();
+
+  let mut files: Vec<PathBuf> = vec![];
+  for (index, dir) in dirs.into_iter().enumerate() {
+    cancel.check()?;
+    progress.emit(ScanProgress::LibraryWalk {
+      path: dir.to_string_lossy().to_string(),
+      index,
+      total,
+    });
+    files.extend(list_manifests(&dir));
+  }
+
+  let out = parse_manifests(&files, cancel)?;
+  progress.emit(ScanProgress::Manifests { count: out.len() });
+  Ok(out)
+}
+
+/// Маніфест однієї гри: шукаємо appmanifest_<appid>.acf у кожній бібліотеці, без повного скану.
+pub fn find_manifest(roots: &[PathBuf], appid: i64) -> Option<AppManifest> {
+  library_dirs(roots)
+    .into_iter()
+    .map(|dir| dir.join(format!("appmanifest_{appid}.acf")))
+    .filter(|p| p.is_file())
+    .find_map(|p| read_manifest(&p))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Instant;
+  use walkdir::WalkDir;
+
+  fn fake_library(tag: &str, manifests: usize, deep_games: usize) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("sgh_fake_steam_{tag}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let steamapps = root.join("steamapps");
+    fs::create_dir_all(&steamapps).unwrap();
+
+    // libraryfolders.vdf завжди містить і сам корінь — перевіряємо, що він не скануватиметься двічі
+    let lf = format!(
+      "\"libraryfolders\"\n{{\n  \"0\"\n  {{\n    \"path\"    \"{}\"\n  }}\n}}\n",
+      root.to_string_lossy().replace('\\', "\\\\")
+    );
+    fs::write(steamapps.join("libraryfolders.vdf"), lf).unwrap();
+
+    for i in 0..manifests {
+      let appid = 10_000 + i;
+      let acf = format!(
+        "\"AppState\"\n{{\n  \"appid\"  \"{appid}\"\n  \"name\"  \"Game {i}\"\n  \"installdir\"  \"game_{i}\"\n}}\n"
+      );
+      fs::write(steamapps.join(format!("appmanifest_{appid}.acf")), acf).unwrap();
+    }
+
+    // глибоке дерево common/ з «шумом», який старий WalkDir обходив повністю
+    for g in 0..deep_games {
+      let mut dir = steamapps.join("common").join(format!("game_{g}"));
+      for depth in 0..6 {
+        dir = dir.join(format!("lvl{depth}"));
+        fs::create_dir_all(&dir).unwrap();
+        for f in 0..20 {
+          fs::write(dir.join(format!("asset_{f}.bin")), b"x").unwrap();
+        }
+      }
+    }
+    root
+  }
+
+  #[test]
+  fn dedups_root_listed_in_libraryfolders() {
+    let root = fake_library("dedup", 3, 1);
+    let dirs = library_dirs(std::slice::from_ref(&root));
+    assert_eq!(dirs.len(), 1);
+
+    let found = installed_manifests(std::slice::from_ref(&root), &ScanReporter::default(), &CancelToken::default()).unwrap();
+    assert_eq!(found.len(), 3);
+
+    let one = find_manifest(std::slice::from_ref(&root), 10_001).unwrap();
+    assert_eq!(one.install_path(), Some(root.join("steamapps").join("common").join("game_1")));
+    let _ = fs::remove_dir_all(root);
+  }
+
+  // cargo test --release bench_scan_fake_library -- --ignored --nocapture
+  #[test]
+  #[ignore]
+  fn bench_scan_fake_library() {
+    let root = fake_library("bench", 2_000, 300);
+
+    let t = Instant::now();
+    let walked = WalkDir::new(root.join("steamapps"))
+      .into_iter()
+      .filter_map(|e| e.ok())
+      .filter(|e| is_manifest(e.path()))
+      .count();
+    let walk_ms = t.elapsed().as_millis();
+
+    let t = Instant::now();
+    let found = installed_manifests(std::slice::from_ref(&root), &ScanReporter::default(), &CancelToken::default()).unwrap();
+    let scan_ms = t.elapsed().as_millis();
+
+    eprintln!("full WalkDir: {walked} manifests in {walk_ms} ms");
+    eprintln!("depth-1 + parallel parse: {} manifests in {scan_ms} ms", found.len());
+    assert_eq!(found.len(), 2_000);
+    let _ = fs::remove_dir_all(root);
+  }
+}
@@ -0,0 +1,249 @@
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Runtime;
+
+use crate::commands;
+use crate::redact;
+
+// ====================== Сховище секретів ======================
+//
+// Ключі API шифруються ChaCha20-Poly1305. Ключ шифрування — випадкові 32 байти
+// у vault.key поруч із prefs.json, доступний лише поточному користувачу
+// (0600 на unix; на Windows app data dir і так під ACL профілю).
+// Ім'я запису йде як AAD, тож шифротекст не можна «переставити» між полями.
+
+const VAULT_VERSION: u32 = 1;
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Secret {
+  SteamApiKey,
+  AiApiKey,
+}
+
+impl Secret {
+  fn name(self) -> &'static str {
+    match self {
+      Secret::SteamApiKey => "steam_api_key",
+      Secret::AiApiKey => "ai_api_key",
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct VaultFile {
+  version: u32,
+  entries: HashMap<String, String>,
+}
+
+fn key_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(commands::data_dir(app)?.join("vault.key"))
+}
+
+fn vault_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+  Ok(commands::data_dir(app)?.join("secrets.vault.json"))
+}
+
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+  let mut opts = fs::OpenOptions::new();
+  opts.write(true).create_new(true);
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    opts.mode(0o600);
+  }
+  let mut f = opts.open(path)?;
+  f.write_all(data)?;
+  f.sync_all()
+}
+
+const KEY_LEN: usize = 32;
+// скільки чекати, поки інший виклик допише vault.key
+const KEY_WAIT_TRIES: u32 = 50;
+const KEY_WAIT_STEP: Duration = Duration::from_millis(10);
+
+fn to_key(bytes: Vec<u8>) -> Result<Key, String> {
+  let arr: [u8; KEY_LEN] = bytes.try_into().map_err(|_| "vault.key is corrupted".to_string())?;
+  Ok(Key::from(arr))
+}
+
+/// Створити vault.key. Якщо паралельний виклик створив його першим — беремо його ключ:
+/// файл міг ще дописуватись, тож коротший за KEY_LEN перечитуємо.
+fn create_key(path: &Path) -> Result<Key, String> {
+  let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+  match write_private(path, &key) {
+    Ok(()) => return Ok(key),
+    Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+    Err(e) => return Err(e.to_string()),
+  }
+  for _ in 0..KEY_WAIT_TRIES {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    if bytes.len() >= KEY_LEN {
+      return to_key(bytes);
+    }
+    std::thread::sleep(KEY_WAIT_STEP);
+  }
+  Err("vault.key is corrupted".into())
+}
+
+fn load_or_create_key(path: &Path) -> Result<Key, String> {
+  match fs::read(path) {
+    Ok(bytes) => to_key(bytes),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => create_key(path),
+    Err(e) => Err(e.to_string()),
+  }
+}
+
+fn cipher<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<ChaCha20Poly1305, String> {
+  let key = load_or_create_key(&key_path(app)?)?;
+  Ok(ChaCha20Poly1305::new(&key))
+}
+
+fn load_vault<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<VaultFile, String> {
+  let p = vault_path(app)?;
+  if !p.exists() {
+    return Ok(VaultFile { version: VAULT_VERSION, ..Default::default() });
+  }
+  let bytes = fs::read(p).map_err(|e| e.to_string())?;
+  serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+fn save_vault<R: Runtime>(app: &tauri::AppHandle<R>, v: &VaultFile) -> Result<(), String> {
+  let p = vault_path(app)?;
+  let data = serde_json::to_vec_pretty(v).map_err(|e| e.to_string())?;
+  fs::write(p, data).map_err(|e| e.to_string())
+}
+
+pub fn get<R: Runtime>(app: &tauri::AppHandle<R>, which: Secret) -> Result<Option<String>, String> {
+  let vault = load_vault(app)?;
+  let Some(enc) = vault.entries.get(which.name()) else { return Ok(None); };
+  let raw = B64.decode(enc).map_err(|e| e.to_string())?;
+  if raw.len() <= NONCE_LEN {
+    return Err("vault entry is corrupted".into());
+  }
+  let (nonce, ct) = raw.split_at(NONCE_LEN);
+  let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| "vault entry is corrupted".to_string())?;
+  let pt = cipher(app)?
+    .decrypt(&Nonce::from(nonce), Payload { msg: ct, aad: which.name().as_bytes() })
+    .map_err(|_| "Cannot decrypt stored secret (vault.key changed?)".to_string())?;
+  let v = String::from_utf8(pt).map_err(|e| e.to_string())?;
+  redact::remember(&v);
+  Ok(Some(v))
+}
+
+/// Порожнє значення видаляє запис.
+pub fn set<R: Runtime>(app: &tauri::AppHandle<R>, which: Secret, value: &str) -> Result<(), String> {
+  let mut vault = load_vault(app)?;
+  vault.version = VAULT_VERSION;
+  let value = value.trim();
+  if value.is_empty() {
+    vault.entries.remove(which.name());
+  } else {
+    redact::remember(value);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ct = cipher(app)?
+      .encrypt(&nonce, Payload { msg: value.as_bytes(), aad: which.name().as_bytes() })
+      .map_err(|_| "Cannot encrypt secret".to_string())?;
+    let mut raw = nonce.to_vec();
+    raw.extend_from_slice(&ct);
+    vault.entries.insert(which.name().to_string(), B64.encode(raw));
+  }
+  save_vault(app, &vault)
+}
+
+// скільки останніх символів ключа видно в прев'ю
+const MASK_REVEAL: usize = 4;
+
+/// Для UI: лише останні MASK_REVEAL символи, решта — крапки. Ключ, коротший
+/// за 2 * MASK_REVEAL, не показуємо взагалі: хвіст був би більшою його частиною.
+pub fn mask(s: &str) -> String {
+  let chars: Vec<char> = s.chars().collect();
+  if chars.len() < 2 * MASK_REVEAL {
+    return "•".repeat(chars.len().max(MASK_REVEAL));
+  }
+  let tail: String = chars[chars.len() - MASK_REVEAL..].iter().collect();
+  format!("{}{}", "•".repeat(chars.len() - MASK_REVEAL), tail)
+}
+
+/// Стан секрету для налаштувань: (чи задано, маскований прев'ю). Сирий ключ назовні не віддаємо.
+pub fn preview<R: Runtime>(app: &tauri::AppHandle<R>, which: Secret) -> (bool, Option<String>) {
+  match get(app, which) {
+    Ok(Some(v)) if !v.is_empty() => (true, Some(mask(&v))),
+    _ => (false, None),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mask_short_keys_reveal_nothing() {
+    assert_eq!(mask(""), "••••");
+    assert_eq!(mask("ab"), "••••");
+    assert_eq!(mask("abcd"), "••••");
+    assert_eq!(mask("abcde"), "•••••");
+    assert_eq!(mask("abcdefg"), "•••••••");
+  }
+
+  #[test]
+  fn mask_shows_only_the_tail() {
+    assert_eq!(mask("abcdefgh"), "••••efgh");
+    let key = "0123456789ABCDEF0123456789ABCDEF";
+    let m = mask(key);
+    assert!(m.ends_with("CDEF"));
+    assert_eq!(m.chars().count(), key.len());
+    assert!(!m.contains("0123"));
+  }
+
+  fn temp_key_path(tag: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sgh_vault_{tag}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("vault.key")
+  }
+
+  #[test]
+  fn losing_the_create_race_reads_the_winners_key() {
+    let path = temp_key_path("race");
+    let winner = [7u8; KEY_LEN];
+    write_private(&path, &winner).unwrap();
+    // create_key після того, як інший виклик уже створив файл
+    assert_eq!(create_key(&path).unwrap(), Key::from(winner));
+    assert_eq!(load_or_create_key(&path).unwrap(), Key::from(winner));
+
+    let path = temp_key_path("threads");
+    let keys: Vec<Key> = std::thread::scope(|s| {
+      let hs: Vec<_> = (0..8).map(|_| s.spawn(|| load_or_create_key(&path).unwrap())).collect();
+      hs.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert!(keys.iter().all(|k| *k == keys[0]));
+    assert_eq!(fs::read(&path).unwrap(), keys[0].to_vec());
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+  }
+
+  #[test]
+  fn corrupted_key_file_is_an_error() {
+    let path = temp_key_path("corrupt");
+    fs::write(&path, [1u8; KEY_LEN + 1]).unwrap();
+    assert!(load_or_create_key(&path).is_err());
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+  }
+
+  #[test]
+  fn mask_counts_chars_not_bytes() {
+    // кожен символ — кілька байтів: зріз по байтах упав би посеред символу
+    assert_eq!(mask("ключ-доступу"), "••••••••тупу");
+    assert_eq!(mask("🔑🔑🔑🔑🔑"), "•••••");
+    assert_eq!(mask("🔑🔑🔑🔑🔑🔑🔑🔑"), "••••🔑🔑🔑🔑");
+    assert_eq!(mask("ключ"), "••••");
+  }
+}
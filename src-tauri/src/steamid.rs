@@ -0,0 +1,95 @@
+// ====================== SteamID64 ======================
+//
+// Біти SteamID64: [63..56] universe, [55..52] тип акаунта, [51..32] instance,
+// [31..0] account id (той самий, що в userdata/<accountid> і data-miniprofile).
+
+const UNIVERSE_PUBLIC: u64 = 1;
+const ACCOUNT_TYPE_INDIVIDUAL: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SteamIdError {
+  Format,
+  Universe,
+  AccountType,
+}
+
+impl SteamIdError {
+  pub fn message(self) -> &'static str {
+    match self {
+      SteamIdError::Format => "SteamID64 must be 17 digits starting with 7656119",
+      SteamIdError::Universe => "SteamID64 is not in the public Steam universe",
+      SteamIdError::AccountType => "SteamID64 is not an individual user account",
+    }
+  }
+}
+
+pub fn parse_steamid64(s: &str) -> Result<u64, SteamIdError> {
+  let s = s.trim();
+  if s.len() != 17 || !s.bytes().all(|b| b.is_ascii_digit()) {
+    return Err(SteamIdError::Format);
+  }
+  let id: u64 = s.parse().map_err(|_| SteamIdError::Format)?;
+  if id >> 56 != UNIVERSE_PUBLIC {
+    return Err(SteamIdError::Universe);
+  }
+  if (id >> 52) & 0xF != ACCOUNT_TYPE_INDIVIDUAL {
+    return Err(SteamIdError::AccountType);
+  }
+  // instance 1 (desktop) і ненульовий account id: 7656119... між BASE+1 і BASE+u32::MAX
+  if id <= STEAMID64_BASE || id > from_account_id(u32::MAX) {
+    return Err(SteamIdError::Format);
+  }
+  Ok(id)
+}
+
+/// SteamID64 індивідуального акаунта в публічному універсумі з нульовим account id.
+pub const STEAMID64_BASE: u64 = 76_561_197_960_265_728;
+
+/// 32-бітний account id — ім'я теки userdata/<accountid>.
+pub fn account_id(steamid64: u64) -> u32 {
+  (steamid64 & 0xFFFF_FFFF) as u32
+}
+
+pub fn from_account_id(account_id: u32) -> u64 {
+  STEAMID64_BASE + account_id as u64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn accepts_individual_public_ids() {
+    assert_eq!(parse_steamid64("76561197960287930"), Ok(76_561_197_960_287_930));
+    assert_eq!(parse_steamid64("  76561198000000000\n"), Ok(76_561_198_000_000_000));
+    assert_eq!(parse_steamid64("76561197960265729"), Ok(from_account_id(1)));
+    assert_eq!(parse_steamid64(&from_account_id(u32::MAX).to_string()), Ok(from_account_id(u32::MAX)));
+    assert_eq!(account_id(76_561_197_960_287_930), 22202);
+  }
+
+  #[test]
+  fn rejects_outside_the_individual_range() {
+    // account id 0 і все нижче BASE: instance 0 при тих самих universe/type
+    assert_eq!(parse_steamid64("76561197960265728"), Err(SteamIdError::Format));
+    assert_eq!(parse_steamid64("76561197960265727"), Err(SteamIdError::Format));
+    // instance 2
+    assert_eq!(parse_steamid64(&(from_account_id(u32::MAX) + 1).to_string()), Err(SteamIdError::Format));
+  }
+
+  #[test]
+  fn rejects_wrong_universe_or_type() {
+    assert_eq!(parse_steamid64("10000000000000000"), Err(SteamIdError::Universe));
+    // universe 1, тип 2 (multiseat) і 3 (game server)
+    assert_eq!(parse_steamid64("81064797587636229"), Err(SteamIdError::AccountType));
+    assert_eq!(parse_steamid64(&((1u64 << 56) | (3 << 52) | 5).to_string()), Err(SteamIdError::AccountType));
+    // група (тип 7) у 17 цифр не влазить
+    assert_eq!(parse_steamid64("103582791429521412"), Err(SteamIdError::Format));
+  }
+
+  #[test]
+  fn rejects_non_digits_and_wrong_length() {
+    for s in ["", "7656119796028793", "765611979602879300", "7656119796028793a", "+6561197960287930", "76561197960287930 1", "７6561197960287930"] {
+      assert_eq!(parse_steamid64(s), Err(SteamIdError::Format), "{s:?}");
+    }
+  }
+}
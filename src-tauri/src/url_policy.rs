@@ -0,0 +1,190 @@
+use tauri::{Runtime, Url};
+
+use crate::launcher::{self, SteamAction};
+
+// ====================== Політика зовнішніх URL ======================
+//
+// open_url_external отримує рядок із вебв'ю й віддає його ОС. Пропускаємо без
+// питань лише https на домени Steam і steam:// з відомими діями (ті самі, що
+// будує launcher::steam_url). Інший http(s) — тільки після підтвердження
+// користувача; file://, javascript:, кастомні схеми тощо — відмова й запис у лог.
+
+const STEAM_DOMAINS: &[&str] = &["steampowered.com", "steamcommunity.com", "steamdeck.com", "s.team"];
+const STEAM_VERBS: &[SteamAction] = &[SteamAction::Install, SteamAction::Validate, SteamAction::Store, SteamAction::Details];
+const MAX_URL_LEN: usize = 2048;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+  Allow(String),
+  /// `host` — як його бачить ОС (IDN уже в punycode), саме його показуємо в діалозі
+  Confirm { url: String, host: String },
+  Block(&'static str),
+}
+
+/// Домен або піддомен зі списку: `store.steampowered.com` так, `steampowered.com.evil.io` і `evilsteampowered.com` ні.
+fn is_steam_host(host: &str) -> bool {
+  STEAM_DOMAINS.iter().any(|d| {
+    host == *d || host.strip_suffix(d).is_some_and(|rest| rest.ends_with('.'))
+  })
+}
+
+fn check_steam(url: &Url) -> Verdict {
+  if url.query().is_some() || url.fragment().is_some() {
+    return Verdict::Block("steam:// with query or fragment");
+  }
+  // steam://nav/games/details/620 → host "nav", path "/games/details/620"
+  let target = format!("steam://{}{}", url.host_str().unwrap_or_default(), url.path());
+  let Some(appid) = target.rsplit('/').next().and_then(|id| id.parse::<i64>().ok()) else {
+    return Verdict::Block("steam:// without appid");
+  };
+  let known = STEAM_VERBS
+    .iter()
+    .any(|a| launcher::steam_url(*a, appid).is_ok_and(|u| u == target));
+  if known { Verdict::Allow(target) } else { Verdict::Block("steam:// verb not allowed") }
+}
+
+pub fn check(raw: &str) -> Verdict {
+  if raw.len() > MAX_URL_LEN {
+    return Verdict::Block("too long");
+  }
+  // парсер URL мовчки викидає \t\n\r і пробіли по краях — не даємо цим ховати схему
+  if raw.chars().any(|c| c.is_control() || c.is_whitespace()) {
+    return Verdict::Block("control or whitespace characters");
+  }
+  let Ok(url) = Url::parse(raw) else {
+    return Verdict::Block("not an absolute URL");
+  };
+  if !url.username().is_empty() || url.password().is_some() {
+    return Verdict::Block("userinfo in URL");
+  }
+
+  match url.scheme() {
+    "steam" => check_steam(&url),
+    scheme @ ("https" | "http") => {
+      let Some(host) = url.host_str().map(str::to_string) else {
+        return Verdict::Block("no host");
+      };
+      if scheme == "https" && url.port().is_none() && is_steam_host(&host) {
+        Verdict::Allow(url.into())
+      } else {
+        Verdict::Confirm { url: url.into(), host }
+      }
+    }
+    _ => Verdict::Block("scheme not allowed"),
+  }
+}
+
+async fn confirm<R: Runtime>(app: &tauri::AppHandle<R>, host: &str) -> Result<bool, String> {
+  use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+  let dialog = app
+    .dialog()
+    .message(format!("Відкрити зовнішнє посилання?\n\n{host}\n\nЦе не сайт Steam."))
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancel);
+  // blocking_show не можна викликати з потоку подій — виносимо в окремий
+  tauri::async_runtime::spawn_blocking(move || dialog.blocking_show())
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Перевірити `raw` і, якщо дозволено, відкрити системним обробником.
+pub async fn open<R: Runtime>(app: &tauri::AppHandle<R>, raw: &str) -> Result<(), String> {
+  use tauri_plugin_opener::OpenerExt;
+  let url = match check(raw) {
+    Verdict::Allow(url) => url,
+    Verdict::Confirm { url, host } => {
+      if !confirm(app, &host).await? {
+        return Err("Opening the link was cancelled".into());
+      }
+      url
+    }
+    Verdict::Block(reason) => {
+      // сам рядок не логуємо: це може бути що завгодно зі сторінки
+      let scheme = raw.split(':').next().unwrap_or_default();
+      log::warn!("external URL blocked ({reason}), scheme {:?}", scheme.chars().take(16).collect::<String>());
+      return Err(format!("URL blocked: {reason}"));
+    }
+  };
+  app.opener().open_url(url, None::<String>).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn allowed(raw: &str) -> bool {
+    matches!(check(raw), Verdict::Allow(_))
+  }
+
+  fn blocked(raw: &str) -> bool {
+    matches!(check(raw), Verdict::Block(_))
+  }
+
+  #[test]
+  fn steam_sites_and_verbs_pass() {
+    assert!(allowed("https://store.steampowered.com/app/620/"));
+    assert!(allowed("https://steamcommunity.com/profiles/76561197960287930"));
+    assert!(allowed("https://STORE.SteamPowered.com/app/620"));
+    assert_eq!(check("steam://install/620"), Verdict::Allow("steam://install/620".into()));
+    assert!(allowed("steam://nav/games/details/620"));
+  }
+
+  #[test]
+  fn lookalike_hosts_need_confirmation() {
+    for raw in [
+      "https://steampowered.com.evil.io/",
+      "https://evilsteampowered.com/",
+      // кирилична «е»
+      "https://store.stеampowered.com/",
+      "https://xn--stampowered-pkj.com/",
+      "https://store.steampowered.com:8443/",
+      "http://store.steampowered.com/",
+      "https://example.com/",
+    ] {
+      assert!(matches!(check(raw), Verdict::Confirm { .. }), "{raw}");
+    }
+    let Verdict::Confirm { host, .. } = check("https://store.stеampowered.com/") else { panic!() };
+    assert!(host.contains("xn--"), "{host}");
+  }
+
+  #[test]
+  fn userinfo_tricks_are_blocked() {
+    assert!(blocked("https://store.steampowered.com@evil.io/"));
+    assert!(blocked("https://store.steampowered.com:x@evil.io/"));
+    assert!(blocked("https://user@store.steampowered.com/"));
+    // для спеціальних схем \ — це /, тож хост тут evil.io
+    assert!(!allowed("https://evil.io\\@store.steampowered.com/"));
+  }
+
+  #[test]
+  fn other_and_encoded_schemes_are_blocked() {
+    for raw in [
+      "file:///etc/passwd",
+      "FILE:///C:/Windows/System32/calc.exe",
+      "javascript:alert(1)",
+      "java\nscript:alert(1)",
+      " https://store.steampowered.com/",
+      "%66ile:///etc/passwd",
+      "file%3A%2F%2F%2Fetc%2Fpasswd",
+      "steam%3A//rungameid/620",
+      "data:text/html,<script>alert(1)</script>",
+      "ms-settings:windowsupdate",
+      "smb://evil.io/share",
+      "//evil.io/",
+      "",
+    ] {
+      assert!(blocked(raw), "{raw:?}");
+    }
+  }
+
+  #[test]
+  fn only_known_steam_verbs() {
+    assert!(blocked("steam://rungameid/620"));
+    assert!(blocked("steam://run/620//-console"));
+    assert!(blocked("steam://install/620?x=1"));
+    assert!(blocked("steam://install/abc"));
+    assert!(blocked("steam://install/0"));
+    assert!(blocked("steam://openurl/https://evil.io/620"));
+    assert!(blocked("steam://nav/console/620"));
+  }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::Runtime;
+
+use crate::commands::{self, PartialSettings};
+use crate::redact;
+use crate::secrets;
+use crate::steamid::{self, SteamIdError};
+
+// ====================== Перевірка налаштувань ======================
+//
+// Один виклик GetPlayerSummaries перевіряє і ключ (403 → відхилено), і всі id
+// одразу (існує? профіль публічний?). Потім по одному GetOwnedGames без appinfo —
+// чи видно список ігор (інакше скан мовчки поверне порожньо).
+
+const STEAM_API: &str = "https://api.steampowered.com";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const VISIBILITY_PUBLIC: u32 = 3;
+// публічний профіль для перевірки ключа, коли жодного валідного id ще не введено
+const PROBE_STEAMID: &str = "76561197960287930";
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagCode {
+  Ok,
+  Missing,
+  BadFormat,
+  BadUniverse,
+  BadAccountType,
+  Rejected,
+  Unreachable,
+  NotFound,
+  ProfilePrivate,
+  GamesPrivate,
+  Skipped,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FieldDiagnostic {
+  pub field: String,
+  /// Для ключа — маскований прев'ю, для id — сам id.
+  pub value: String,
+  pub ok: bool,
+  pub code: DiagCode,
+  pub message: String,
+  pub persona: Option<String>,
+}
+
+impl FieldDiagnostic {
+  fn new(field: &str, value: &str, code: DiagCode, message: impl Into<String>) -> Self {
+    Self {
+      field: field.to_string(),
+      value: value.to_string(),
+      ok: code == DiagCode::Ok,
+      code,
+      message: message.into(),
+      persona: None,
+    }
+  }
+
+  fn set(&mut self, code: DiagCode, message: impl Into<String>) {
+    self.ok = code == DiagCode::Ok;
+    self.code = code;
+    self.message = message.into();
+  }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SettingsValidation {
+  pub ok: bool,
+  pub api_key: FieldDiagnostic,
+  pub main_steam_id64: FieldDiagnostic,
+  pub family_ids: Vec<FieldDiagnostic>,
+}
+
+/// Ключ Steam Web API — 32 hex-символи.
+fn is_api_key_format(s: &str) -> bool {
+  let s = s.trim();
+  s.len() == 32 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn id_diag(field: &str, raw: &str) -> FieldDiagnostic {
+  if raw.trim().is_empty() {
+    return FieldDiagnostic::new(field, raw, DiagCode::Missing, "SteamID64 is empty");
+  }
+  match steamid::parse_steamid64(raw) {
+    Ok(_) => FieldDiagnostic::new(field, raw.trim(), DiagCode::Skipped, "Not checked online"),
+    Err(e) => {
+      let code = match e {
+        SteamIdError::Format => DiagCode::BadFormat,
+        SteamIdError::Universe => DiagCode::BadUniverse,
+        SteamIdError::AccountType => DiagCode::BadAccountType,
+      };
+      FieldDiagnostic::new(field, raw.trim(), code, e.message())
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct Player {
+  steamid: String,
+  personaname: Option<String>,
+  communityvisibilitystate: Option<u32>,
+}
+
+enum Probe<T> {
+  Ok(T),
+  Rejected(String),
+  Unreachable(String),
+}
+
+async fn player_summaries(client: &reqwest::Client, key: &str, ids: &[String]) -> Probe<HashMap<String, Player>> {
+  #[derive(Deserialize)]
+  struct Inner { players: Vec<Player> }
+  #[derive(Deserialize)]
+  struct Root { response: Inner }
+
+  let url = format!("{STEAM_API}/ISteamUser/GetPlayerSummaries/v2/?key={key}&steamids={}", ids.join(","));
+  let res = match client.get(&url).send().await {
+    Ok(r) => r,
+    Err(e) => return Probe::Unreachable(redact::http_err(e)),
+  };
+  let status = res.status();
+  if status.as_u16() == 401 || status.as_u16() == 403 {
+    return Probe::Rejected(format!("Steam rejected the key (HTTP {})", status.as_u16()));
+  }
+  if !status.is_success() {
+    return Probe::Unreachable(format!("Steam API HTTP {status}"));
+  }
+  match res.json::<Root>().await {
+    Ok(root) => Probe::Ok(root.response.players.into_iter().map(|p| (p.steamid.clone(), p)).collect()),
+    Err(e) => Probe::Unreachable(redact::http_err(e)),
+  }
+}
+
+/// `Some(true)` — список ігор видно, `Some(false)` — прихований, `None` — запит не вдався.
+async fn games_visible(client: &reqwest::Client, key: &str, sid: &str) -> Option<bool> {
+  #[derive(Deserialize)]
+  struct Inner { game_count: Option<u32> }
+  #[derive(Deserialize)]
+  struct Root { response: Inner }
+
+  let url = format!("{STEAM_API}/IPlayerService/GetOwnedGames/v1/?key={key}&steamid={sid}&include_appinfo=0");
+  let res = client.get(&url).send().await.ok()?;
+  if !res.status().is_success() { return None; }
+  let root: Root = res.json().await.ok()?;
+  Some(root.response.game_count.is_some())
+}
+
+/// Порожні поля беруться з уже збережених налаштувань, тож можна перевірити і поточний стан.
+#[tauri::command]
+pub async fn validate_settings<R: Runtime>(app: tauri::AppHandle<R>, s: PartialSettings) -> Result<SettingsValidation, String> {
+  let prefs = commands::load_prefs_internal(&app)?;
+  let key = match s.api_key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty()) {
+    Some(k) => { redact::remember(&k); Some(k) }
+    None => commands::steam_api_key(&app).ok(),
+  };
+  let main = s.main_steam_id64.unwrap_or(prefs.main_steam_id64);
+  let fams = s.family_ids.map(commands::dedup_ids).unwrap_or(prefs.family_ids);
+
+  let mut key_diag = match &key {
+    None => FieldDiagnostic::new("api_key", "", DiagCode::Missing, "Steam API key is not set"),
+    Some(k) if !is_api_key_format(k) => FieldDiagnostic::new(
+      "api_key",
+      &secrets::mask(k),
+      DiagCode::BadFormat,
+      "Steam API key must be 32 hexadecimal characters",
+    ),
+    Some(k) => FieldDiagnostic::new("api_key", &secrets::mask(k), DiagCode::Skipped, "Not checked online"),
+  };
+  let mut main_diag = id_diag("main_steam_id64", &main);
+  let mut fam_diags: Vec<FieldDiagnostic> = fams.iter().map(|f| id_diag("family_ids", f)).collect();
+
+  if let (Some(key), DiagCode::Skipped) = (&key, key_diag.code) {
+    let client = reqwest::Client::builder()
+      .timeout(PROBE_TIMEOUT)
+      .build()
+      .map_err(redact::http_err)?;
+
+    let ids: Vec<String> = std::iter::once(&main_diag)
+      .chain(fam_diags.iter())
+      .filter(|d| d.code == DiagCode::Skipped)
+      .map(|d| d.value.clone())
+      .collect();
+    // GetPlayerSummaries потребує хоча б один id; для перевірки ключа згодиться будь-який
+    let probe_ids = if ids.is_empty() { vec![PROBE_STEAMID.to_string()] } else { ids };
+
+    match player_summaries(&client, key, &probe_ids).await {
+      Probe::Rejected(msg) => key_diag.set(DiagCode::Rejected, msg),
+      Probe::Unreachable(msg) => key_diag.set(DiagCode::Unreachable, msg),
+      Probe::Ok(players) => {
+        key_diag.set(DiagCode::Ok, "Key accepted by Steam");
+        for d in std::iter::once(&mut main_diag).chain(fam_diags.iter_mut()) {
+          if d.code != DiagCode::Skipped { continue; }
+          let Some(p) = players.get(&d.value) else {
+            d.set(DiagCode::NotFound, "No Steam profile with this id");
+            continue;
+          };
+          d.persona = p.personaname.clone();
+          if p.communityvisibilitystate.unwrap_or(0) != VISIBILITY_PUBLIC {
+            d.set(DiagCode::ProfilePrivate, "Profile is not public");
+            continue;
+          }
+          match games_visible(&client, key, &d.value).await {
+            Some(true) => d.set(DiagCode::Ok, "Profile and game list are public"),
+            Some(false) => d.set(DiagCode::GamesPrivate, "Game details are private in Steam privacy settings"),
+            None => d.set(DiagCode::Unreachable, "Could not query owned games"),
+          }
+        }
+      }
+    }
+  }
+
+  let ok = key_diag.ok && main_diag.ok && fam_diags.iter().all(|d| d.ok);
+  Ok(SettingsValidation { ok, api_key: key_diag, main_steam_id64: main_diag, family_ids: fam_diags })
+}
@@ -0,0 +1,264 @@
+// ====================== Текстовий VDF (KeyValues) ======================
+//
+// loginusers.vdf, localconfig.vdf, config.vdf — вкладені `"ключ" { ... }` і
+// `"ключ" "значення"`. Регулярки з `(.*?)\}` обривають блок на першій
+// вкладеній дужці, тому тут мінімальний рекурсивний розбір.
+// Ключі у Steam пишуться як завгодно ("apps"/"Apps"), тож пошук без регістру.
+// Умови платформи (`"ключ" "значення" [$WIN32]`) не перевіряємо — пропускаємо,
+// тож за однаковим ключем get() віддає перший варіант.
+
+// справжні файли Steam — до десятка рівнів; глибше — зіпсований або навмисний файл
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug)]
+pub enum Vdf {
+  Str(String),
+  Obj(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+  pub fn get(&self, key: &str) -> Option<&Vdf> {
+    match self {
+      Vdf::Obj(items) => items.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v),
+      Vdf::Str(_) => None,
+    }
+  }
+
+  /// Шлях ключів: `root.path(&["Software", "Valve", "Steam", "apps"])`.
+  pub fn path(&self, keys: &[&str]) -> Option<&Vdf> {
+    keys.iter().try_fold(self, |node, k| node.get(k))
+  }
+
+  pub fn str(&self, key: &str) -> Option<&str> {
+    match self.get(key)? {
+      Vdf::Str(s) => Some(s.as_str()),
+      Vdf::Obj(_) => None,
+    }
+  }
+
+  pub fn u64(&self, key: &str) -> Option<u64> {
+    self.str(key)?.trim().parse().ok()
+  }
+
+  /// "1"/"0" як у loginusers.vdf.
+  pub fn flag(&self, key: &str) -> bool {
+    self.u64(key).unwrap_or(0) != 0
+  }
+
+  pub fn entries(&self) -> &[(String, Vdf)] {
+    match self {
+      Vdf::Obj(items) => items,
+      Vdf::Str(_) => &[],
+    }
+  }
+}
+
+struct Lexer<'a> {
+  s: &'a [u8],
+  i: usize,
+}
+
+enum Tok {
+  Str(String),
+  Open,
+  Close,
+}
+
+impl Lexer<'_> {
+  fn next(&mut self) -> Option<Tok> {
+    loop {
+      while self.i < self.s.len() && self.s[self.i].is_ascii_whitespace() {
+        self.i += 1;
+      }
+      // коментарі // до кінця рядка
+      if self.s.get(self.i..).is_some_and(|r| r.starts_with(b"//")) {
+        while self.i < self.s.len() && self.s[self.i] != b'\n' {
+          self.i += 1;
+        }
+        continue;
+      }
+      // умова платформи [$WIN32] / [!$OSX] після ключа чи значення
+      if self.s.get(self.i) == Some(&b'[') {
+        while self.i < self.s.len() && !b"]\n".contains(&self.s[self.i]) {
+          self.i += 1;
+        }
+        // обрізаний файл: без `]` лишаємось на кінці
+        if self.i < self.s.len() {
+          self.i += 1;
+        }
+        continue;
+      }
+      break;
+    }
+    let c = *self.s.get(self.i)?;
+    self.i += 1;
+    match c {
+      b'{' => Some(Tok::Open),
+      b'}' => Some(Tok::Close),
+      b'"' => {
+        let mut out = Vec::new();
+        while let Some(&b) = self.s.get(self.i) {
+          self.i += 1;
+          match b {
+            b'"' => break,
+            b'\\' => {
+              // `\` останнім байтом обрізаного файлу — рядок закінчився
+              let Some(&e) = self.s.get(self.i) else { break };
+              self.i += 1;
+              out.push(match e {
+                b'n' => b'\n',
+                b't' => b'\t',
+                other => other,
+              });
+            }
+            _ => out.push(b),
+          }
+        }
+        Some(Tok::Str(String::from_utf8_lossy(&out).into_owned()))
+      }
+      _ => {
+        // ключ без лапок (трапляється в старих файлах)
+        let start = self.i - 1;
+        while self.i < self.s.len() && !self.s[self.i].is_ascii_whitespace() && !b"{}\"".contains(&self.s[self.i]) {
+          self.i += 1;
+        }
+        Some(Tok::Str(String::from_utf8_lossy(&self.s[start..self.i]).into_owned()))
+      }
+    }
+  }
+}
+
+fn parse_obj(lx: &mut Lexer, depth: usize) -> Result<Vec<(String, Vdf)>, String> {
+  if depth > MAX_DEPTH {
+    return Err(format!("VDF nesting deeper than {MAX_DEPTH} levels"));
+  }
+  let mut items = Vec::new();
+  while let Some(tok) = lx.next() {
+    let key = match tok {
+      Tok::Str(k) => k,
+      Tok::Close if depth > 0 => break,
+      Tok::Close | Tok::Open => continue,
+    };
+    match lx.next() {
+      Some(Tok::Str(v)) => items.push((key, Vdf::Str(v))),
+      Some(Tok::Open) => items.push((key, Vdf::Obj(parse_obj(lx, depth + 1)?))),
+      Some(Tok::Close) if depth > 0 => break,
+      _ => break,
+    }
+  }
+  Ok(items)
+}
+
+/// Обрізаний файл не є помилкою: повертаємо все, що встигли прочитати.
+/// Помилка — лише вкладеність глибше за MAX_DEPTH.
+pub fn parse(text: &str) -> Result<Vdf, String> {
+  let mut lx = Lexer { s: text.as_bytes(), i: 0 };
+  Ok(Vdf::Obj(parse_obj(&mut lx, 0)?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn nested_blocks_quotes_and_escapes() {
+    let v = parse(r#"
+"UserLocalConfigStore"
+{
+  "friends" { "PersonaName" "Bob \"B\" \\ x" }
+  "Software" { "valve" { "Steam" {
+    "Apps"
+    {
+      "10" { "LastPlayed" "1700000000" "Playtime" "42" "cloud" { "quota" "1" } }
+      "20" { "MinutesPlayed" "7" }
+    }
+    "line\tkey" "a\nb"
+    unquoted value
+  } } }
+}"#).unwrap();
+    let store = v.get("userlocalconfigstore").unwrap();
+    let steam = store.path(&["Software", "Valve", "Steam"]).unwrap();
+    let apps = steam.get("apps").unwrap();
+    assert_eq!(apps.entries().len(), 2);
+    assert_eq!(apps.get("10").unwrap().u64("Playtime"), Some(42));
+    assert_eq!(store.path(&["friends"]).unwrap().str("PersonaName"), Some("Bob \"B\" \\ x"));
+    assert_eq!(steam.str("line\tkey"), Some("a\nb"));
+    assert_eq!(steam.str("unquoted"), Some("value"));
+  }
+
+  #[test]
+  fn comments_and_conditionals_are_skipped() {
+    let v = parse(r#"
+// коментар на початку
+"config"
+{
+  "Path" "C:\\Steam" [$WIN32]  // значення для Windows
+  "Path" "/home/u/.steam" [$LINUX]
+  "Lang" "english" [!$X360]
+  "Opt" [$OSX] { "a" "1" }
+  "After" "ok" // "Fake" "x"
+}"#).unwrap();
+    let c = v.get("config").unwrap();
+    assert_eq!(c.entries().len(), 5);
+    assert_eq!(c.str("Path"), Some("C:\\Steam"));
+    assert_eq!(c.str("Lang"), Some("english"));
+    assert_eq!(c.get("Opt").unwrap().u64("a"), Some(1));
+    assert_eq!(c.str("After"), Some("ok"));
+    assert!(c.get("Fake").is_none());
+    assert!(c.entries().iter().all(|(k, _)| !k.starts_with('[')));
+  }
+
+  #[test]
+  fn loginusers_sample() {
+    let v = parse(r#""users"
+{
+	"76561197960287930"
+	{
+		"AccountName"		"gabelogannewell"
+		"PersonaName"		"Rabscuttle"
+		"RememberPassword"		"1"
+		"WantsOfflineMode"		"0"
+		"SkipOfflineModeWarning"		"0"
+		"AllowAutoLogin"		"1"
+		"MostRecent"		"1"
+		"Timestamp"		"1712345678"
+	}
+	"76561198000000000"
+	{
+		"AccountName"		"kid"
+		"PersonaName"		"Малий"
+		"RememberPassword"		"0"
+		"MostRecent"		"0"
+		"Timestamp"		"1700000000"
+	}
+}
+"#).unwrap();
+    let users = v.get("users").unwrap();
+    assert_eq!(users.entries().len(), 2);
+    let gabe = users.get("76561197960287930").unwrap();
+    assert_eq!(gabe.str("AccountName"), Some("gabelogannewell"));
+    assert!(gabe.flag("MostRecent") && gabe.flag("AllowAutoLogin") && !gabe.flag("WantsOfflineMode"));
+    assert_eq!(gabe.u64("Timestamp"), Some(1_712_345_678));
+    let kid = users.get("76561198000000000").unwrap();
+    assert_eq!(kid.str("PersonaName"), Some("Малий"));
+    assert!(!kid.flag("AllowAutoLogin"));
+  }
+
+  #[test]
+  fn truncated_is_partial_and_deep_nesting_is_an_error() {
+    let v = parse("\"a\" { \"b\" { \"c\" \"1\"").unwrap();
+    assert_eq!(v.path(&["a", "b"]).unwrap().u64("c"), Some(1));
+    assert!(parse("\"k\" \"unterminated").is_ok());
+    // обрізано посеред умови платформи, escape-послідовності чи коментаря
+    for text in ["\"k\" [", "\"k\" \"v\" [$WIN", "\"k\" \"abc\\", "\"k\" \"\\", "\"k\" \"v\" /", "\"k\" \"v\" //x", "[", "\\"] {
+      assert!(parse(text).is_ok(), "{text:?}");
+    }
+    assert_eq!(parse("\"k\" \"abc\\").unwrap().str("k"), Some("abc"));
+    assert_eq!(parse("\"k\" \"v\" [$WIN").unwrap().str("k"), Some("v"));
+
+    let ok = "\"k\" {".repeat(MAX_DEPTH) + &"}".repeat(MAX_DEPTH);
+    assert!(parse(&ok).is_ok());
+    let deep = "\"k\" {".repeat(100_000);
+    assert!(parse(&deep).unwrap_err().contains("nesting"));
+  }
+}
@@ -1,5 +1,6 @@
 import { useEffect, useMemo, useState } from "react";
 import { listen } from "@tauri-apps/api/event";
+import { Channel } from "@tauri-apps/api/core";
 
 import AnimatedLogo from "./ui/brand/AnimatedLogo";
 import AssistantPanel from "./ui/scan/AssistantPanel";
@@ -7,7 +8,16 @@ import Onboarding from "./ui/onboarding/Onboarding";
 import "./ui/scan/ScanScreen.css";
 
 import { call } from "./lib/backend";
-import type { LibraryGame, AccountHint, PartialSettings } from "./lib/backend";
+import type {
+  LibraryGame,
+  AccountHint,
+  SettingsView,
+  ScanProgress,
+  LibraryRefreshed,
+  DeepLinkAction,
+  BrowserHelper,
+  NativeHostInstall,
+} from "./lib/backend";
 import SettingsModal from "./ui/onboarding/SettingsModal";
 
 const steam = {
@@ -29,7 +39,9 @@ export default function ScanScreen() {
   const [error, setError] = useState<string>();
   const [games, setGames] = useState<LibraryGame[]>([]);
 
+  // apiKey — лише щойно введений ключ; збережений бекенд не віддає, тільки прев'ю
   const [apiKey, setApiKey] = useState("");
+  const [apiKeyPreview, setApiKeyPreview] = useState<string>();
   const [steamId, setSteamId] = useState("");
   const [familyIds, setFamilyIds] = useState("");
   const [accounts, setAccounts] = useState<AccountHint[]>([]);
@@ -40,6 +52,7 @@ export default function ScanScreen() {
   const [settingsTab, setSettingsTab] = useState<"params" | "scan" | "library">("params");
   const [search, setSearch] = useState("");
   const [showOnboarding, setShowOnboarding] = useState(false);
+  const [scanPhase, setScanPhase] = useState<ScanProgress>();
 
   const [locale, setLocale] = useState<"uk" | "en">(() => {
     try {
@@ -75,8 +88,8 @@ export default function ScanScreen() {
   useEffect(() => {
     (async () => {
       try {
-        const s = (await call<PartialSettings>("get_settings")) || {};
-        if (s.api_key) setApiKey(s.api_key);
+        const s = await call<SettingsView>("get_settings");
+        if (s.api_key_set) setApiKeyPreview(s.api_key_preview ?? undefined);
         if (s.main_steam_id64) setSteamId(s.main_steam_id64);
         if (s.family_ids && s.family_ids.length) {
           setFamilyIds(s.family_ids.join(", "));
@@ -99,8 +112,44 @@ export default function ScanScreen() {
       const ids = (ev.payload || "").trim();
       if (ids) setFamilyIds(ids);
     });
+
+    // steamgamehelper://game/<appid> — показати гру в бібліотеці
+    const openGame = (a: DeepLinkAction) => {
+      if (a.action !== "game") return;
+      setShowSettings(true);
+      setSettingsTab("library");
+      setSearch(String(a.appid));
+    };
+    // спершу підписка, потім take: після take бекенд шле цей вид лише подією
+    const unlistenDeepLink = listen<DeepLinkAction>("deep-link-action", (ev) => openGame(ev.payload));
+    unlistenDeepLink
+      .then(() => call<DeepLinkAction[]>("take_pending_deep_links", { action: "game" }))
+      .then((list) => list.forEach(openGame))
+      .catch(() => {});
+    return () => {
+      unlistenPromise.then((u) => u());
+      unlistenDeepLink.then((u) => u());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlistenPromise = listen<LibraryRefreshed>("library-refreshed", async () => {
+      const cached = await call<LibraryGame[]>("load_library_cache").catch(() => []);
+      if (cached.length) setGames(cached);
+    });
+    // інший профіль — інші id і власний кеш бібліотеки
+    const unlistenProfile = listen<string>("profile-switched", async () => {
+      const s = await call<SettingsView>("get_settings").catch(() => undefined);
+      if (s) {
+        setSteamId(s.main_steam_id64 || "");
+        setFamilyIds((s.family_ids || []).join(", "));
+      }
+      const cached = await call<LibraryGame[]>("load_library_cache").catch(() => []);
+      setGames(cached);
+    });
     return () => {
       unlistenPromise.then((u) => u());
+      unlistenProfile.then((u) => u());
     };
   }, []);
 
@@ -113,7 +162,7 @@ export default function ScanScreen() {
       setSavedHint("saving");
       call("save_settings", {
         s: {
-          api_key: apiKey.trim(),
+          api_key: apiKey.trim() || undefined,
           main_steam_id64: steamId.trim(),
           family_ids: fam,
         },
@@ -139,7 +188,7 @@ export default function ScanScreen() {
     try {
       await call("save_settings", {
         s: {
-          api_key: apiKey.trim(),
+          api_key: apiKey.trim() || undefined,
           main_steam_id64: steamId.trim(),
           family_ids: fam,
         },
@@ -157,11 +206,19 @@ export default function ScanScreen() {
   }
 
   async function installBrowserHelper() {
-    const dir = await call<string>("ensure_browser_helper").catch(() => "");
+    const helper = await call<BrowserHelper>("ensure_browser_helper").catch(() => undefined);
+    // host для кнопки «Send to SteamGameHelper» (учасники сім'ї без буфера обміну)
+    const host = await call<NativeHostInstall>("install_native_host").catch(() => undefined);
     await call("open_extensions_manager").catch(() => {});
-    if (dir) {
+    if (helper) {
+      const hostNote = host?.installed.length
+        ? `\n\nNative host зареєстровано (${host.installed.length}).`
+        : "";
       alert(
-        `Файли помічника збережено:\n${dir}\n\nУ браузері увімкни «Режим розробника» та додай цю папку як розширення.`,
+        `Файли помічника v${helper.version} збережено.\n\n` +
+          `Chrome / Edge / Brave: увімкни «Режим розробника» та додай папку як розширення:\n${helper.chromium_dir}\n\n` +
+          `Firefox: about:debugging → «Цей Firefox» → «Завантажити тимчасовий додаток» і вибери manifest.json у:\n${helper.firefox_dir}` +
+          hostNote,
       );
     }
   }
@@ -179,14 +236,9 @@ export default function ScanScreen() {
   }
 
   async function scanUnified() {
-    if (!apiKey.trim()) {
-      return setError(
-        locale === "en"
-          ? "Specify Steam API key in Settings."
-          : "Вкажіть Steam API key у Налаштуваннях.",
-      );
-    }
-    if (!steamId.trim()) {
+    // без ключа бекенд зібере офлайн-бібліотеку з локальних файлів Steam
+    const hasKey = !!(apiKey.trim() || apiKeyPreview);
+    if (hasKey && !steamId.trim()) {
       return setError(
         locale === "en"
           ? "Select or enter SteamID64 in Settings."
@@ -197,18 +249,21 @@ export default function ScanScreen() {
       .split(/[, \n\r\t]+/g)
       .map((s) => s.trim())
       .filter(Boolean);
+    const onProgress = new Channel<ScanProgress>();
+    onProgress.onmessage = (p) => setScanPhase(p);
     const data = await run(async () => {
       return await call<LibraryGame[]>("scan_library_unified", {
-        api_key: apiKey,
+        api_key: apiKey.trim() || null,
         main_steam_id64: steamId,
         family_ids: fam.length ? fam : null,
+        onProgress,
       });
     });
+    setScanPhase(undefined);
     if (data) {
       setGames(data);
       call("save_settings", {
         s: {
-          api_key: apiKey.trim(),
           main_steam_id64: steamId.trim(),
           family_ids: fam,
         },
@@ -218,6 +273,10 @@ export default function ScanScreen() {
     }
   }
 
+  async function cancelScan() {
+    await call<boolean>("cancel_scan").catch(() => false);
+  }
+
   const filtered = useMemo(() => {
     const q = search.trim().toLowerCase();
     return q
@@ -303,6 +362,7 @@ export default function ScanScreen() {
         locale={locale}
         setLocale={setLocale}
         apiKey={apiKey}
+        apiKeyPreview={apiKeyPreview}
         setApiKey={setApiKey}
         steamId={steamId}
         setSteamId={setSteamId}
@@ -316,6 +376,8 @@ export default function ScanScreen() {
         busy={busy}
         error={error}
         onScan={scanUnified}
+        onCancelScan={cancelScan}
+        scanPhase={scanPhase}
         onClearCache={clearCache}
         games={games}
         installedCount={totalInstalled}
@@ -1,9 +1,12 @@
 import { useEffect, useRef, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { Channel } from "@tauri-apps/api/core";
 import { colors } from "../ui/palette";
 import { call } from "../lib/backend";
-import type { LibraryGame } from "../lib/backend";
+import type { DeepLinkAction, LibraryGame, LlmStreamEvent, Picks, Recommendation } from "../lib/backend";
 
-type Msg = { role: "assistant" | "user"; text: string; ts: number };
+// picks — щоб під відповіддю можна було відхилити окрему гру
+type Msg = { role: "assistant" | "user"; text: string; ts: number; picks?: Picks };
 type Locale = "uk" | "en";
 type Mode = "chat" | "raw";
 
@@ -30,6 +33,13 @@ function tFactory(locale: Locale) {
     tabRaw: "Raw",
     tip: "Порада: відкрий «Settings → Scan», щоб оновити бібліотеку для точніших рекомендацій.",
     chatOverlay: "AI chat is under development. Coming soon ✨",
+    heuristicNote: "Модель не дала відповіді — підбірка за евристикою:",
+    rejectTitle: "Не пропонувати цю гру в цій розмові",
+    rejectMsg: "Тільки не «{name}», щось інше",
+    thinkingCtx: "Думаю… у контексті {kept} з {total} кандидатів",
+    writing: "Пишу відповідь… ігор: {n}",
+    stop: "Зупинити",
+    stopped: "Зупинено.",
   };
   const en: Record<string, string> = {
     intro:
@@ -46,10 +56,24 @@ function tFactory(locale: Locale) {
     tabRaw: "Raw",
     tip: "Tip: open Settings → Scan to refresh the library for better recommendations.",
     chatOverlay: "AI chat is under development. Coming soon ✨",
+    heuristicNote: "The model gave no usable answer — heuristic picks:",
+    rejectTitle: "Don’t suggest this game again in this chat",
+    rejectMsg: "Not “{name}”, something else",
+    thinkingCtx: "Thinking… {kept} of {total} candidates in context",
+    writing: "Writing the answer… games: {n}",
+    stop: "Stop",
+    stopped: "Stopped.",
   };
   return (k: string) => (locale === "en" ? en : uk)[k] ?? k;
 }
 
+function formatPicks(p: Picks, note: string) {
+  const lines = p.picks.map(
+    (r) => `• ${r.name}${r.installed ? " ✓" : ""} — ${r.reason}`,
+  );
+  return (p.source === "heuristic" ? [note, ...lines] : lines).join("\n");
+}
+
 function RawHeuristicsTab() {
   const [list, setList] = useState<LibraryGame[]>([]);
   const [picks, setPicks] = useState<
@@ -181,6 +205,10 @@ export default function AskAssistant() {
 
   const listRef = useRef<HTMLDivElement | null>(null);
   const sendingRef = useRef(false);
+  // id поточної генерації для cancel_llm
+  const genRef = useRef<number | null>(null);
+  // розмова на бекенді: історія та вже запропоновані ігри
+  const chatRef = useRef<string>(crypto.randomUUID());
 
   useEffect(() => {
     const div = listRef.current;
@@ -205,7 +233,8 @@ export default function AskAssistant() {
     });
   }, [locale]);
 
-  async function send(msg: string) {
+  // reject — appid, від яких користувач відмовився; бекенд більше їх не пропонує
+  async function send(msg: string, reject: number[] = []) {
     if (mode !== "chat") return;
 
     const trimmed = msg.trim();
@@ -216,40 +245,84 @@ export default function AskAssistant() {
     setText("");
 
     const thinking = t("thinking");
-    setMessages((m) => [...m, { role: "assistant", text: thinking, ts: Date.now() }]);
+    const ts = Date.now();
+    setMessages((m) => [...m, { role: "assistant", text: thinking, ts }]);
     setBusy(true);
+    // відповідь пишемо в ту саму бульбашку, що й «думаю…»
+    const put = (txt: string, picks?: Picks) =>
+      setMessages((m) =>
+        m.map((x) => (x.role === "assistant" && x.ts === ts ? { ...x, text: txt, picks } : x)),
+      );
 
     try {
-      const answer = await call<string>("llm_chat", { user: { text: trimmed } });
-      setMessages((m) => {
-        const next = m.slice();
-        for (let i = next.length - 1; i >= 0; i--) {
-          if (next[i].role === "assistant" && next[i].text === thinking) {
-            next[i] = { role: "assistant", text: answer || "", ts: Date.now() };
-            return next;
-          }
+      // чанки — сирий JSON під граматикою: показуємо лише прогрес, а перевірені picks — у кінці
+      let acc = "";
+      const onEvent = new Channel<LlmStreamEvent>();
+      onEvent.onmessage = (ev) => {
+        if (ev.type === "started") {
+          genRef.current = ev.id;
+        } else if (ev.type === "prompt") {
+          put(
+            t("thinkingCtx")
+              .replace("{kept}", String(ev.debug.candidates_kept))
+              .replace("{total}", String(ev.debug.candidates_total)),
+          );
+        } else if (ev.type === "chunk") {
+          acc += ev.text;
+          put(t("writing").replace("{n}", String(acc.split('"appid"').length - 1)));
+        } else if (ev.type === "done" && ev.stop === "cancelled") {
+          put(t("stopped"));
+        } else if (ev.type === "done" || ev.type === "fallback") {
+          put(formatPicks(ev.picks, t("heuristicNote")), ev.picks);
         }
-        return [...next, { role: "assistant", text: answer || "", ts: Date.now() }];
+      };
+      await call("llm_chat_stream", {
+        user: { text: trimmed, chat_id: chatRef.current, reject },
+        onEvent,
       });
     } catch (e: any) {
-      const msgErr = `[${t("errPrefix")}]: ${t("llmUnavailable")} ${e?.toString?.() ?? ""}`.trim();
-      setMessages((m) => {
-        const next = m.slice();
-        for (let i = next.length - 1; i >= 0; i--) {
-          if (next[i].role === "assistant" && next[i].text === t("thinking")) {
-            next[i] = { role: "assistant", text: msgErr, ts: Date.now() };
-            return next;
-          }
-        }
-        return [...next, { role: "assistant", text: msgErr, ts: Date.now() }];
-      });
+      put(`[${t("errPrefix")}]: ${t("llmUnavailable")} ${e?.toString?.() ?? ""}`.trim());
     } finally {
+      genRef.current = null;
       setBusy(false);
       sendingRef.current = false;
     }
   }
 
+  // steamgamehelper://recommend?mood=... — бекенд доставляє лише після «OK» у діалозі підтвердження
+  const sendRef = useRef(send);
+  sendRef.current = send;
+  useEffect(() => {
+    const onAction = (a: DeepLinkAction) => {
+      if (a.action === "recommend") sendRef.current(a.mood);
+    };
+    // спершу підписка, потім take: після take бекенд шле цей вид лише подією
+    const unlisten = listen<DeepLinkAction>("deep-link-action", (ev) => onAction(ev.payload));
+    unlisten
+      .then(() => call<DeepLinkAction[]>("take_pending_deep_links", { action: "recommend" }))
+      .then((list) => list.forEach(onAction))
+      .catch(() => {});
+    return () => {
+      unlisten.then((u) => u());
+    };
+  }, []);
+
+  function stop() {
+    if (genRef.current != null) {
+      call<boolean>("cancel_llm", { id: genRef.current }).catch(() => false);
+    }
+  }
+
+  function rejectPick(r: Recommendation) {
+    send(t("rejectMsg").replace("{name}", r.name), [r.appid]);
+  }
+
   function reset() {
+    if (genRef.current != null) {
+      call<boolean>("cancel_llm", { id: genRef.current }).catch(() => false);
+    }
+    call<boolean>("delete_conversation", { chatId: chatRef.current }).catch(() => false);
+    chatRef.current = crypto.randomUUID();
     setMessages([{ role: "assistant", text: t("ready"), ts: Date.now() }]);
     setText("");
     setBusy(false);
@@ -400,6 +473,30 @@ export default function AskAssistant() {
                     }}
                   >
                     {m.text}
+                    {m.picks && m.picks.picks.length > 0 && (
+                      <div style={{ display: "flex", gap: 6, flexWrap: "wrap", marginTop: 8 }}>
+                        {m.picks.picks.map((r) => (
+                          <button
+                            key={r.appid}
+                            type="button"
+                            onClick={() => rejectPick(r)}
+                            disabled={busy}
+                            title={t("rejectTitle")}
+                            style={{
+                              padding: "3px 8px",
+                              fontSize: 12,
+                              borderRadius: 999,
+                              border: `1px solid ${colors.border}`,
+                              background: colors.panelSoft,
+                              color: colors.textMuted,
+                              cursor: busy ? "default" : "pointer",
+                            }}
+                          >
+                            ✕ {r.name}
+                          </button>
+                        ))}
+                      </div>
+                    )}
                   </div>
                 </div>
               ))}
@@ -453,6 +550,21 @@ export default function AskAssistant() {
               >
                 {t("send")}
               </button>
+              <button
+                type="button"
+                onClick={stop}
+                style={{
+                  padding: "10px 12px",
+                  borderRadius: 10,
+                  background: colors.panel,
+                  color: colors.text,
+                  border: `1px solid ${colors.border}`,
+                  opacity: 0.75,
+                }}
+                disabled={!busy}
+              >
+                {t("stop")}
+              </button>
               <button
                 type="button"
                 style={{
@@ -1,7 +1,7 @@
 import { useEffect, useMemo, useState } from "react";
 import { colors } from "../ui/palette";
 import { call } from "../lib/backend";
-import type { LibraryGame, PartialSettings } from "../lib/backend";
+import type { LibraryGame, PlayerSummary } from "../lib/backend";
 
 function guessGenres(name: string): string[] {
   const n = name.toLowerCase();
@@ -65,21 +65,9 @@ export default function RawHeuristicsTab() {
   useEffect(() => {
     (async () => {
       try {
-        const s = await call<PartialSettings>("get_settings");
-        const apiKey = s.api_key?.trim();
-        const sid = s.main_steam_id64?.trim();
-        if (!apiKey || !sid) return;
-
-        const url = `https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v2/?key=${encodeURIComponent(
-          apiKey,
-        )}&steamids=${encodeURIComponent(sid)}`;
-
-        const res = await fetch(url);
-        if (!res.ok) return;
-        const json = await res.json();
-        const player = json?.response?.players?.[0];
-        if (player?.avatarfull) setAvatar(player.avatarfull as string);
-        if (player?.personaname) setPersona(player.personaname as string);
+        const player = await call<PlayerSummary | null>("get_player_summary");
+        if (player?.avatar) setAvatar(player.avatar);
+        if (player?.persona) setPersona(player.persona);
       } catch {
       }
     })();
@@ -6,11 +6,37 @@ export type LibraryGame = {
   installed: boolean;
   shared_from?: string | null;
   playtime_minutes?: number | null;
+  /** verified — Steam API; решта — офлайн-скан з локальних файлів */
+  confidence?: Confidence;
 };
 
+export type Confidence = "low" | "medium" | "high" | "verified";
+
 export type AccountHint = {
   steamid64: string;
   persona: string;
+  account_name: string;
+  most_recent: boolean;
+  timestamp: number;
+  remember_password: boolean;
+  wants_offline_mode: boolean;
+  allow_auto_login: boolean;
+  has_userdata: boolean;
+  local_apps: number;
+};
+
+export type AppPlaytime = {
+  appid: number;
+  playtime_minutes?: number | null;
+  last_played_unix?: number | null;
+};
+
+export type AccountPlaytime = {
+  steamid64: string;
+  account_id: number;
+  persona: string;
+  total_minutes: number;
+  apps: AppPlaytime[];
 };
 
 export type PartialSettings = {
@@ -19,6 +45,236 @@ export type PartialSettings = {
   family_ids?: string[];
   ai_api_key?: string;
   ai_base_url?: string;
+  ai_model?: string;
+  // контекст моделі на сервері; 0 — типовий
+  ai_context_tokens?: number;
+  llm_backend?: LlmBackendKind;
+  refresh_interval_hours?: number | null;
+  clear_api_key?: boolean;
+  clear_ai_api_key?: boolean;
+  llm_template?: ChatFormat;
+  clear_llm_template?: boolean;
+};
+
+// get_settings / load_cached_inputs: ключі ніколи не повертаються, лише прев'ю
+export type SettingsView = {
+  api_key_set: boolean;
+  api_key_preview?: string | null;
+  main_steam_id64?: string | null;
+  family_ids?: string[] | null;
+  ai_api_key_set: boolean;
+  ai_api_key_preview?: string | null;
+  ai_base_url?: string | null;
+  ai_model?: string | null;
+  // null — типовий (4096)
+  ai_context_tokens?: number | null;
+  llm_backend: LlmBackendKind;
+  refresh_interval_hours?: number | null;
+  // null — шаблон визначається за метаданими моделі
+  llm_template?: ChatFormat | null;
+};
+
+export type DiagCode =
+  | "ok"
+  | "missing"
+  | "bad_format"
+  | "bad_universe"
+  | "bad_account_type"
+  | "rejected"
+  | "unreachable"
+  | "not_found"
+  | "profile_private"
+  | "games_private"
+  | "skipped";
+
+export type FieldDiagnostic = {
+  field: "api_key" | "main_steam_id64" | "family_ids";
+  value: string;
+  ok: boolean;
+  code: DiagCode;
+  message: string;
+  persona?: string | null;
+};
+
+// validate_settings: порожні поля перевіряються за збереженими налаштуваннями
+export type SettingsValidation = {
+  ok: boolean;
+  api_key: FieldDiagnostic;
+  main_steam_id64: FieldDiagnostic;
+  family_ids: FieldDiagnostic[];
+};
+
+export type PlayerSummary = {
+  persona: string;
+  avatar?: string | null;
+};
+
+export type ScanProgress =
+  | { phase: "started"; accounts: number }
+  | { phase: "offline"; reason: string }
+  | { phase: "api_account"; steamid64: string; index: number; total: number; games: number }
+  | { phase: "library_walk"; path: string; index: number; total: number }
+  | { phase: "manifests"; count: number }
+  | { phase: "user_stats"; files: number; apps: number }
+  | { phase: "finished"; games: number }
+  | { phase: "cancelled" };
+
+export type LibraryRefreshed = {
+  games: number;
+  at_unix: number;
+};
+
+export type RefreshState = {
+  last_attempt_unix: number;
+  last_success_unix: number;
+  last_outcome: "" | "ok" | "error" | "skipped";
+  last_error?: string | null;
+  consecutive_failures: number;
+  next_due_unix: number;
+};
+
+export type Profile = {
+  id: string;
+  name: string;
+  main_steam_id64: string;
+  family_ids: string[];
+  created_at: number;
+};
+
+export type ProfilesView = {
+  active: string;
+  profiles: Profile[];
+  suggestions: AccountHint[];
+};
+
+export type DeepLinkAction =
+  | { action: "import_family"; ids: string[] }
+  | { action: "recommend"; mood: string }
+  | { action: "game"; appid: number };
+
+export type FamilyMember = {
+  steamid64: string;
+  persona: string;
+  role: "organizer" | "adult" | "child" | "unknown";
+};
+
+export type SteamAction = "install" | "validate" | "store" | "details";
+
+export type StopReason = "eos" | "max_tokens" | "wall_time" | "cancelled";
+
+export type ChatFormat = "llama3" | "chatml" | "phi3" | "gemma" | "mistral";
+
+// openai_compat — Ollama / LM Studio / llama-server / vLLM за ai_base_url
+export type LlmBackendKind = "local" | "openai_compat";
+
+export type QueuePolicy = "reject" | "queue" | "replace";
+
+// llm_chat / llm_chat_stream: appid лише з бібліотеки, name/installed — з неї ж
+export type Recommendation = {
+  appid: number;
+  name: string;
+  reason: string;
+  installed: boolean;
+};
+
+export type Picks = {
+  source: "llm" | "heuristic";
+  picks: Recommendation[];
+};
+
+// get_conversation: історія розмови з асистентом
+export type ChatMessage = { role: "user" | "assistant"; text: string };
+
+export type Conversation = {
+  id: string;
+  messages: ChatMessage[];
+  suggested: number[];
+  rejected: number[];
+  updated_at: number;
+};
+
+// list_models / select_model / reload_model
+export type MemoryFit = {
+  required_bytes: number;
+  available_bytes: number;
+  fits: boolean;
+};
+
+export type ModelInfo = {
+  path: string;
+  file_name: string;
+  size_bytes: number;
+  architecture: string;
+  name: string;
+  parameters: number;
+  quantization?: string | null;
+  context_length?: number | null;
+  memory?: MemoryFit | null;
+  selected: boolean;
+  loaded: boolean;
+  error?: string | null;
+};
+
+export type GenStats = {
+  prompt_tokens: number;
+  cached_tokens: number;
+  prompt_ms: number;
+  gen_tokens: number;
+  gen_ms: number;
+};
+
+// benchmark_model: prompt_tokens_per_sec — лише по токенах поза кешем
+export type Benchmark = {
+  model: string;
+  n_threads: number;
+  n_ctx: number;
+  stats: GenStats;
+  prompt_tokens_per_sec: number;
+  gen_tokens_per_sec: number;
+};
+
+// як зібрано промпт під контекст моделі; dropped — що не влізло
+export type PromptDebug = {
+  tokenizer: "model" | "approx";
+  n_ctx: number;
+  max_tokens: number;
+  prompt_tokens: number;
+  candidates_total: number;
+  candidates_kept: number;
+  history_total: number;
+  history_kept: number;
+  dropped: string[];
+};
+
+export type LlmStreamEvent =
+  | { type: "started"; id: number }
+  | { type: "prompt"; debug: PromptDebug }
+  | { type: "chunk"; text: string }
+  | { type: "done"; text: string; stop: StopReason; picks: Picks }
+  | { type: "fallback"; error: string; picks: Picks };
+
+export type LaunchStarted = {
+  appid: number;
+  tracking: boolean;
+};
+
+export type PlaySession = {
+  appid: number;
+  name: string;
+  started_at: number;
+  ended_at: number;
+  duration_secs: number;
+};
+
+export type BrowserHelper = {
+  version: string;
+  chromium_dir: string;
+  firefox_dir: string;
+};
+
+export type NativeHostInstall = {
+  chromium_extension_id: string;
+  installed: string[];
 };
 
 export const call = <T,>(
@@ -1,5 +1,6 @@
 import { useEffect, useMemo, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import type { AccountHint } from "../../lib/backend";
 import "./onboarding.css";
 
 /** Палітра, як у Steam */
@@ -17,8 +18,6 @@ const colors = {
   ok: "#59bf40",
 };
 
-type AccountHint = { steamid64: string; persona: string };
-
 type Props = {
   // Steam
   initialApiKey?: string;
@@ -77,7 +76,7 @@ export default function Onboarding({
         step2Title: "Step 2 — Steam Web API Key",
         step2P: "Paste your Steam API key. If you don’t know where to get it — tap the button.",
         whereKey: "Where to get a key?",
-        storedLocally: "The key is stored locally in prefs.json.",
+        storedLocally: "The key is stored locally in an encrypted vault.",
         step3Title: "Step 3 — Main SteamID64",
         step3P: "Pick a detected account or enter your SteamID64 manually.",
         found: "Found",
@@ -120,7 +119,7 @@ export default function Onboarding({
       step2Title: "Крок 2 — Steam Web API Key",
       step2P: "Вставте ваш Steam API Key. Якщо не знаєте де взяти — натисніть кнопку.",
       whereKey: "Де взяти ключ?",
-      storedLocally: "Ключ зберігається локально у prefs.json.",
+      storedLocally: "Ключ зберігається локально у зашифрованому сховищі.",
       step3Title: "Крок 3 — Основний SteamID64",
       step3P: "Оберіть знайдений акаунт або введіть SteamID64 вручну.",
       found: "Знайдено",
@@ -150,9 +149,12 @@ export default function Onboarding({
   }, [lang]);
 
   useEffect(() => {
-    // підказки акаунтів
+    // підказки акаунтів; бекенд віддає останній використаний першим
     invoke<AccountHint[]>("detect_accounts")
-      .then((h) => setAccounts(h || []))
+      .then((h) => {
+        setAccounts(h || []);
+        if (h && h.length) setSteamId((cur) => cur.trim() || h[0].steamid64);
+      })
       .catch(() => {});
   }, []);
 
@@ -262,7 +264,9 @@ export default function Onboarding({
           >
             {accounts.map((a) => (
               <option key={a.steamid64} value={a.steamid64}>
-                {a.persona || a.steamid64} ({a.steamid64})
+                {a.persona || a.account_name || a.steamid64} ({a.steamid64})
+                {a.most_recent ? " ★" : ""}
+                {a.local_apps ? ` · ${a.local_apps}` : ""}
               </option>
             ))}
           </select>
@@ -16,6 +16,7 @@ type Props = {
   locale: "uk" | "en";
   setLocale: (locale: "uk" | "en") => void;
   apiKey: string;
+  apiKeyPreview?: string;
   setApiKey: (v: string) => void;
   steamId: string;
   setSteamId: (v: string) => void;
@@ -34,6 +35,7 @@ export default function SettingsGeneralTab(props: Props) {
     locale,
     setLocale,
     apiKey,
+    apiKeyPreview,
     setApiKey,
     steamId,
     setSteamId,
@@ -118,7 +120,7 @@ export default function SettingsGeneralTab(props: Props) {
         <input
           value={apiKey}
           onChange={(e) => setApiKey(e.target.value)}
-          placeholder="XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"
+          placeholder={apiKeyPreview || "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX"}
           style={{
             padding: "10px 12px",
             borderRadius: 10,
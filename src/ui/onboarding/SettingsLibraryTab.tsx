@@ -1,4 +1,5 @@
-import type { LibraryGame } from "../../lib/backend";
+import { useEffect, useState } from "react";
+import { call, type AccountPlaytime, type LibraryGame } from "../../lib/backend";
 import LibraryView from "../scan/LibraryView";
 
 type Props = {
@@ -11,18 +12,62 @@ type Props = {
   setSearch: (v: string) => void;
 };
 
+const hours = (m: number) => `${(m / 60).toFixed(1)} h`;
+
 export default function SettingsLibraryTab(props: Props) {
   const { locale, sortedGames, total, sortBy, setSortBy, search, setSearch } = props;
+  // розбивка локального часу по акаунтах цього ПК (спільний комп'ютер)
+  const [showBreakdown, setShowBreakdown] = useState(false);
+  const [breakdown, setBreakdown] = useState<AccountPlaytime[]>([]);
+
+  useEffect(() => {
+    if (!showBreakdown) return;
+    call<AccountPlaytime[]>("local_playtime_breakdown", { appids: null })
+      .then(setBreakdown)
+      .catch(() => setBreakdown([]));
+  }, [showBreakdown]);
+
+  const names = new Map(sortedGames.map((g) => [g.appid, g.name]));
 
   return (
-    <LibraryView
-      games={sortedGames}
-      total={total}
-      sortBy={sortBy}
-      setSortBy={setSortBy}
-      search={search}
-      setSearch={setSearch}
-      locale={locale}
-    />
+    <>
+      <LibraryView
+        games={sortedGames}
+        total={total}
+        sortBy={sortBy}
+        setSortBy={setSortBy}
+        search={search}
+        setSearch={setSearch}
+        locale={locale}
+      />
+
+      <label style={{ display: "flex", gap: 8, alignItems: "center", marginTop: 12 }}>
+        <input
+          type="checkbox"
+          checked={showBreakdown}
+          onChange={(e) => setShowBreakdown(e.target.checked)}
+        />
+        {locale === "en"
+          ? "Show local playtime per account on this PC"
+          : "Показати локальний час по акаунтах цього ПК"}
+      </label>
+
+      {showBreakdown &&
+        breakdown.map((a) => (
+          <div key={a.steamid64} style={{ marginTop: 10 }}>
+            <b>{a.persona || a.steamid64}</b> — {hours(a.total_minutes)}
+            <ul style={{ margin: "4px 0 0 16px" }}>
+              {a.apps
+                .filter((x) => x.playtime_minutes)
+                .slice(0, 10)
+                .map((x) => (
+                  <li key={x.appid}>
+                    {names.get(x.appid) ?? x.appid}: {hours(x.playtime_minutes ?? 0)}
+                  </li>
+                ))}
+            </ul>
+          </div>
+        ))}
+    </>
   );
 }
@@ -1,4 +1,4 @@
-import type { AccountHint, LibraryGame } from "../../lib/backend";
+import type { AccountHint, LibraryGame, ScanProgress } from "../../lib/backend";
 import SettingsGeneralTab from "./SettingsGeneralTab";
 import SettingsScanTab from "./SettingsScanTab";
 import SettingsLibraryTab from "./SettingsLibraryTab";
@@ -13,6 +13,7 @@ type Props = {
   setLocale: (locale: "uk" | "en") => void;
 
   apiKey: string;
+  apiKeyPreview?: string;
   setApiKey: (v: string) => void;
   steamId: string;
   setSteamId: (v: string) => void;
@@ -30,6 +31,8 @@ type Props = {
   busy: boolean;
   error?: string;
   onScan: () => void;
+  onCancelScan: () => void;
+  scanPhase?: ScanProgress;
   onClearCache: () => void;
 
   games: LibraryGame[];
@@ -65,6 +68,7 @@ export default function SettingsModal(props: Props) {
     locale,
     setLocale,
     apiKey,
+    apiKeyPreview,
     setApiKey,
     steamId,
     setSteamId,
@@ -78,6 +82,8 @@ export default function SettingsModal(props: Props) {
     busy,
     error,
     onScan,
+    onCancelScan,
+    scanPhase,
     onClearCache,
     games,
     installedCount,
@@ -205,6 +211,7 @@ export default function SettingsModal(props: Props) {
               locale={locale}
               setLocale={setLocale}
               apiKey={apiKey}
+              apiKeyPreview={apiKeyPreview}
               setApiKey={setApiKey}
               steamId={steamId}
               setSteamId={setSteamId}
@@ -225,6 +232,8 @@ export default function SettingsModal(props: Props) {
               busy={busy}
               error={error}
               onScan={onScan}
+              onCancelScan={onCancelScan}
+              scanPhase={scanPhase}
               onClearCache={onClearCache}
               games={games}
               installedCount={installedCount}
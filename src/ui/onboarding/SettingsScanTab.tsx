@@ -1,4 +1,4 @@
-import type { LibraryGame } from "../../lib/backend";
+import type { LibraryGame, ScanProgress } from "../../lib/backend";
 
 const steam = {
   panel: "#0f1b2b",
@@ -17,13 +17,46 @@ type Props = {
   busy: boolean;
   error?: string;
   onScan: () => void;
+  onCancelScan: () => void;
+  scanPhase?: ScanProgress;
   onClearCache: () => void;
   games: LibraryGame[];
   installedCount: number;
 };
 
+function phaseLabel(p: ScanProgress, locale: "uk" | "en"): string {
+  const en = locale === "en";
+  switch (p.phase) {
+    case "started":
+      return en ? `Accounts to query: ${p.accounts}` : `Акаунтів до запиту: ${p.accounts}`;
+    case "offline":
+      return en
+        ? `Offline library from local files (${p.reason})`
+        : `Офлайн-бібліотека з локальних файлів (${p.reason})`;
+    case "api_account":
+      return en
+        ? `Steam API ${p.index + 1}/${p.total}: ${p.games} games`
+        : `Steam API ${p.index + 1}/${p.total}: ігор ${p.games}`;
+    case "library_walk":
+      return en
+        ? `Library folder ${p.index + 1}/${p.total}: ${p.path}`
+        : `Тека бібліотеки ${p.index + 1}/${p.total}: ${p.path}`;
+    case "manifests":
+      return en ? `Installed manifests: ${p.count}` : `Маніфестів встановлених: ${p.count}`;
+    case "user_stats":
+      return en
+        ? `Local stats: ${p.apps} apps from ${p.files} files`
+        : `Локальна статистика: ${p.apps} ігор з ${p.files} файлів`;
+    case "finished":
+      return en ? `Done: ${p.games} games` : `Готово: ${p.games} ігор`;
+    case "cancelled":
+      return en ? "Scan cancelled" : "Сканування скасовано";
+  }
+}
+
 export default function SettingsScanTab(props: Props) {
-  const { locale, busy, error, onScan, onClearCache, games, installedCount } = props;
+  const { locale, busy, error, onScan, onCancelScan, scanPhase, onClearCache, games, installedCount } =
+    props;
 
   return (
     <div
@@ -67,6 +100,21 @@ export default function SettingsScanTab(props: Props) {
             ? "Scan library"
             : "Сканувати бібліотеку"}
         </button>
+        {busy && (
+          <button
+            onClick={onCancelScan}
+            style={{
+              padding: "10px 14px",
+              borderRadius: 10,
+              background: steam.panelSoft,
+              color: steam.text,
+              border: `1px solid ${steam.border}`,
+              cursor: "pointer",
+            }}
+          >
+            {locale === "en" ? "Cancel" : "Скасувати"}
+          </button>
+        )}
         <button
           onClick={onClearCache}
           disabled={busy}
@@ -112,6 +160,11 @@ export default function SettingsScanTab(props: Props) {
           </div>
         </div>
       </div>
+      {busy && scanPhase && (
+        <div style={{ color: steam.textMuted, fontSize: 13 }}>
+          {phaseLabel(scanPhase, locale)}
+        </div>
+      )}
       {error && (
         <div
           style={{
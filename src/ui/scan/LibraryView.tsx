@@ -1,4 +1,5 @@
-import type { LibraryGame } from "../../lib/backend";
+import { call } from "../../lib/backend";
+import type { LibraryGame, LaunchStarted, SteamAction } from "../../lib/backend";
 
 type SortBy = "name" | "playtime" | "installed";
 type Locale = "uk" | "en";
@@ -32,6 +33,19 @@ export default function LibraryView({
     chipBg: "#203448",
   };
 
+  const steamAction = (appid: number, action: SteamAction) =>
+    call("open_steam_action", { appid, action }).catch(() => {});
+
+  const actionBtn = (primary: boolean) => ({
+    fontSize: 12,
+    padding: "4px 12px",
+    borderRadius: 8,
+    border: `1px solid ${primary ? "#1c4f2b" : steam.inputBorder}`,
+    background: primary ? "rgba(89,191,64,.15)" : steam.chipBg,
+    color: primary ? "#59bf40" : steam.text,
+    cursor: "pointer",
+  });
+
   const t = (k: string) => {
     const uk: Record<string, string> = {
       search: "Пошук у бібліотеці…",
@@ -44,6 +58,12 @@ export default function LibraryView({
       time: "Час у грі",
       hoursShort: "год",
       familyFrom: "Family від",
+      play: "Грати",
+      install: "Встановити",
+      store: "Магазин",
+      conf_high: "офлайн: встановлена",
+      conf_medium: "офлайн: запускалась",
+      conf_low: "офлайн: лише ліцензія",
       noResults: "Немає результатів. Запусти сканування або зміни фільтр/пошук.",
     };
     const en: Record<string, string> = {
@@ -57,6 +77,12 @@ export default function LibraryView({
       time: "Playtime",
       hoursShort: "h",
       familyFrom: "Family from",
+      play: "Play",
+      install: "Install",
+      store: "Store",
+      conf_high: "offline: installed",
+      conf_medium: "offline: played",
+      conf_low: "offline: license only",
       noResults: "No results. Run scan or change filter/search.",
     };
     return (locale === "en" ? en : uk)[k] ?? k;
@@ -161,6 +187,9 @@ export default function LibraryView({
               }}
             >
               appid: {g.appid}
+              {g.confidence && g.confidence !== "verified" && (
+                <> · {t(`conf_${g.confidence}`)}</>
+              )}
             </div>
             <div
               style={{
@@ -185,6 +214,27 @@ export default function LibraryView({
                 {t("familyFrom")}: <code>{g.shared_from}</code>
               </div>
             )}
+            <div style={{ display: "flex", gap: 6, marginTop: 8 }}>
+              {g.installed ? (
+                <button
+                  onClick={() =>
+                    call<LaunchStarted>("launch_game", { appid: g.appid }).catch(() => {})
+                  }
+                  style={actionBtn(true)}
+                >
+                  ▶ {t("play")}
+                </button>
+              ) : (
+                <>
+                  <button onClick={() => steamAction(g.appid, "install")} style={actionBtn(true)}>
+                    ⬇ {t("install")}
+                  </button>
+                  <button onClick={() => steamAction(g.appid, "store")} style={actionBtn(false)}>
+                    {t("store")}
+                  </button>
+                </>
+              )}
+            </div>
           </div>
         ))}
       </div>